@@ -0,0 +1,362 @@
+//! Reading build scans in every shape we receive them: echo-server JSON captures (also
+//! compressed, as `.json.gz` or `.json.zst`), raw upload bodies (`.scan` files), bare
+//! gzip event streams, stdin and whole directories.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use base64::Engine as _;
+use clap::ValueEnum;
+use format::compression::{self, Codec};
+use models::BuildScanPayload;
+
+/// Path argument meaning "read from stdin".
+pub const STDIN: &str = "-";
+
+/// Extensions of the scan inputs a directory holds, longest first; stripped from input
+/// names to name the parse results.
+const INPUT_SUFFIXES: &[&str] = &[".json.gz", ".json.zst", ".json", ".scan"];
+
+const UPLOAD_MAGIC: [u8; 2] = [0x28, 0xC5];
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// Detect from the leading bytes
+    #[default]
+    Auto,
+    /// Echo-server capture with a base64 request body
+    Json,
+    /// Raw upload body: outer header followed by the gzip stream
+    Raw,
+    /// Gzip event stream without the outer header
+    Gzip,
+}
+
+/// A scan ready to hand to the parser.
+pub enum ScanBytes {
+    Upload(Vec<u8>),
+    Gzip(Vec<u8>),
+}
+
+impl ScanBytes {
+    pub fn parse(&self, options: lib::ParseOptions) -> Result<BuildScanPayload> {
+        let result = match self {
+            ScanBytes::Upload(bytes) => lib::parse_with(bytes, options),
+            ScanBytes::Gzip(bytes) => lib::parse_gzip_with(bytes, options),
+        };
+        result.context("Failed to parse build scan payload")
+    }
+
+    /// The decompressed event stream, for tools that walk frames themselves.
+    pub fn event_stream(&self) -> Result<Vec<u8>> {
+        let gzip = match self {
+            ScanBytes::Upload(bytes) => {
+                let header = outer_header::OuterHeader::parse(bytes)
+                    .context("Failed to parse outer header")?;
+                &bytes[header.gzip_offset..]
+            }
+            ScanBytes::Gzip(bytes) => bytes.as_slice(),
+        };
+        decompress::Decompressor::decompress(gzip).context("Failed to decompress event stream")
+    }
+}
+
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN
+}
+
+pub fn read_bytes(path: &Path) -> Result<Vec<u8>> {
+    if is_stdin(path) {
+        let mut bytes = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut bytes)
+            .context("Failed to read input from stdin")?;
+        return Ok(bytes);
+    }
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+    if let Some(codec) = compressed_capture(path) {
+        return codec
+            .decode(&bytes)
+            .with_context(|| format!("Failed to decompress capture: {}", path.display()));
+    }
+    Ok(bytes)
+}
+
+/// How the proxy compressed a capture, if it did; a bare event stream is read as is.
+fn compressed_capture(path: &Path) -> Option<Codec> {
+    let name = path.file_name()?.to_str()?;
+    match compression::split(name) {
+        (stem, codec) if stem.ends_with(format::PAYLOAD_SUFFIX) => codec,
+        _ => None,
+    }
+}
+
+pub fn detect(bytes: &[u8]) -> Option<InputFormat> {
+    if bytes.starts_with(&UPLOAD_MAGIC) {
+        Some(InputFormat::Raw)
+    } else if bytes.starts_with(&GZIP_MAGIC) {
+        Some(InputFormat::Gzip)
+    } else if bytes.trim_ascii_start().starts_with(b"{") {
+        Some(InputFormat::Json)
+    } else {
+        None
+    }
+}
+
+pub fn load(path: &Path, format: InputFormat) -> Result<ScanBytes> {
+    let bytes = read_bytes(path)?;
+    from_bytes(bytes, format, path)?
+        .context("Payload request body does not contain a \"base64\" string field")
+}
+
+/// Like `load`, but `None` for files that hold no build scan: JSON captures without a
+/// binary body and, in auto mode, files of no recognized format (READMEs, BUILD files).
+pub fn load_if_scan(path: &Path, format: InputFormat) -> Result<Option<ScanBytes>> {
+    let bytes = read_bytes(path)?;
+    if format == InputFormat::Auto && detect(&bytes).is_none() {
+        return Ok(None);
+    }
+    from_bytes(bytes, format, path)
+}
+
+/// Parses every build scan among `inputs`, expanding directories, for commands that look
+/// across scans. Files without a scan are skipped; scans that fail to parse are reported
+/// on stderr and left out.
+pub fn parse_all(
+    inputs: &[PathBuf],
+    format: InputFormat,
+    options: lib::ParseOptions,
+) -> Result<Vec<(PathBuf, BuildScanPayload)>> {
+    let mut scans = Vec::new();
+    for path in expand(inputs)? {
+        match load_if_scan(&path, format)
+            .and_then(|scan| scan.map(|s| s.parse(options)).transpose())
+        {
+            Ok(Some(payload)) => scans.push((path, payload)),
+            Ok(None) => {}
+            Err(e) => eprintln!("skipping {}: {e:#}", path.display()),
+        }
+    }
+    if scans.is_empty() {
+        bail!("No build scans could be parsed from the given inputs");
+    }
+    Ok(scans)
+}
+
+/// Reads every proxy capture among `inputs`, expanding directories, ordered by capture
+/// time. Files that aren't `format::Payload` JSON (parse results, HAR files, the index)
+/// are skipped with a note on stderr.
+pub fn load_payloads(inputs: &[PathBuf]) -> Result<Vec<format::Payload>> {
+    let mut payloads = Vec::new();
+    for path in expand(inputs)? {
+        let payload = read_bytes(&path).and_then(|bytes| {
+            serde_json::from_slice::<format::Payload>(&bytes).context("not a proxy capture")
+        });
+        match payload {
+            Ok(payload) => payloads.push(payload),
+            Err(e) => eprintln!("skipping {}: {e:#}", path.display()),
+        }
+    }
+    if payloads.is_empty() {
+        bail!("No proxy captures found in the given inputs");
+    }
+    payloads.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(payloads)
+}
+
+/// `inputs` with directories replaced by the files in them.
+fn expand(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            paths.extend(list_dir(input)?);
+        } else {
+            paths.push(input.clone());
+        }
+    }
+    Ok(paths)
+}
+
+/// Interprets `bytes` as `format`. Returns `None` for JSON captures without a binary
+/// body, such as the plugin's token and configuration requests.
+pub fn from_bytes(bytes: Vec<u8>, format: InputFormat, origin: &Path) -> Result<Option<ScanBytes>> {
+    let format = match format {
+        InputFormat::Auto => detect(&bytes).with_context(|| {
+            format!(
+                "Unrecognized input format in {} (expected JSON capture, 0x28C5 upload or gzip)",
+                origin.display()
+            )
+        })?,
+        explicit => explicit,
+    };
+    Ok(match format {
+        InputFormat::Json => upload_from_capture(&bytes)?.map(ScanBytes::Upload),
+        InputFormat::Raw => Some(ScanBytes::Upload(bytes)),
+        InputFormat::Gzip => Some(ScanBytes::Gzip(bytes)),
+        InputFormat::Auto => unreachable!("resolved above"),
+    })
+}
+
+/// Extracts the raw upload bytes (outer header + gzip payload) from a capture, or `None`
+/// when the request body has no `base64` field.
+pub fn upload_from_capture(json: &[u8]) -> Result<Option<Vec<u8>>> {
+    let payload: format::Payload = serde_json::from_slice(json)
+        .context("Failed to parse input as echo-server Payload JSON")?;
+
+    let Some(b64_str) = payload.request.body.get("base64").and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+
+    base64::engine::general_purpose::STANDARD
+        .decode(b64_str)
+        .map(Some)
+        .context("Failed to decode base64 body")
+}
+
+/// Regular files inside `dir` and its subdirectories (the proxy's daily layout), sorted
+/// by path. What the proxy saves beside its `Payload` captures (parse results, HAR
+/// captures and the index) is left out.
+pub fn list_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read input directory: {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.is_file() && !is_capture_sidecar(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    if paths.is_empty() {
+        bail!("No input files in {}", dir.display());
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn is_capture_sidecar(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let name = compression::split(name).0;
+    name == format::INDEX_FILE
+        || format::CAPTURE_SUFFIXES
+            .iter()
+            .filter(|suffix| **suffix != format::PAYLOAD_SUFFIX)
+            .any(|suffix| name.ends_with(suffix))
+}
+
+/// Where the parse result for `path`, found under `dir`, goes in `output`: the same
+/// relative path with the input's extension replaced by `.json`. Only known extensions
+/// are stripped, so capture names keep the milliseconds after their timestamp's dot.
+pub fn output_path(dir: &Path, path: &Path, output: &Path) -> PathBuf {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    let name = relative
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let stem = INPUT_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(&name);
+    output.join(relative.with_file_name(format!("{stem}.json")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats_by_magic() {
+        assert_eq!(detect(&[0x28, 0xC5, 0x00, 0x02]), Some(InputFormat::Raw));
+        assert_eq!(detect(&[0x1f, 0x8b, 0x08]), Some(InputFormat::Gzip));
+        assert_eq!(detect(b"  \n{\"request_id\": 1}"), Some(InputFormat::Json));
+        assert_eq!(detect(b"PK\x03\x04"), None);
+        assert_eq!(detect(&[]), None);
+    }
+
+    #[test]
+    fn lists_daily_subdirectories_without_sidecars() {
+        let dir = std::env::temp_dir().join(format!("input-list-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("2026-10-18")).unwrap();
+        std::fs::write(dir.join("2026-10-18/b.json.gz"), b"").unwrap();
+        std::fs::write(dir.join("a.json"), b"").unwrap();
+        std::fs::write(dir.join("a.build-scan.json"), b"").unwrap();
+        std::fs::write(dir.join("2026-10-18/b.parse-report.json.gz"), b"").unwrap();
+        std::fs::write(dir.join("2026-10-18/c.json.zst"), b"").unwrap();
+        std::fs::write(dir.join("c.build-scan.json.zst"), b"").unwrap();
+        std::fs::write(dir.join("c.har"), b"").unwrap();
+        std::fs::write(dir.join("index.tsv"), b"").unwrap();
+
+        let paths = list_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            paths,
+            [
+                dir.join("2026-10-18/b.json.gz"),
+                dir.join("2026-10-18/c.json.zst"),
+                dir.join("a.json")
+            ]
+        );
+        assert_eq!(compressed_capture(&paths[0]), Some(Codec::Gzip));
+        assert_eq!(compressed_capture(&paths[1]), Some(Codec::Zstd));
+        assert_eq!(compressed_capture(Path::new("events.gz")), None);
+    }
+
+    #[test]
+    fn names_outputs_after_whole_capture_names() {
+        let dir = Path::new("/captures");
+        let out = Path::new("/parsed");
+        let a = output_path(
+            dir,
+            &dir.join("2026-10-18/20261018_120000.123-4acbc8f0.json.gz"),
+            out,
+        );
+        let b = output_path(dir, &dir.join("20261018_120000.456-647fee21.json"), out);
+        assert_eq!(a, out.join("2026-10-18/20261018_120000.123-4acbc8f0.json"));
+        assert_eq!(b, out.join("20261018_120000.456-647fee21.json"));
+        assert_eq!(
+            output_path(dir, &dir.join("upload.json.zst"), out),
+            out.join("upload.json")
+        );
+        assert_eq!(
+            output_path(dir, &dir.join("upload.v2.scan"), out),
+            out.join("upload.v2.json")
+        );
+        assert_eq!(
+            output_path(dir, &dir.join("events.gz"), out),
+            out.join("events.gz.json")
+        );
+    }
+
+    #[test]
+    fn loads_payloads_in_capture_order() {
+        let dir = std::env::temp_dir().join(format!("input-payloads-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let capture = |timestamp: &str| {
+            serde_json::json!({
+                "request_id": timestamp,
+                "timestamp": timestamp,
+                "request": {"method": "GET", "uri": "/", "headers": [], "body": ""},
+                "response": {"status": 200, "headers": [], "body": ""},
+            })
+            .to_string()
+        };
+        std::fs::write(dir.join("a.json"), capture("20261018_120000.000")).unwrap();
+        std::fs::write(dir.join("b.json"), capture("20261018_110000.000")).unwrap();
+        std::fs::write(dir.join("b.parsed.json"), b"{\"events\": []}").unwrap();
+        std::fs::write(dir.join("index.tsv"), b"timestamp\tfile\n").unwrap();
+
+        let payloads = load_payloads(std::slice::from_ref(&dir)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let timestamps: Vec<_> = payloads.iter().map(|p| p.timestamp.as_str()).collect();
+        assert_eq!(timestamps, ["20261018_110000.000", "20261018_120000.000"]);
+    }
+}
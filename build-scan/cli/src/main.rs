@@ -1,8 +1,13 @@
+mod dump;
+mod input;
+mod otlp_http;
+mod report;
+
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
-use base64::Engine as _;
+use anyhow::{Context, Result, bail};
 use clap::{Parser, Subcommand};
+use input::InputFormat;
 
 #[derive(Parser)]
 #[command(name = "build-scan-cli")]
@@ -14,63 +19,492 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Parse an echo-server payload file and extract the build scan data
+    /// Parse a captured build scan and extract the build scan data
     Parse {
-        /// Path to the input echo-server JSON payload file
+        /// Echo-server JSON capture, raw upload body, gzip event stream, `-` for stdin,
+        /// or a directory of captures
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Path to write the parsed build scan JSON output, `-` for stdout; a directory
+        /// when the input is one
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Input format; `auto` detects it from the leading bytes
+        #[arg(short, long, value_enum, default_value_t)]
+        format: InputFormat,
+
+        /// Decode event bodies across all cores
+        #[arg(long)]
+        parallel: bool,
+    },
+    /// Print a human-readable report of a captured build scan
+    Summary {
+        /// Same inputs as `parse`, except directories
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Input format; `auto` detects it from the leading bytes
+        #[arg(short, long, value_enum, default_value_t)]
+        format: InputFormat,
+
+        /// Number of slowest tasks to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+    /// List every frame of the event stream with its decoded event or a hex dump
+    Dump {
+        /// Same inputs as `parse`, except directories
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Input format; `auto` detects it from the leading bytes
+        #[arg(short, long, value_enum, default_value_t)]
+        format: InputFormat,
+
+        /// Only frames with these wire ids (repeatable or comma-separated)
+        #[arg(short, long = "wire-id", value_delimiter = ',')]
+        wire_ids: Vec<u16>,
+
+        /// First ordinal to show
+        #[arg(long)]
+        from: Option<i32>,
+
+        /// Last ordinal to show
+        #[arg(long)]
+        to: Option<i32>,
+
+        /// Hex dump decoded bodies as well
+        #[arg(long)]
+        hex: bool,
+    },
+    /// Export the task and transform timeline as Chrome Trace Event JSON for Perfetto
+    ChromeTrace {
+        /// Same inputs as `parse`, except directories
         #[arg(short, long)]
         input: PathBuf,
 
-        /// Path to write the parsed build scan JSON output
+        /// Path to write the trace JSON, `-` for stdout
         #[arg(short, long)]
         output: PathBuf,
+
+        /// Input format; `auto` detects it from the leading bytes
+        #[arg(short, long, value_enum, default_value_t)]
+        format: InputFormat,
+    },
+    /// Export the build as an OpenTelemetry trace (OTLP/JSON)
+    Otlp {
+        /// Same inputs as `parse`, except directories
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Path to write the OTLP/JSON request, `-` for stdout
+        #[arg(short, long, required_unless_present = "endpoint")]
+        output: Option<PathBuf>,
+
+        /// OTLP/HTTP collector base URL to push the trace to, e.g. http://localhost:4318
+        #[arg(long)]
+        endpoint: Option<String>,
+
+        /// Input format; `auto` detects it from the leading bytes
+        #[arg(short, long, value_enum, default_value_t)]
+        format: InputFormat,
+    },
+    /// Task duration statistics and regressions across many scans
+    Trends {
+        /// Scan files or directories of them, in any format `parse` accepts
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Input format; `auto` detects it per file
+        #[arg(short, long, value_enum, default_value_t)]
+        format: InputFormat,
+
+        /// Percent slowdown of the recent median over the baseline that counts as a
+        /// regression
+        #[arg(long, default_value_t = 20.0)]
+        threshold: f64,
+
+        /// Number of latest runs compared against the earlier ones
+        #[arg(long, default_value_t = 3)]
+        recent: usize,
+
+        /// Leave out tasks with fewer executed runs
+        #[arg(long, default_value_t = 5)]
+        min_runs: usize,
+
+        /// Only list regressed tasks
+        #[arg(long)]
+        regressions_only: bool,
+
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
     },
+    /// Tasks that both succeeded and failed with identical inputs across many scans
+    Flaky {
+        /// Scan files or directories of them, in any format `parse` accepts
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Input format; `auto` detects it per file
+        #[arg(short, long, value_enum, default_value_t)]
+        format: InputFormat,
+
+        /// Example scans listed per outcome
+        #[arg(long, default_value_t = 3)]
+        examples: usize,
+
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Convert proxy captures to an HTTP Archive (HAR 1.2) file
+    Har {
+        /// Capture files or directories of them, compressed or not
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Path to write the HAR file, `-` for stdout
+        #[arg(short, long, default_value = "-")]
+        output: PathBuf,
+    },
+    /// Print the catalog of known event wire ids as JSON
+    WireIds,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Parse { input, output } => run_parse(&input, &output),
+        Commands::Parse {
+            input,
+            output,
+            format,
+            parallel,
+        } => {
+            let options = lib::ParseOptions {
+                parallel_decode: parallel,
+            };
+            if input.is_dir() {
+                run_parse_dir(&input, &output, format, options)
+            } else {
+                run_parse(&input, &output, format, options)
+            }
+        }
+        Commands::Summary { input, format, top } => run_summary(&input, format, top),
+        Commands::Dump {
+            input,
+            format,
+            wire_ids,
+            from,
+            to,
+            hex,
+        } => run_dump(
+            &input,
+            format,
+            &dump::FrameFilter { wire_ids, from, to },
+            hex,
+        ),
+        Commands::ChromeTrace {
+            input,
+            output,
+            format,
+        } => run_chrome_trace(&input, &output, format),
+        Commands::Otlp {
+            input,
+            output,
+            endpoint,
+            format,
+        } => run_otlp(&input, output.as_deref(), endpoint.as_deref(), format),
+        Commands::Trends {
+            inputs,
+            format,
+            threshold,
+            recent,
+            min_runs,
+            regressions_only,
+            json,
+        } => run_trends(
+            &inputs,
+            format,
+            &trends::TrendOptions {
+                regression_threshold: threshold / 100.0,
+                recent_runs: recent,
+                min_runs,
+            },
+            regressions_only,
+            json,
+        ),
+        Commands::Flaky {
+            inputs,
+            format,
+            examples,
+            json,
+        } => run_flaky(&inputs, format, examples, json),
+        Commands::Har { inputs, output } => run_har(&inputs, &output),
+        Commands::WireIds => run_wire_ids(),
     }
 }
 
-fn run_parse(input: &Path, output: &Path) -> Result<()> {
-    // 1. Read the input file
-    let contents = std::fs::read_to_string(input)
-        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
-
-    // 2. Deserialize into echo-server Payload
-    let payload: format::Payload = serde_json::from_str(&contents)
-        .context("Failed to parse input as echo-server Payload JSON")?;
+fn run_parse(
+    input: &Path,
+    output: &Path,
+    format: InputFormat,
+    options: lib::ParseOptions,
+) -> Result<()> {
+    let scan = input::load(input, format)?;
+    let build_scan = scan.parse(options)?;
+    write_build_scan(&build_scan, output)
+}
 
-    // 3. Extract base64 body
-    let b64_str = payload
-        .request
-        .body
-        .get("base64")
-        .and_then(|v| v.as_str())
-        .context("Payload request body does not contain a \"base64\" string field")?;
+/// Parses every capture in `input` into `output/<stem>.json`. JSON captures without a
+/// binary body and, in auto mode, unrecognized files are skipped; failures are reported
+/// per file and fail the run at the end.
+fn run_parse_dir(
+    input: &Path,
+    output: &Path,
+    format: InputFormat,
+    options: lib::ParseOptions,
+) -> Result<()> {
+    let paths = input::list_dir(input)?;
+    std::fs::create_dir_all(output)
+        .with_context(|| format!("Failed to create output directory: {}", output.display()))?;
 
-    // 4. Decode base64 — these are the full raw bytes (outer header + gzip payload)
-    let raw_bytes = base64::engine::general_purpose::STANDARD
-        .decode(b64_str)
-        .context("Failed to decode base64 body")?;
+    let mut failed = 0;
+    for path in &paths {
+        let result = input::load_if_scan(path, format).and_then(|scan| match scan {
+            Some(scan) => {
+                let target = input::output_path(input, path, output);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent).with_context(|| {
+                        format!("Failed to create output directory: {}", parent.display())
+                    })?;
+                }
+                write_build_scan(&scan.parse(options)?, &target).map(|()| true)
+            }
+            None => Ok(false),
+        });
+        match result {
+            Ok(true) => {}
+            Ok(false) => eprintln!("skipped {}: not a build scan upload", path.display()),
+            Err(e) => {
+                failed += 1;
+                eprintln!("failed {}: {e:#}", path.display());
+            }
+        }
+    }
 
-    // 5. Parse build scan (handles outer header + decompression + framing + decode internally)
-    let build_scan = lib::parse(&raw_bytes).context("Failed to parse build scan payload")?;
+    if failed > 0 {
+        bail!("{failed} of {} inputs failed to parse", paths.len());
+    }
+    Ok(())
+}
 
-    // 6. Serialize to JSON
+fn write_build_scan(build_scan: &models::BuildScanPayload, output: &Path) -> Result<()> {
     let json_output =
-        serde_json::to_string_pretty(&build_scan).context("Failed to serialize build scan")?;
+        serde_json::to_string_pretty(build_scan).context("Failed to serialize build scan")?;
+
+    write_output(output, &json_output)?;
+    if !input::is_stdin(output) {
+        eprintln!("Parsed build scan written to {}", output.display());
+    }
+    for raw in &build_scan.raw_events {
+        eprintln!(
+            "  undecoded: {} x{}",
+            wire_ids::describe(raw.wire_id),
+            raw.count
+        );
+    }
+    Ok(())
+}
+
+fn run_summary(input: &Path, format: InputFormat, top: usize) -> Result<()> {
+    let build_scan = input::load(input, format)?.parse(lib::ParseOptions::default())?;
+    print!("{}", report::render(&build_scan, top));
+    Ok(())
+}
+
+fn run_dump(
+    input: &Path,
+    format: InputFormat,
+    filter: &dump::FrameFilter,
+    hex: bool,
+) -> Result<()> {
+    let stream = input::load(input, format)?.event_stream()?;
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    dump::dump(&stream, filter, hex, &mut out)?;
+    std::io::Write::flush(&mut out)?;
+    Ok(())
+}
+
+fn run_chrome_trace(input: &Path, output: &Path, format: InputFormat) -> Result<()> {
+    let build_scan = input::load(input, format)?.parse(lib::ParseOptions::default())?;
+    let trace = chrome_trace::export(&build_scan);
+    let json = serde_json::to_string(&trace).context("Failed to serialize trace")?;
+    write_output(output, &json)?;
+    if !input::is_stdin(output) {
+        eprintln!("Chrome trace written to {}", output.display());
+    }
+    Ok(())
+}
+
+fn run_otlp(
+    input: &Path,
+    output: Option<&Path>,
+    endpoint: Option<&str>,
+    format: InputFormat,
+) -> Result<()> {
+    let build_scan = input::load(input, format)?.parse(lib::ParseOptions::default())?;
+    let trace_id = *uuid::Uuid::new_v4().as_bytes();
+    let request = otlp::export(&build_scan, trace_id)
+        .context("Build scan has no timed build, task or transform to export")?;
+    let json = serde_json::to_string(&request).context("Failed to serialize OTLP request")?;
 
-    // 7. Write output
-    std::fs::write(output, &json_output)
-        .with_context(|| format!("Failed to write output file: {}", output.display()))?;
+    if let Some(output) = output {
+        write_output(output, &json)?;
+        if !input::is_stdin(output) {
+            eprintln!("OTLP trace written to {}", output.display());
+        }
+    }
+    if let Some(endpoint) = endpoint {
+        otlp_http::push(endpoint, json.into_bytes())?;
+        eprintln!("OTLP trace pushed to {}", otlp_http::traces_url(endpoint));
+    }
+    Ok(())
+}
+
+fn run_trends(
+    inputs: &[PathBuf],
+    format: InputFormat,
+    options: &trends::TrendOptions,
+    regressions_only: bool,
+    json: bool,
+) -> Result<()> {
+    let scans = input::parse_all(inputs, format, lib::ParseOptions::default())?;
+    let named: Vec<(String, &models::BuildScanPayload)> = scans
+        .iter()
+        .map(|(path, payload)| (scan_name(path), payload))
+        .collect();
+    let mut trends = trends::analyze(&named, options);
+    if regressions_only {
+        trends.retain(|t| t.regression.is_some());
+    }
+
+    if json {
+        let json = serde_json::to_string_pretty(&trends).context("Failed to serialize trends")?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    println!(
+        "{} scans, {} tasks with at least {} executed runs",
+        scans.len(),
+        trends.len(),
+        options.min_runs
+    );
+    println!(
+        "{:<48} {:>5} {:>10} {:>10} {:>12} {:>8}  REGRESSION",
+        "TASK", "RUNS", "P50", "P90", "TREND/SCAN", "OUTLIERS"
+    );
+    for trend in &trends {
+        let regression = trend
+            .regression
+            .as_ref()
+            .map(|r| {
+                format!(
+                    "+{:.0}% ({} -> {})",
+                    r.change * 100.0,
+                    report::format_duration(r.baseline_p50_ms),
+                    report::format_duration(r.recent_p50_ms)
+                )
+            })
+            .unwrap_or_default();
+        println!(
+            "{:<48} {:>5} {:>10} {:>10} {:>+10.0}ms {:>8}  {regression}",
+            trend.identity_path(),
+            trend.runs,
+            report::format_duration(trend.p50_ms),
+            report::format_duration(trend.p90_ms),
+            trend.slope_ms_per_scan,
+            trend.outliers.len()
+        );
+    }
+    Ok(())
+}
+
+fn run_flaky(inputs: &[PathBuf], format: InputFormat, examples: usize, json: bool) -> Result<()> {
+    let scans = input::parse_all(inputs, format, lib::ParseOptions::default())?;
+    let named: Vec<(String, &models::BuildScanPayload)> = scans
+        .iter()
+        .map(|(path, payload)| (scan_name(path), payload))
+        .collect();
+    let flaky = flaky::detect(&named, examples);
+
+    if json {
+        let json =
+            serde_json::to_string_pretty(&flaky).context("Failed to serialize flaky tasks")?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    println!(
+        "{} scans, {} flaky tasks with identical inputs",
+        scans.len(),
+        flaky.len()
+    );
+    for task in &flaky {
+        println!(
+            "{}  passed {}x, failed {}x, flipped {}x  inputs {}",
+            task.task_path, task.successes, task.failures, task.flips, task.inputs_hash
+        );
+        println!("    passed in: {}", task.passing_scans.join(", "));
+        println!("    failed in: {}", task.failing_scans.join(", "));
+    }
+    Ok(())
+}
+
+/// File name without extension, used to identify a scan in cross-scan reports.
+fn scan_name(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn run_har(inputs: &[PathBuf], output: &Path) -> Result<()> {
+    let payloads = input::load_payloads(inputs)?;
+    let har = format::har::Har::from_payloads(&payloads);
+    let json = serde_json::to_string_pretty(&har).context("Failed to serialize HAR")?;
+    write_output(output, &json)?;
+    if !input::is_stdin(output) {
+        eprintln!(
+            "{} exchanges written to {}",
+            payloads.len(),
+            output.display()
+        );
+    }
+    Ok(())
+}
 
-    println!("Parsed build scan written to {}", output.display());
+fn run_wire_ids() -> Result<()> {
+    let json = serde_json::to_string_pretty(wire_ids::CATALOG)
+        .context("Failed to serialize wire id catalog")?;
+    println!("{json}");
     Ok(())
 }
 
+/// Writes `contents` to `output`, or to stdout when `output` is `-`.
+fn write_output(output: &Path, contents: &str) -> Result<()> {
+    if input::is_stdin(output) {
+        println!("{contents}");
+        return Ok(());
+    }
+    std::fs::write(output, contents)
+        .with_context(|| format!("Failed to write output file: {}", output.display()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,28 +518,181 @@ mod tests {
         std::env::temp_dir().join(format!("cli_test_{name}_{ts}"))
     }
 
-    #[test]
-    fn error_when_base64_field_missing() {
-        let payload = serde_json::json!({
-            "request_id": "test-002",
+    /// A gzip member holding an empty event stream.
+    const EMPTY_GZIP: [u8; 20] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x03, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// Outer header for GRADLE 9.3.1 / plugin 4.3.2, followed by `EMPTY_GZIP`.
+    fn raw_upload() -> Vec<u8> {
+        let mut raw = vec![
+            0x28, 0xc5, 0x00, 0x02, 0x00, 0x16, 0x00, 0x06, 0x47, 0x52, 0x41, 0x44, 0x4c, 0x45,
+            0x00, 0x05, 0x39, 0x2e, 0x33, 0x2e, 0x31, 0x00, 0x05, 0x34, 0x2e, 0x33, 0x2e, 0x32,
+        ];
+        raw.extend_from_slice(&EMPTY_GZIP);
+        raw
+    }
+
+    fn capture(body: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "request_id": "test-001",
             "timestamp": "2025-01-01T00:00:00Z",
             "request": {
                 "method": "POST",
                 "uri": "/scan",
                 "headers": [],
-                "body": "just a plain string, not an object"
+                "body": body
             },
             "response": {
                 "status": 200
             }
-        });
+        })
+    }
+
+    #[test]
+    fn parses_raw_and_gzip_inputs() {
+        for (name, bytes) in [("raw", raw_upload()), ("gzip", EMPTY_GZIP.to_vec())] {
+            let input_path = temp_path(&format!("{name}_in"));
+            let output_path = temp_path(&format!("{name}_out.json"));
+            std::fs::write(&input_path, &bytes).unwrap();
+
+            run_parse(
+                &input_path,
+                &output_path,
+                InputFormat::Auto,
+                lib::ParseOptions::default(),
+            )
+            .unwrap();
+            let json: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(&output_path).unwrap()).unwrap();
+            assert!(json.is_object(), "{name}: {json}");
+
+            let _ = std::fs::remove_file(&input_path);
+            let _ = std::fs::remove_file(&output_path);
+        }
+    }
+
+    #[test]
+    fn explicit_format_overrides_detection() {
+        let input_path = temp_path("forced_raw_in");
+        let output_path = temp_path("forced_raw_out.json");
+        std::fs::write(&input_path, EMPTY_GZIP).unwrap();
+
+        let result = run_parse(
+            &input_path,
+            &output_path,
+            InputFormat::Raw,
+            lib::ParseOptions::default(),
+        );
+        let err_msg = format!("{:#}", result.unwrap_err());
+        assert!(err_msg.contains("magic"), "got: {err_msg}");
+
+        let _ = std::fs::remove_file(&input_path);
+    }
+
+    #[test]
+    fn parses_directory_skipping_bodiless_captures() {
+        use base64::Engine as _;
+
+        let input_dir = temp_path("dir_in");
+        let output_dir = temp_path("dir_out");
+        std::fs::create_dir_all(&input_dir).unwrap();
+        let b64 = base64::engine::general_purpose::STANDARD.encode(raw_upload());
+        let with_body = capture(serde_json::json!({ "base64": b64 }));
+        let without_body = capture(serde_json::json!("token request"));
+        std::fs::write(input_dir.join("a.json"), with_body.to_string()).unwrap();
+        std::fs::write(input_dir.join("b.json"), without_body.to_string()).unwrap();
+        std::fs::write(input_dir.join("c.scan"), raw_upload()).unwrap();
+
+        run_parse_dir(
+            &input_dir,
+            &output_dir,
+            InputFormat::Auto,
+            lib::ParseOptions::default(),
+        )
+        .unwrap();
+        assert!(output_dir.join("a.json").exists());
+        assert!(!output_dir.join("b.json").exists());
+        assert!(output_dir.join("c.json").exists());
+
+        std::fs::write(input_dir.join("README"), b"notes").unwrap();
+        std::fs::write(input_dir.join("d.scan"), [0x28, 0xc5, 0x00, 0x02]).unwrap();
+        let err = run_parse_dir(
+            &input_dir,
+            &output_dir,
+            InputFormat::Auto,
+            lib::ParseOptions::default(),
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("1 of 5"), "got: {err:#}");
+
+        let _ = std::fs::remove_dir_all(&input_dir);
+        let _ = std::fs::remove_dir_all(&output_dir);
+    }
+
+    #[test]
+    fn parses_same_second_captures_to_separate_outputs() {
+        use base64::Engine as _;
+
+        let input_dir = temp_path("same_second_in");
+        let output_dir = temp_path("same_second_out");
+        std::fs::create_dir_all(input_dir.join("2026-10-18")).unwrap();
+        let b64 = base64::engine::general_purpose::STANDARD.encode(raw_upload());
+        let with_body = capture(serde_json::json!({ "base64": b64 })).to_string();
+        for name in [
+            "2026-10-18/20261018_120000.123-4acbc8f0.json",
+            "2026-10-18/20261018_120000.456-647fee21.json",
+        ] {
+            std::fs::write(input_dir.join(name), &with_body).unwrap();
+        }
+        // What the proxy writes beside the captures isn't input.
+        std::fs::write(
+            input_dir.join("2026-10-18/20261018_120000.123-4acbc8f0.build-scan.json"),
+            b"{\"tasks\": []}",
+        )
+        .unwrap();
+        std::fs::write(input_dir.join("index.tsv"), b"timestamp\tfile\n").unwrap();
+
+        run_parse_dir(
+            &input_dir,
+            &output_dir,
+            InputFormat::Auto,
+            lib::ParseOptions::default(),
+        )
+        .unwrap();
+        let mut outputs: Vec<_> = std::fs::read_dir(output_dir.join("2026-10-18"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        outputs.sort();
+        assert_eq!(
+            outputs,
+            [
+                "20261018_120000.123-4acbc8f0.json",
+                "20261018_120000.456-647fee21.json"
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(&input_dir);
+        let _ = std::fs::remove_dir_all(&output_dir);
+    }
+
+    #[test]
+    fn error_when_base64_field_missing() {
+        let payload = capture(serde_json::json!("just a plain string, not an object"));
 
         let input_path = temp_path("missing_b64_in.json");
         let output_path = temp_path("missing_b64_out.json");
 
         std::fs::write(&input_path, serde_json::to_string_pretty(&payload).unwrap()).unwrap();
 
-        let result = run_parse(&input_path, &output_path);
+        let result = run_parse(
+            &input_path,
+            &output_path,
+            InputFormat::Auto,
+            lib::ParseOptions::default(),
+        );
         assert!(result.is_err());
 
         let err_msg = format!("{:#}", result.unwrap_err());
@@ -132,7 +719,12 @@ mod tests {
         // Ensure input does not exist
         let _ = std::fs::remove_file(&input_path);
 
-        let result = run_parse(&input_path, &output_path);
+        let result = run_parse(
+            &input_path,
+            &output_path,
+            InputFormat::Auto,
+            lib::ParseOptions::default(),
+        );
         assert!(result.is_err());
 
         let err_msg = format!("{:#}", result.unwrap_err());
@@ -0,0 +1,119 @@
+//! OTLP/HTTP push of exported traces.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+
+const TRACES_PATH: &str = "/v1/traces";
+
+/// `endpoint` is a collector base URL as in `OTEL_EXPORTER_OTLP_ENDPOINT`; the traces
+/// path is appended unless it is already there.
+pub fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with(TRACES_PATH) {
+        endpoint.to_string()
+    } else {
+        format!("{endpoint}{TRACES_PATH}")
+    }
+}
+
+/// POSTs an OTLP/JSON `ExportTraceServiceRequest` and fails on any non-2xx response.
+pub fn push(endpoint: &str, body: Vec<u8>) -> Result<()> {
+    let url = traces_url(endpoint);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start async runtime")?;
+
+    runtime.block_on(async {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30))
+            .build()
+            .context("Failed to create HTTP client")?;
+        let response = client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send traces to {url}"))?;
+        let status = response.status();
+        if !status.is_success() {
+            let detail = response.text().await.unwrap_or_default();
+            bail!("Collector at {url} rejected traces: {status} {detail}");
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Accepts one request and returns its request line and body.
+    fn collector(status: &'static str) -> (String, std::thread::JoinHandle<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let mut stream = reader.into_inner();
+            write!(stream, "HTTP/1.1 {status}\r\ncontent-length: 0\r\n\r\n").unwrap();
+            (request_line, body)
+        });
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn appends_traces_path() {
+        assert_eq!(
+            traces_url("http://collector:4318/"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url("http://collector:4318/v1/traces"),
+            "http://collector:4318/v1/traces"
+        );
+    }
+
+    #[test]
+    fn pushes_to_local_collector() {
+        let (endpoint, handle) = collector("200 OK");
+        push(&endpoint, br#"{"resourceSpans":[]}"#.to_vec()).unwrap();
+
+        let (request_line, body) = handle.join().unwrap();
+        assert!(
+            request_line.starts_with("POST /v1/traces "),
+            "{request_line}"
+        );
+        assert_eq!(body, br#"{"resourceSpans":[]}"#);
+    }
+
+    #[test]
+    fn reports_rejection() {
+        let (endpoint, handle) = collector("400 Bad Request");
+        let err = push(&endpoint, b"{}".to_vec()).unwrap_err();
+        handle.join().unwrap();
+        assert!(format!("{err:#}").contains("400"), "{err:#}");
+    }
+}
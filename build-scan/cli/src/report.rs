@@ -0,0 +1,217 @@
+//! Plain-text rendering of a parsed build scan for the `summary` command.
+
+use std::fmt::Write as _;
+
+use models::{BuildOutcome, BuildScanPayload, EnvironmentData};
+
+pub fn render(payload: &BuildScanPayload, slowest: usize) -> String {
+    let summary = summary::summarize(payload, slowest);
+    let mut out = String::new();
+
+    if let Some(header) = &payload.header {
+        let _ = writeln!(
+            out,
+            "{} {} (plugin {}, upload format v{})",
+            title_case(&header.tool_type),
+            header.tool_version,
+            header.plugin_version,
+            header.version
+        );
+    }
+
+    match &payload.build {
+        Some(build) => {
+            let outcome = match build.outcome {
+                Some(BuildOutcome::Success) => "SUCCESS",
+                Some(BuildOutcome::Failed) => "FAILED",
+                None => "UNFINISHED",
+            };
+            match build.duration_ms {
+                Some(ms) => {
+                    let _ = writeln!(out, "Outcome:     {outcome} in {}", format_duration(ms));
+                }
+                None => {
+                    let _ = writeln!(out, "Outcome:     {outcome}");
+                }
+            }
+            if !build.requested_tasks.is_empty() {
+                let _ = writeln!(out, "Requested:   {}", build.requested_tasks.join(" "));
+            }
+            if !build.excluded_tasks.is_empty() {
+                let _ = writeln!(out, "Excluded:    {}", build.excluded_tasks.join(" "));
+            }
+        }
+        None => {
+            let _ = writeln!(out, "Outcome:     unknown (no build events)");
+        }
+    }
+
+    if let Some(environment) = &payload.environment {
+        render_environment(&mut out, environment);
+    }
+
+    let _ = writeln!(out, "\nTasks: {}", summary.task_count);
+    for (outcome, count) in &summary.outcomes {
+        let _ = writeln!(out, "  {:<26}{count:>6}", format!("{outcome:?}"));
+    }
+    if summary.without_outcome > 0 {
+        let _ = writeln!(
+            out,
+            "  {:<26}{:>6}",
+            "(no outcome)", summary.without_outcome
+        );
+    }
+
+    if !summary.slowest.is_empty() {
+        let _ = writeln!(out, "\nSlowest tasks:");
+        for task in &summary.slowest {
+            let outcome = task.outcome.map(|o| format!("{o:?}")).unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "  {:>10}  {}  {outcome}",
+                format_duration(task.duration_ms.unwrap_or_default()),
+                task.task_path
+            );
+        }
+    }
+
+    let cache = summary.cache;
+    let _ = writeln!(out, "\nBuild cache:");
+    let _ = writeln!(out, "  hits          {:>6}", cache.hits);
+    let _ = writeln!(out, "  misses        {:>6}", cache.misses);
+    let _ = writeln!(out, "  not cacheable {:>6}", cache.not_cacheable);
+    let _ = writeln!(out, "  up to date    {:>6}", cache.up_to_date);
+    if let Some(rate) = cache.hit_rate() {
+        let _ = writeln!(out, "  hit rate      {:>5.1}%", rate * 100.0);
+    }
+
+    out
+}
+
+fn render_environment(out: &mut String, env: &EnvironmentData) {
+    let _ = writeln!(out, "\nEnvironment:");
+    if env.os_name.is_some() || env.os_family.is_some() {
+        let name = env.os_name.as_deref().or(env.os_family.as_deref());
+        let _ = writeln!(
+            out,
+            "  OS:          {}{}{}",
+            name.unwrap_or_default(),
+            env.os_version
+                .as_deref()
+                .map(|v| format!(" {v}"))
+                .unwrap_or_default(),
+            env.os_arch
+                .as_deref()
+                .map(|a| format!(" ({a})"))
+                .unwrap_or_default()
+        );
+    }
+    if let Some(version) = &env.jvm_version {
+        let details: Vec<&str> = [env.jvm_vendor.as_deref(), env.jvm_vm_name.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        if details.is_empty() {
+            let _ = writeln!(out, "  JVM:         {version}");
+        } else {
+            let _ = writeln!(out, "  JVM:         {version} ({})", details.join(", "));
+        }
+    }
+    if let Some(cpus) = env.num_processors {
+        let _ = writeln!(out, "  CPUs:        {cpus}");
+    }
+    if let Some(workers) = env.max_workers {
+        let _ = writeln!(out, "  Max workers: {workers}");
+    }
+    if let Some(host) = &env.hostname {
+        let _ = writeln!(out, "  Host:        {host}");
+    }
+}
+
+/// `850ms`, `12.345s`, `3m 04.2s`.
+pub fn format_duration(ms: i64) -> String {
+    if ms < 1000 {
+        format!("{ms}ms")
+    } else if ms < 60_000 {
+        format!("{}.{:03}s", ms / 1000, ms % 1000)
+    } else {
+        let minutes = ms / 60_000;
+        let tenths = (ms % 60_000) / 100;
+        format!("{minutes}m {:02}.{}s", tenths / 10, tenths % 10)
+    }
+}
+
+/// `GRADLE` -> `Gradle`.
+fn title_case(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::{BuildData, HeaderData, Task, TaskOutcome};
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(850), "850ms");
+        assert_eq!(format_duration(12_345), "12.345s");
+        assert_eq!(format_duration(184_250), "3m 04.2s");
+    }
+
+    #[test]
+    fn renders_build_and_tasks() {
+        let payload = BuildScanPayload {
+            header: Some(HeaderData {
+                version: 2,
+                tool_type: "GRADLE".into(),
+                tool_version: "9.3.1".into(),
+                plugin_version: "4.3.2".into(),
+            }),
+            build: Some(BuildData {
+                duration_ms: Some(5_000),
+                outcome: Some(BuildOutcome::Failed),
+                failure_id: Some(1),
+                requested_tasks: vec!["build".into()],
+                ..Default::default()
+            }),
+            environment: Some(EnvironmentData {
+                num_processors: Some(8),
+                max_workers: Some(4),
+                ..Default::default()
+            }),
+            tasks: vec![Task {
+                id: 1,
+                build_path: ":".into(),
+                task_path: ":app:compileJava".into(),
+                class_name: None,
+                outcome: Some(TaskOutcome::FromCache),
+                cacheable: Some(true),
+                caching_disabled_reason: None,
+                caching_disabled_explanation: None,
+                origin_build_cache_key: None,
+                actionable: None,
+                started_at: Some(0),
+                finished_at: Some(40),
+                duration_ms: Some(40),
+                inputs: None,
+            }],
+            ..Default::default()
+        };
+
+        let text = render(&payload, 5);
+        assert!(text.starts_with("Gradle 9.3.1 (plugin 4.3.2, upload format v2)"));
+        assert!(text.contains("Outcome:     FAILED in 5.000s"));
+        assert!(text.contains("Requested:   build"));
+        assert!(text.contains("Max workers: 4"));
+        assert!(text.contains("FromCache"));
+        assert!(text.contains("40ms  :app:compileJava  FromCache"));
+        assert!(text.contains("hit rate      100.0%"));
+    }
+}
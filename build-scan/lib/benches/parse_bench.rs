@@ -0,0 +1,149 @@
+//! Parser benchmarks over the captured payloads in `captured-output/payloads`.
+//!
+//! Run with `bazel run //build-scan/lib/benches:parse_bench -- --bench`. Under
+//! `bazel test` criterion runs each benchmark once as a smoke test.
+
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+
+struct Capture {
+    name: String,
+    raw: Vec<u8>,
+}
+
+fn payload_dir() -> Option<PathBuf> {
+    if let Ok(srcdir) = std::env::var("TEST_SRCDIR") {
+        let bazel_path = Path::new(&srcdir)
+            .join("_main")
+            .join("captured-output")
+            .join("payloads");
+        if bazel_path.is_dir() {
+            return Some(bazel_path);
+        }
+    }
+
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .ancestors()
+        .map(|ancestor| ancestor.join("captured-output").join("payloads"))
+        .find(|candidate| candidate.is_dir())
+}
+
+/// Every captured request that carries a binary build scan body.
+fn load_captures() -> Vec<Capture> {
+    let Some(dir) = payload_dir() else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .expect("payload directory must be readable")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let contents = std::fs::read_to_string(&path).ok()?;
+            let json: serde_json::Value = serde_json::from_str(&contents).ok()?;
+            let b64 = json["request"]["body"]["base64"].as_str()?;
+            let raw = base64::engine::general_purpose::STANDARD.decode(b64).ok()?;
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            Some(Capture { name, raw })
+        })
+        .collect()
+}
+
+fn decompressed(capture: &Capture) -> Vec<u8> {
+    let header = outer_header::OuterHeader::parse(&capture.raw).expect("valid outer header");
+    decompress::Decompressor::decompress(&capture.raw[header.gzip_offset..])
+        .expect("valid gzip stream")
+}
+
+fn bench_decompress(c: &mut Criterion, captures: &[Capture]) {
+    let mut group = c.benchmark_group("decompress");
+    for capture in captures {
+        group.throughput(Throughput::Bytes(capture.raw.len() as u64));
+        group.bench_function(&capture.name, |b| {
+            b.iter(|| decompressed(black_box(capture)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_frames(c: &mut Criterion, captures: &[Capture]) {
+    let mut group = c.benchmark_group("frames");
+    for capture in captures {
+        let stream = decompressed(capture);
+        group.throughput(Throughput::Bytes(stream.len() as u64));
+        group.bench_function(&capture.name, |b| {
+            b.iter(|| {
+                framing::EventFrameReader::new(black_box(&stream))
+                    .filter_map(Result::ok)
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Decodes every frame body, counting failures instead of stopping at the first one so
+/// that decoders still being reverse-engineered don't hide the cost of the rest.
+fn bench_decode(c: &mut Criterion, captures: &[Capture]) {
+    let registry = events::DecoderRegistry::new();
+    let mut group = c.benchmark_group("decode");
+    for capture in captures {
+        let stream = decompressed(capture);
+        let frames: Vec<_> = framing::EventFrameReader::new(&stream)
+            .filter_map(Result::ok)
+            .collect();
+        group.throughput(Throughput::Elements(frames.len() as u64));
+        group.bench_function(&capture.name, |b| {
+            b.iter(|| {
+                frames
+                    .iter()
+                    .map(|frame| registry.decode(frame.wire_id, black_box(frame.body)))
+                    .filter(Result::is_err)
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_parse(c: &mut Criterion, captures: &[Capture]) {
+    // A capture that stops parsing is a regression, not a capture to leave out.
+    assert!(!captures.is_empty(), "no build scan captures to parse");
+    for capture in captures {
+        if let Err(e) = lib::parse(&capture.raw) {
+            panic!("{} no longer parses: {e}", capture.name);
+        }
+    }
+    let mut group = c.benchmark_group("parse");
+    for capture in captures {
+        group.throughput(Throughput::Bytes(capture.raw.len() as u64));
+        group.bench_function(format!("{}/sequential", capture.name), |b| {
+            b.iter(|| lib::parse(black_box(&capture.raw)))
+        });
+        let parallel = lib::ParseOptions {
+            parallel_decode: true,
+        };
+        group.bench_function(format!("{}/parallel", capture.name), |b| {
+            b.iter(|| lib::parse_with(black_box(&capture.raw), parallel))
+        });
+    }
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    let captures = load_captures();
+    bench_decompress(c, &captures);
+    bench_frames(c, &captures);
+    bench_decode(c, &captures);
+    bench_parse(c, &captures);
+}
+
+criterion_group!(parse_benches, benches);
+criterion_main!(parse_benches);
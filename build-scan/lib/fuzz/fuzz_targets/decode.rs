@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The first two bytes pick a catalogued wire id so every decoder gets exercised; the
+// rest is the event body.
+fuzz_target!(|data: &[u8]| {
+    let Some((selector, body)) = data.split_first_chunk::<2>() else {
+        return;
+    };
+    let index = u16::from_le_bytes(*selector) as usize % wire_ids::CATALOG.len();
+    let registry = events::DecoderRegistry::new();
+    let _ = registry.decode(wire_ids::CATALOG[index].wire_id, body);
+});
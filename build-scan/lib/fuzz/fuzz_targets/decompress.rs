@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Keep the cap small so gzip bombs are rejected quickly instead of exhausting the
+// fuzzer's memory budget.
+const LIMIT: usize = 1024 * 1024;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decompress::Decompressor::decompress_with_limit(data, LIMIT);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for frame in framing::EventFrameReader::new(data) {
+        if frame.is_err() {
+            break;
+        }
+    }
+});
@@ -1,12 +1,13 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use events::DecodedEvent;
 use framing::FramedEvent;
 use models::{BuildScanPayload, RawEventSummary, Task, TaskOutcome};
 
-pub fn assemble(events: Vec<(FramedEvent, DecodedEvent)>) -> BuildScanPayload {
-    let mut identities: HashMap<i64, (String, String)> = HashMap::new();
-    let mut started: HashMap<i64, (String, Option<String>, i64)> = HashMap::new();
+pub fn assemble(events: Vec<(FramedEvent<'_>, DecodedEvent<'_>)>) -> BuildScanPayload {
+    let mut identities: HashMap<i64, (Arc<str>, Arc<str>)> = HashMap::new();
+    let mut started: HashMap<i64, StartedInfo> = HashMap::new();
     let mut finished: HashMap<i64, FinishedInfo> = HashMap::new();
     let mut raw_counts: HashMap<u16, usize> = HashMap::new();
     let mut property_names_map: HashMap<i64, events::TaskInputsPropertyNamesEvent> = HashMap::new();
@@ -22,9 +23,19 @@ pub fn assemble(events: Vec<(FramedEvent, DecodedEvent)>) -> BuildScanPayload {
         HashMap::new();
     let mut planned_nodes: Vec<events::PlannedNodeEvent> = Vec::new();
     let mut transform_requests: Vec<events::TransformExecutionRequestEvent> = Vec::new();
+    let mut transform_identifications: HashMap<i64, events::TransformIdentificationEvent> =
+        HashMap::new();
+    // Execution id -> start timestamp, in stream order of first start.
+    let mut transform_started: Vec<(i64, i64)> = Vec::new();
+    let mut transform_finished: HashMap<i64, (events::TransformExecutionFinishedEvent, i64)> =
+        HashMap::new();
     let mut task_registration_summary: Option<events::TaskRegistrationSummaryEvent> = None;
     let mut basic_memory_stats: Option<events::BasicMemoryStatsEvent> = None;
     let mut resource_usage: Option<events::ResourceUsageEvent> = None;
+    let mut build = models::BuildData::default();
+    let mut environment = models::EnvironmentData::default();
+    let mut has_build = false;
+    let mut has_environment = false;
 
     for (frame, decoded) in &events {
         match decoded {
@@ -108,23 +119,80 @@ pub fn assemble(events: Vec<(FramedEvent, DecodedEvent)>) -> BuildScanPayload {
             }
             // Decoded for protocol coverage; not yet consumed by assembly.
             DecodedEvent::JavaToolchainUsage(_) => {}
-            DecodedEvent::TransformExecutionStarted(_) => {}
-            DecodedEvent::TransformIdentification(_) => {}
-            DecodedEvent::TransformExecutionFinished(_) => {}
+            DecodedEvent::TransformExecutionStarted(e) => {
+                transform_started.push((e.id, frame.timestamp));
+            }
+            DecodedEvent::TransformIdentification(e) => {
+                transform_identifications.insert(e.id, e.clone());
+            }
+            DecodedEvent::TransformExecutionFinished(e) => {
+                transform_finished.insert(e.id, (e.clone(), frame.timestamp));
+            }
             DecodedEvent::OutputStyledText(_) => {}
-            DecodedEvent::BuildStarted => {}
-            DecodedEvent::BuildAgent(_) => {}
-            DecodedEvent::BuildRequestedTasks(_) => {}
-            DecodedEvent::BuildFinished(_) => {}
-            DecodedEvent::BuildModes(_) => {}
+            DecodedEvent::BuildStarted => {
+                has_build = true;
+                build.started_at = Some(frame.timestamp);
+            }
+            DecodedEvent::BuildRequestedTasks(e) => {
+                has_build = true;
+                build.requested_tasks = e.requested.clone();
+                build.excluded_tasks = e.excluded.clone();
+            }
+            DecodedEvent::BuildFinished(e) => {
+                has_build = true;
+                build.finished_at = Some(frame.timestamp);
+                build.failure_id = e.failure_id;
+                build.outcome = Some(match e.failure_id {
+                    Some(_) => models::BuildOutcome::Failed,
+                    None => models::BuildOutcome::Success,
+                });
+            }
+            DecodedEvent::BuildAgent(e) => {
+                has_environment = true;
+                environment.username = e.username.clone();
+                environment.hostname = e.local_hostname.clone();
+            }
+            DecodedEvent::BuildModes(e) => {
+                has_build = true;
+                has_environment = true;
+                environment.max_workers = e.max_workers;
+                build.modes = Some(models::BuildModesData {
+                    refresh_dependencies: e.refresh_dependencies,
+                    parallel_project_execution: e.parallel_project_execution,
+                    rerun_tasks: e.rerun_tasks,
+                    continuous: e.continuous,
+                    continue_on_failure: e.continue_on_failure,
+                    configure_on_demand: e.configure_on_demand,
+                    daemon: e.daemon,
+                    offline: e.offline,
+                    dry_run: e.dry_run,
+                });
+            }
+            DecodedEvent::Encoding(e) => {
+                has_environment = true;
+                environment.default_charset = Some(e.default_charset.clone());
+            }
+            DecodedEvent::Hardware(e) => {
+                has_environment = true;
+                environment.num_processors = Some(e.num_processors);
+            }
+            DecodedEvent::Jvm(e) => {
+                has_environment = true;
+                environment.jvm_version = e.version.clone();
+                environment.jvm_vendor = e.vendor.clone();
+                environment.jvm_vm_name = e.vm_name.clone();
+            }
+            DecodedEvent::Os(e) => {
+                has_environment = true;
+                environment.os_family = e.family.clone();
+                environment.os_name = e.name.clone();
+                environment.os_version = e.version.clone();
+                environment.os_arch = e.arch.clone();
+            }
             DecodedEvent::DaemonState(_) => {}
-            DecodedEvent::Encoding(_) => {}
             DecodedEvent::FileRefRoots(_) => {}
-            DecodedEvent::Hardware(_) => {}
-            DecodedEvent::Jvm(_) => {}
             DecodedEvent::JvmArgs(_) => {}
             DecodedEvent::Locality(_) => {}
-            DecodedEvent::Os(_) => {}
             DecodedEvent::ScopeIds(_) => {}
             DecodedEvent::Raw(r) => {
                 *raw_counts.entry(r.wire_id).or_insert(0) += 1;
@@ -142,7 +210,7 @@ pub fn assemble(events: Vec<(FramedEvent, DecodedEvent)>) -> BuildScanPayload {
             let fin = finished.get(&id);
             let finished_at = fin.map(|f| f.timestamp);
             let duration_ms = match (started_at, finished_at) {
-                (Some(s), Some(f)) => Some(f - s),
+                (Some(s), Some(f)) => f.checked_sub(s),
                 _ => None,
             };
             let inputs =
@@ -226,7 +294,7 @@ pub fn assemble(events: Vec<(FramedEvent, DecodedEvent)>) -> BuildScanPayload {
                 build_path,
                 task_path,
                 class_name,
-                outcome: fin.and_then(|f| f.outcome.clone()),
+                outcome: fin.and_then(|f| f.outcome),
                 cacheable: fin.and_then(|f| f.cacheable),
                 caching_disabled_reason: fin.and_then(|f| f.caching_disabled_reason.clone()),
                 caching_disabled_explanation: fin
@@ -243,11 +311,24 @@ pub fn assemble(events: Vec<(FramedEvent, DecodedEvent)>) -> BuildScanPayload {
 
     tasks.sort_by_key(|t| t.id);
 
+    if let (Some(s), Some(f)) = (build.started_at, build.finished_at) {
+        build.duration_ms = f.checked_sub(s);
+    }
+
     let mut raw_events: Vec<RawEventSummary> = raw_counts
         .into_iter()
-        .map(|(wire_id, count)| RawEventSummary { wire_id, count })
+        .map(|(wire_id, count)| {
+            let known = wire_ids::lookup(wire_id);
+            RawEventSummary {
+                wire_id,
+                name: known.map(|e| e.name.to_string()),
+                version: known.map(|e| e.version.to_string()),
+                count,
+            }
+        })
         .collect();
-    raw_events.sort_by_key(|r| r.wire_id);
+    // Most frequent first, so the undecoded event types worth tackling next lead the list.
+    raw_events.sort_by(|a, b| b.count.cmp(&a.count).then(a.wire_id.cmp(&b.wire_id)));
 
     let planned_nodes_data: Vec<models::PlannedNodeData> = planned_nodes
         .into_iter()
@@ -260,6 +341,31 @@ pub fn assemble(events: Vec<(FramedEvent, DecodedEvent)>) -> BuildScanPayload {
         })
         .collect();
 
+    let identification_by_execution: HashMap<i64, i64> = transform_requests
+        .iter()
+        .filter_map(|r| Some((r.execution_id?, r.identification_id?)))
+        .collect();
+    let transform_executions: Vec<models::TransformExecutionData> = transform_started
+        .into_iter()
+        .map(|(id, started_at)| {
+            let identification_id = identification_by_execution.get(&id).copied();
+            let identification = identification_id.and_then(|i| transform_identifications.get(&i));
+            let fin = transform_finished.get(&id);
+            let finished_at = fin.map(|(_, ts)| *ts);
+            models::TransformExecutionData {
+                id,
+                identification_id,
+                transform_action_class: identification.map(|i| i.transform_action_class.clone()),
+                input_artifact_name: identification.map(|i| i.input_artifact_name.clone()),
+                outcome: fin.and_then(|(e, _)| e.outcome),
+                failure_id: fin.and_then(|(e, _)| e.failure_id),
+                started_at: Some(started_at),
+                finished_at,
+                duration_ms: finished_at.and_then(|f| f.checked_sub(started_at)),
+            }
+        })
+        .collect();
+
     let transform_requests_data: Vec<models::TransformExecutionRequestData> = transform_requests
         .into_iter()
         .map(|e| models::TransformExecutionRequestData {
@@ -270,9 +376,13 @@ pub fn assemble(events: Vec<(FramedEvent, DecodedEvent)>) -> BuildScanPayload {
         .collect();
 
     BuildScanPayload {
+        header: None,
+        build: has_build.then_some(build),
+        environment: has_environment.then_some(environment),
         tasks,
         planned_nodes: planned_nodes_data,
         transform_execution_requests: transform_requests_data,
+        transform_executions,
         raw_events,
         task_registration_summary: task_registration_summary.map(|e| {
             models::TaskRegistrationSummaryData {
@@ -355,11 +465,14 @@ fn assemble_indexed_normalized_samples(
     }
 }
 
+/// Build path, class name and start timestamp from `TaskStarted`.
+type StartedInfo = (Arc<str>, Option<Arc<str>>, i64);
+
 struct FinishedInfo {
     outcome: Option<TaskOutcome>,
     cacheable: Option<bool>,
-    caching_disabled_reason: Option<String>,
-    caching_disabled_explanation: Option<String>,
+    caching_disabled_reason: Option<Arc<str>>,
+    caching_disabled_explanation: Option<Arc<str>>,
     origin_build_cache_key: Option<Vec<u8>>,
     actionable: Option<bool>,
     timestamp: i64,
@@ -370,12 +483,16 @@ mod tests {
     use super::*;
     use events::*;
 
-    fn frame(wire_id: u16, ts: i64) -> FramedEvent {
+    fn frame(wire_id: u16, ts: i64) -> FramedEvent<'static> {
         FramedEvent {
             wire_id,
             timestamp: ts,
+            actual_timestamp: ts,
             ordinal: 0,
-            body: vec![],
+            flags: 0,
+            offset: 0,
+            frame_len: 0,
+            body: &[],
         }
     }
 
@@ -397,6 +514,7 @@ mod tests {
                     build_path: ":".into(),
                     path: ":app:build".into(),
                     class_name: Some("org.gradle.DefaultTask".into()),
+                    parent: None,
                 }),
             ),
             (
@@ -405,12 +523,15 @@ mod tests {
                     id: 1,
                     path: ":app:build".into(),
                     outcome: Some(3),
+                    skip_message: None,
                     cacheable: Some(false),
                     caching_disabled_reason_category: None,
                     caching_disabled_explanation: None,
                     origin_build_invocation_id: None,
                     origin_build_cache_key: None,
+                    origin_execution_time: None,
                     actionable: Some(false),
+                    up_to_date_messages: vec![],
                     skip_reason_message: None,
                 }),
             ),
@@ -418,11 +539,120 @@ mod tests {
         let payload = assemble(events);
         assert_eq!(payload.tasks.len(), 1);
         let task = &payload.tasks[0];
-        assert_eq!(task.task_path, ":app:build");
+        assert_eq!(&*task.task_path, ":app:build");
         assert_eq!(task.started_at, Some(2000));
         assert_eq!(task.finished_at, Some(3000));
         assert_eq!(task.duration_ms, Some(1000));
         assert!(matches!(task.outcome, Some(TaskOutcome::Success)));
         assert!(task.inputs.is_none());
     }
+
+    #[test]
+    fn test_raw_events_sorted_by_count() {
+        // 149 and 66 are undecoded ids from the captured payloads.
+        let raw = |wire_id| {
+            (
+                frame(wire_id, 0),
+                DecodedEvent::Raw(RawEvent { wire_id, body: &[] }),
+            )
+        };
+        let payload = assemble(vec![raw(66), raw(149), raw(149)]);
+
+        assert_eq!(payload.raw_events.len(), 2);
+        assert_eq!(payload.raw_events[0].wire_id, 149);
+        assert_eq!(payload.raw_events[0].count, 2);
+        assert_eq!(payload.raw_events[1].wire_id, 66);
+        assert_eq!(payload.raw_events[1].count, 1);
+        assert!(payload.raw_events.iter().all(|e| e.name.is_none()));
+        assert!(payload.raw_events.iter().all(|e| e.version.is_none()));
+    }
+
+    #[test]
+    fn test_build_and_environment() {
+        let events = vec![
+            (frame(6, 100), DecodedEvent::BuildStarted),
+            (
+                frame(5, 110),
+                DecodedEvent::BuildRequestedTasks(BuildRequestedTasksEvent {
+                    requested: vec!["build".into()],
+                    excluded: vec![],
+                }),
+            ),
+            (
+                frame(12, 120),
+                DecodedEvent::Hardware(HardwareEvent { num_processors: 8 }),
+            ),
+            (
+                frame(259, 5100),
+                DecodedEvent::BuildFinished(BuildFinishedEvent { failure_id: None }),
+            ),
+        ];
+        let payload = assemble(events);
+
+        let build = payload.build.unwrap();
+        assert_eq!(build.started_at, Some(100));
+        assert_eq!(build.duration_ms, Some(5000));
+        assert_eq!(build.outcome, Some(models::BuildOutcome::Success));
+        assert_eq!(build.requested_tasks, vec![Arc::<str>::from("build")]);
+        let environment = payload.environment.unwrap();
+        assert_eq!(environment.num_processors, Some(8));
+        assert!(environment.os_name.is_none());
+        assert!(payload.header.is_none());
+    }
+
+    #[test]
+    fn test_transform_execution_joined_with_identification() {
+        let events = vec![
+            (
+                frame(136, 0),
+                DecodedEvent::TransformIdentification(TransformIdentificationEvent {
+                    id: 7,
+                    component_identity: 1,
+                    input_artifact_name: "guava.jar".into(),
+                    transform_action_class: "org.gradle.Unzip".into(),
+                    from_attributes: vec![],
+                    to_attributes: vec![],
+                }),
+            ),
+            (
+                frame(137, 0),
+                DecodedEvent::TransformExecutionRequest(TransformExecutionRequestEvent {
+                    node_id: Some(1),
+                    identification_id: Some(7),
+                    execution_id: Some(42),
+                }),
+            ),
+            (
+                frame(138, 500),
+                DecodedEvent::TransformExecutionStarted(TransformExecutionStartedEvent { id: 42 }),
+            ),
+            (
+                frame(395, 750),
+                DecodedEvent::TransformExecutionFinished(TransformExecutionFinishedEvent {
+                    id: 42,
+                    failure_id: None,
+                    outcome: Some(1),
+                    execution_reasons: vec![],
+                    caching_disabled_reason_category: None,
+                    caching_disabled_explanation: None,
+                    origin_build_invocation_id: None,
+                    origin_build_cache_key: None,
+                    origin_execution_time: None,
+                }),
+            ),
+        ];
+        let payload = assemble(events);
+
+        assert_eq!(payload.transform_executions.len(), 1);
+        let t = &payload.transform_executions[0];
+        assert_eq!(t.id, 42);
+        assert_eq!(t.identification_id, Some(7));
+        assert_eq!(
+            t.transform_action_class.as_deref(),
+            Some("org.gradle.Unzip")
+        );
+        assert_eq!(t.input_artifact_name.as_deref(), Some("guava.jar"));
+        assert_eq!(t.duration_ms, Some(250));
+        assert_eq!(t.outcome, Some(1));
+    }
 }
@@ -0,0 +1,269 @@
+//! Chrome Trace Event export of the task and transform timeline, for Perfetto or
+//! `chrome://tracing`.
+//!
+//! The payload doesn't say which worker thread ran what, so work items are packed onto
+//! lanes greedily: each item, in start order, takes the lowest lane that is free by the
+//! time it starts. With one lane per concurrently running item, the lane count equals
+//! the peak parallelism and gaps in a lane are idle worker time.
+
+use models::BuildScanPayload;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+const PID: u32 = 1;
+/// Track for the build span; worker lanes start after it.
+const BUILD_TID: u32 = 0;
+
+#[derive(Debug, Serialize)]
+pub struct Trace {
+    #[serde(rename = "traceEvents")]
+    pub trace_events: Vec<TraceEvent>,
+    #[serde(rename = "displayTimeUnit")]
+    pub display_time_unit: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TraceEvent {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cat: Option<&'static str>,
+    pub ph: &'static str,
+    /// Microseconds since the start of the build.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ts: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dur: Option<i64>,
+    pub pid: u32,
+    pub tid: u32,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub args: Map<String, Value>,
+}
+
+struct Span {
+    name: String,
+    cat: &'static str,
+    start: i64,
+    end: i64,
+    args: Map<String, Value>,
+}
+
+pub fn export(payload: &BuildScanPayload) -> Trace {
+    let mut spans = Vec::new();
+
+    for task in &payload.tasks {
+        let (Some(start), Some(end)) = (task.started_at, task.finished_at) else {
+            continue;
+        };
+        let mut args = Map::new();
+        args.insert("build_path".into(), json!(&*task.build_path));
+        if let Some(outcome) = task.outcome {
+            args.insert("outcome".into(), json!(outcome.as_str()));
+        }
+        if let Some(cacheable) = task.cacheable {
+            args.insert("cacheable".into(), json!(cacheable));
+        }
+        if let Some(reason) = &task.caching_disabled_reason {
+            args.insert("caching_disabled_reason".into(), json!(&**reason));
+        }
+        if let Some(class_name) = &task.class_name {
+            args.insert("class_name".into(), json!(&**class_name));
+        }
+        spans.push(Span {
+            name: task.task_path.to_string(),
+            cat: "task",
+            start,
+            end,
+            args,
+        });
+    }
+
+    for transform in &payload.transform_executions {
+        let (Some(start), Some(end)) = (transform.started_at, transform.finished_at) else {
+            continue;
+        };
+        let action = transform
+            .transform_action_class
+            .as_deref()
+            .unwrap_or("transform");
+        let name = match &transform.input_artifact_name {
+            Some(artifact) => format!("{action} {artifact}"),
+            None => action.to_string(),
+        };
+        let mut args = Map::new();
+        if let Some(outcome) = transform.outcome {
+            args.insert("outcome".into(), json!(outcome));
+        }
+        if let Some(failure_id) = transform.failure_id {
+            args.insert("failure_id".into(), json!(failure_id));
+        }
+        spans.push(Span {
+            name,
+            cat: "transform",
+            start,
+            end,
+            args,
+        });
+    }
+
+    spans.sort_by_key(|s| (s.start, s.end));
+    let lanes = assign_lanes(spans.iter().map(|s| (s.start, s.end)));
+
+    let build = payload.build.as_ref();
+    let origin = build
+        .and_then(|b| b.started_at)
+        .into_iter()
+        .chain(spans.first().map(|s| s.start))
+        .min()
+        .unwrap_or(0);
+    let micros = |ms: i64| ms.saturating_sub(origin).saturating_mul(1000);
+
+    let mut trace_events = vec![metadata("process_name", BUILD_TID, "Build")];
+    trace_events.push(metadata("thread_name", BUILD_TID, "Build"));
+    let lane_count = lanes.iter().copied().max().map_or(0, |max| max + 1);
+    for lane in 0..lane_count {
+        trace_events.push(metadata(
+            "thread_name",
+            lane_tid(lane),
+            &format!("Worker lane {}", lane + 1),
+        ));
+    }
+
+    if let Some((Some(start), Some(end))) = build.map(|b| (b.started_at, b.finished_at)) {
+        let mut args = Map::new();
+        if let Some(outcome) = build.and_then(|b| b.outcome) {
+            args.insert("outcome".into(), json!(outcome));
+        }
+        trace_events.push(TraceEvent {
+            name: "Build".into(),
+            cat: Some("build"),
+            ph: "X",
+            ts: Some(micros(start)),
+            dur: Some(end.saturating_sub(start).saturating_mul(1000)),
+            pid: PID,
+            tid: BUILD_TID,
+            args,
+        });
+    }
+
+    for (span, lane) in spans.into_iter().zip(lanes) {
+        trace_events.push(TraceEvent {
+            name: span.name,
+            cat: Some(span.cat),
+            ph: "X",
+            ts: Some(micros(span.start)),
+            dur: Some(span.end.saturating_sub(span.start).saturating_mul(1000)),
+            pid: PID,
+            tid: lane_tid(lane),
+            args: span.args,
+        });
+    }
+
+    Trace {
+        trace_events,
+        display_time_unit: "ms",
+    }
+}
+
+/// Lane index for each `(start, end)` interval, which must be sorted by start. An
+/// interval may start on a lane at the same instant the previous one ended.
+pub fn assign_lanes(intervals: impl IntoIterator<Item = (i64, i64)>) -> Vec<usize> {
+    let mut lane_ends: Vec<i64> = Vec::new();
+    intervals
+        .into_iter()
+        .map(
+            |(start, end)| match lane_ends.iter().position(|&e| e <= start) {
+                Some(lane) => {
+                    lane_ends[lane] = end;
+                    lane
+                }
+                None => {
+                    lane_ends.push(end);
+                    lane_ends.len() - 1
+                }
+            },
+        )
+        .collect()
+}
+
+fn lane_tid(lane: usize) -> u32 {
+    BUILD_TID + 1 + lane as u32
+}
+
+fn metadata(kind: &'static str, tid: u32, name: &str) -> TraceEvent {
+    let mut args = Map::new();
+    args.insert("name".into(), json!(name));
+    TraceEvent {
+        name: kind.into(),
+        cat: None,
+        ph: "M",
+        ts: None,
+        dur: None,
+        pid: PID,
+        tid,
+        args,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::{BuildData, Task, TaskOutcome};
+
+    fn task(id: i64, start: i64, end: i64) -> Task {
+        Task {
+            id,
+            build_path: ":".into(),
+            task_path: format!(":t{id}").into(),
+            class_name: None,
+            outcome: Some(TaskOutcome::Success),
+            cacheable: Some(true),
+            caching_disabled_reason: None,
+            caching_disabled_explanation: None,
+            origin_build_cache_key: None,
+            actionable: None,
+            started_at: Some(start),
+            finished_at: Some(end),
+            duration_ms: Some(end - start),
+            inputs: None,
+        }
+    }
+
+    #[test]
+    fn test_assign_lanes_reuses_free_lanes() {
+        let lanes = assign_lanes([(0, 10), (0, 5), (5, 8), (9, 12), (10, 11)]);
+        assert_eq!(lanes, vec![0, 1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_export_tasks() {
+        let payload = BuildScanPayload {
+            build: Some(BuildData {
+                started_at: Some(1000),
+                finished_at: Some(1100),
+                ..Default::default()
+            }),
+            tasks: vec![task(1, 1010, 1050), task(2, 1020, 1030)],
+            ..Default::default()
+        };
+        let trace = export(&payload);
+        let json = serde_json::to_value(&trace).unwrap();
+        let events = json["traceEvents"].as_array().unwrap();
+
+        let complete: Vec<&Value> = events.iter().filter(|e| e["ph"] == "X").collect();
+        assert_eq!(complete.len(), 3);
+        assert_eq!(complete[0]["name"], "Build");
+        assert_eq!(complete[0]["dur"], 100_000);
+        assert_eq!(complete[1]["name"], ":t1");
+        assert_eq!(complete[1]["ts"], 10_000);
+        assert_eq!(complete[1]["tid"], 1);
+        assert_eq!(complete[1]["args"]["outcome"], "SUCCESS");
+        assert_eq!(complete[1]["args"]["cacheable"], true);
+        assert_eq!(complete[2]["tid"], 2);
+
+        let lanes = events
+            .iter()
+            .filter(|e| e["ph"] == "M" && e["name"] == "thread_name")
+            .count();
+        assert_eq!(lanes, 3);
+    }
+}
@@ -1,35 +1,128 @@
-use flate2::read::GzDecoder;
+use flate2::Crc;
+use flate2::bufread::DeflateDecoder;
 use std::io::Read;
 
 use error::ParseError;
 
+/// Default cap on the decompressed event stream. Real build scans stay within a few tens
+/// of megabytes; anything past this is treated as a gzip bomb.
+pub const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const CM_DEFLATE: u8 = 8;
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+/// Decompresses the single gzip member that follows the outer header (RFC 1952).
+///
+/// The input must start exactly at the member, i.e. at `OuterHeader::gzip_offset`, and
+/// must end with its trailer. Offsets in errors are relative to the start of the member.
 pub struct Decompressor;
 
 impl Decompressor {
-    pub fn decompress(raw_data: &[u8]) -> Result<Vec<u8>, ParseError> {
-        let mut start_idx = 0;
-        for i in 0..raw_data.len().saturating_sub(2) {
-            if raw_data[i] == 0x1f && raw_data[i + 1] == 0x8b && raw_data[i + 2] == 0x08 {
-                start_idx = i;
-                break;
-            }
-        }
+    pub fn decompress(gzip: &[u8]) -> Result<Vec<u8>, ParseError> {
+        Self::decompress_with_limit(gzip, MAX_DECOMPRESSED_SIZE)
+    }
+
+    pub fn decompress_with_limit(gzip: &[u8], limit: usize) -> Result<Vec<u8>, ParseError> {
+        let body_start = Self::skip_header(gzip)?;
 
-        let mut decoder = GzDecoder::new(&raw_data[start_idx..]);
+        let mut decoder = DeflateDecoder::new(&gzip[body_start..]).take(limit as u64 + 1);
         let mut decompressed = Vec::new();
         decoder
             .read_to_end(&mut decompressed)
             .map_err(|_| ParseError::InvalidGzip)?;
+        if decompressed.len() > limit {
+            return Err(ParseError::DecompressedTooLarge { limit });
+        }
+        let rest = decoder.into_inner().into_inner();
+
+        let trailer_start = gzip.len() - rest.len();
+        let (trailer, after) = rest
+            .split_first_chunk::<8>()
+            .ok_or(ParseError::TruncatedGzip)?;
+        let expected_crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let expected_len = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+
+        let mut crc = Crc::new();
+        crc.update(&decompressed);
+        if crc.sum() != expected_crc {
+            return Err(ParseError::GzipCrcMismatch {
+                expected: expected_crc,
+                actual: crc.sum(),
+            });
+        }
+        // ISIZE is the uncompressed length modulo 2^32.
+        if decompressed.len() as u32 != expected_len {
+            return Err(ParseError::GzipLengthMismatch {
+                expected: expected_len,
+                actual: decompressed.len() as u32,
+            });
+        }
+
+        let end = trailer_start + 8;
+        if after.starts_with(&GZIP_MAGIC) {
+            return Err(ParseError::MultipleGzipMembers { offset: end });
+        }
+        if !after.is_empty() {
+            return Err(ParseError::TrailingData {
+                offset: end,
+                len: after.len(),
+            });
+        }
 
         Ok(decompressed)
     }
+
+    /// Validates the fixed member header and skips the optional fields, returning the
+    /// offset of the deflate stream.
+    fn skip_header(gzip: &[u8]) -> Result<usize, ParseError> {
+        if gzip.len() < 10 {
+            return Err(ParseError::TruncatedGzip);
+        }
+        if gzip[..2] != GZIP_MAGIC {
+            return Err(ParseError::InvalidGzipHeader {
+                reason: "missing gzip magic at header offset",
+            });
+        }
+        if gzip[2] != CM_DEFLATE {
+            return Err(ParseError::InvalidGzipHeader {
+                reason: "unsupported compression method",
+            });
+        }
+        let flags = gzip[3];
+        let mut pos = 10;
+
+        if flags & FEXTRA != 0 {
+            let len_bytes = gzip.get(pos..pos + 2).ok_or(ParseError::TruncatedGzip)?;
+            pos += 2 + u16::from_le_bytes([len_bytes[0], len_bytes[1]]) as usize;
+        }
+        for field in [FNAME, FCOMMENT] {
+            if flags & field != 0 {
+                let nul = gzip
+                    .get(pos..)
+                    .and_then(|rest| rest.iter().position(|&b| b == 0))
+                    .ok_or(ParseError::TruncatedGzip)?;
+                pos += nul + 1;
+            }
+        }
+        if flags & FHCRC != 0 {
+            pos += 2;
+        }
+        if pos > gzip.len() {
+            return Err(ParseError::TruncatedGzip);
+        }
+        Ok(pos)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use flate2::Compression;
     use flate2::write::GzEncoder;
+    use flate2::{Compression, GzBuilder};
     use std::io::Write;
 
     fn gzip_compress(data: &[u8]) -> Vec<u8> {
@@ -47,13 +140,80 @@ mod tests {
     }
 
     #[test]
-    fn test_decompress_with_prefix() {
-        let original = b"test data";
-        let compressed = gzip_compress(original);
+    fn test_decompress_rejects_data_before_member() {
+        let compressed = gzip_compress(b"test data");
         let mut with_prefix = vec![0x00, 0x01, 0x02];
         with_prefix.extend_from_slice(&compressed);
-        let result = Decompressor::decompress(&with_prefix).unwrap();
-        assert_eq!(result, original);
+        let result = Decompressor::decompress(&with_prefix);
+        assert!(matches!(result, Err(ParseError::InvalidGzipHeader { .. })));
+    }
+
+    #[test]
+    fn test_decompress_with_file_name_header() {
+        let mut encoder = GzBuilder::new()
+            .filename("events.bin")
+            .comment("captured")
+            .write(Vec::new(), Compression::default());
+        encoder.write_all(b"named").unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(Decompressor::decompress(&compressed).unwrap(), b"named");
+    }
+
+    #[test]
+    fn test_decompress_crc_mismatch() {
+        let mut compressed = gzip_compress(b"checksum me");
+        let crc_at = compressed.len() - 8;
+        compressed[crc_at] ^= 0xFF;
+        assert!(matches!(
+            Decompressor::decompress(&compressed),
+            Err(ParseError::GzipCrcMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decompress_length_mismatch() {
+        let mut compressed = gzip_compress(b"length");
+        let len_at = compressed.len() - 4;
+        compressed[len_at] += 1;
+        assert!(matches!(
+            Decompressor::decompress(&compressed),
+            Err(ParseError::GzipLengthMismatch {
+                expected: 7,
+                actual: 6
+            })
+        ));
+    }
+
+    #[test]
+    fn test_decompress_truncated_trailer() {
+        let compressed = gzip_compress(b"cut short");
+        let truncated = &compressed[..compressed.len() - 3];
+        assert!(matches!(
+            Decompressor::decompress(truncated),
+            Err(ParseError::TruncatedGzip)
+        ));
+    }
+
+    #[test]
+    fn test_decompress_trailing_garbage() {
+        let mut compressed = gzip_compress(b"payload");
+        let end = compressed.len();
+        compressed.extend_from_slice(&[0xAB, 0xCD]);
+        assert!(matches!(
+            Decompressor::decompress(&compressed),
+            Err(ParseError::TrailingData { offset, len: 2 }) if offset == end
+        ));
+    }
+
+    #[test]
+    fn test_decompress_concatenated_members() {
+        let mut compressed = gzip_compress(b"first");
+        let end = compressed.len();
+        compressed.extend_from_slice(&gzip_compress(b"second"));
+        assert!(matches!(
+            Decompressor::decompress(&compressed),
+            Err(ParseError::MultipleGzipMembers { offset }) if offset == end
+        ));
     }
 
     #[test]
@@ -62,4 +222,15 @@ mod tests {
         let result = Decompressor::decompress(&invalid);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_decompress_rejects_output_over_limit() {
+        let compressed = gzip_compress(&vec![0u8; 64 * 1024]);
+        let result = Decompressor::decompress_with_limit(&compressed, 1024);
+        assert!(matches!(
+            result,
+            Err(ParseError::DecompressedTooLarge { limit: 1024 })
+        ));
+        assert!(Decompressor::decompress_with_limit(&compressed, 64 * 1024).is_ok());
+    }
 }
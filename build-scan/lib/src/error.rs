@@ -6,6 +6,19 @@ pub enum ParseError {
     Io(#[from] std::io::Error),
     #[error("Failed to decompress Gzip stream")]
     InvalidGzip,
+    #[error("Invalid gzip header: {reason}")]
+    InvalidGzipHeader { reason: &'static str },
+    #[error("Gzip stream ends before its trailer")]
+    TruncatedGzip,
+    #[error("Gzip CRC32 mismatch: trailer {expected:#010x}, data {actual:#010x}")]
+    GzipCrcMismatch { expected: u32, actual: u32 },
+    #[error("Gzip length mismatch: trailer {expected}, data {actual}")]
+    GzipLengthMismatch { expected: u32, actual: u32 },
+    #[error("Unexpected second gzip member at offset {offset}")]
+    MultipleGzipMembers { offset: usize },
+    #[error("{len} bytes of trailing data after gzip member at offset {offset}")]
+    TrailingData { offset: usize, len: usize },
+    #[deprecated(note = "varints end at Kryo's nine-byte varlong form, so none can run over")]
     #[error("Malformed LEB128 varint at offset {offset}")]
     MalformedLeb128 { offset: usize },
     #[error("Unexpected end of data at offset {offset}")]
@@ -16,4 +29,45 @@ pub enum ParseError {
     InvalidHeader { reason: &'static str },
     #[error("Invalid string intern reference: index {index}")]
     InvalidStringRef { index: usize },
+    #[error("Decompressed payload exceeds {limit} bytes")]
+    DecompressedTooLarge { limit: usize },
+    #[error("Length prefix {len} at offset {offset} exceeds the remaining input")]
+    LengthOutOfBounds { len: u64, offset: usize },
+    #[error("String of {len} chars at offset {offset} exceeds the remaining input")]
+    StringLengthOutOfBounds { len: usize, offset: usize },
+    #[error("Unsupported list prefix {prefix} at offset {offset}")]
+    UnsupportedListPrefix { prefix: u64, offset: usize },
+    #[error("Failed to decode {event} body: {source}")]
+    InvalidEventBody {
+        wire_id: u16,
+        event: String,
+        source: Box<ParseError>,
+    },
+}
+
+impl ParseError {
+    /// Variant name, for grouping failures in logs and metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "Io",
+            Self::InvalidGzip => "InvalidGzip",
+            Self::InvalidGzipHeader { .. } => "InvalidGzipHeader",
+            Self::TruncatedGzip => "TruncatedGzip",
+            Self::GzipCrcMismatch { .. } => "GzipCrcMismatch",
+            Self::GzipLengthMismatch { .. } => "GzipLengthMismatch",
+            Self::MultipleGzipMembers { .. } => "MultipleGzipMembers",
+            Self::TrailingData { .. } => "TrailingData",
+            #[allow(deprecated)]
+            Self::MalformedLeb128 { .. } => "MalformedLeb128",
+            Self::UnexpectedEof { .. } => "UnexpectedEof",
+            Self::InvalidUtf8 => "InvalidUtf8",
+            Self::InvalidHeader { .. } => "InvalidHeader",
+            Self::InvalidStringRef { .. } => "InvalidStringRef",
+            Self::DecompressedTooLarge { .. } => "DecompressedTooLarge",
+            Self::LengthOutOfBounds { .. } => "LengthOutOfBounds",
+            Self::StringLengthOutOfBounds { .. } => "StringLengthOutOfBounds",
+            Self::UnsupportedListPrefix { .. } => "UnsupportedListPrefix",
+            Self::InvalidEventBody { .. } => "InvalidEventBody",
+        }
+    }
 }
@@ -1,114 +1,36 @@
-use error::ParseError;
-
-use super::{BasicMemoryStatsEvent, BodyDecoder, DecodedEvent, MemoryPoolSnapshotEvent};
-
-pub struct BasicMemoryStatsDecoder;
-
-/// Wire 257: BasicMemoryStats_1_1 — 3 longs + list of MemoryPoolSnapshot + gcTime.
-impl BodyDecoder for BasicMemoryStatsDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let free = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_zigzag_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let total = if kryo::is_field_present(flags as u16, 1) {
-            Some(kryo::read_zigzag_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let max = if kryo::is_field_present(flags as u16, 2) {
-            Some(kryo::read_zigzag_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let peak_snapshots = if kryo::is_field_present(flags as u16, 3) {
-            let count = kryo::read_positive_varint_i32(body, &mut pos)? as usize;
-            let mut snapshots = Vec::with_capacity(count);
-            for _ in 0..count {
-                snapshots.push(decode_memory_pool_snapshot(body, &mut pos, &mut table)?);
-            }
-            snapshots
-        } else {
-            vec![]
-        };
-
-        let gc_time = if kryo::is_field_present(flags as u16, 4) {
-            Some(kryo::read_zigzag_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::BasicMemoryStats(BasicMemoryStatsEvent {
-            free,
-            total,
-            max,
-            peak_snapshots,
-            gc_time,
-        }))
+use kryo::{InternedString, ListOf, PositiveI64};
+
+use super::{BasicMemoryStatsEvent, MemoryPoolSnapshotEvent};
+
+event_schema! {
+    /// Wire 257: BasicMemoryStats_1_1 — 3 longs + list of MemoryPoolSnapshot + gcTime.
+    BasicMemoryStatsDecoder => BasicMemoryStats(BasicMemoryStatsEvent) {
+        flags: byte,
+        free: optional(PositiveI64, 0),
+        total: optional(PositiveI64, 1),
+        max: optional(PositiveI64, 2),
+        peak_snapshots: or_default(ListOf<MemoryPoolSnapshotEvent>, 3),
+        gc_time: optional(PositiveI64, 4),
     }
 }
 
-fn decode_memory_pool_snapshot(
-    body: &[u8],
-    pos: &mut usize,
-    table: &mut kryo::StringInternTable,
-) -> Result<MemoryPoolSnapshotEvent, ParseError> {
-    let flags = kryo::read_flags_byte(body, pos)?;
-
-    let name = if kryo::is_field_present(flags as u16, 0) {
-        Some(table.read_string(body, pos)?)
-    } else {
-        None
-    };
-
-    // bit 1: heap boolean — value IS the bit (is_field_present=true means heap=true)
-    let heap = kryo::is_field_present(flags as u16, 1);
-
-    let init = if kryo::is_field_present(flags as u16, 2) {
-        Some(kryo::read_zigzag_i64(body, pos)?)
-    } else {
-        None
-    };
-
-    let used = if kryo::is_field_present(flags as u16, 3) {
-        Some(kryo::read_zigzag_i64(body, pos)?)
-    } else {
-        None
-    };
-
-    let committed = if kryo::is_field_present(flags as u16, 4) {
-        Some(kryo::read_zigzag_i64(body, pos)?)
-    } else {
-        None
-    };
-
-    let max = if kryo::is_field_present(flags as u16, 5) {
-        Some(kryo::read_zigzag_i64(body, pos)?)
-    } else {
-        None
-    };
-
-    Ok(MemoryPoolSnapshotEvent {
-        name,
-        heap,
-        init,
-        used,
-        committed,
-        max,
-    })
+record_schema! {
+    MemoryPoolSnapshotEvent {
+        flags: byte,
+        name: optional(InternedString, 0),
+        // bit 1: heap boolean — value IS the bit (is_field_present=true means heap=true)
+        heap: flag(1),
+        init: optional(PositiveI64, 2),
+        used: optional(PositiveI64, 3),
+        committed: optional(PositiveI64, 4),
+        max: optional(PositiveI64, 5),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_absent() {
@@ -134,15 +56,14 @@ mod tests {
         // flags bits: bit=0 means present, bit=1 means absent
         // bits 3 and 4 absent (set), bits 0,1,2 present (clear) → 0b00011000 = 0x18
         let mut data = vec![0x18u8];
-        // free = 100 → zigzag: 100*2 = 200 → varint 200 = 0xC8 0x01
-        data.push(0xC8);
-        data.push(0x01);
-        // total = 512 → zigzag: 512*2 = 1024 → varint 1024 = 0x80 0x08
+        // free = 100 → varint 0x64
+        data.push(0x64);
+        // total = 512 → varint 0x80 0x04
         data.push(0x80);
-        data.push(0x08);
-        // max = 1024 → zigzag: 1024*2 = 2048 → varint 2048 = 0x80 0x10
+        data.push(0x04);
+        // max = 1024 → varint 0x80 0x08
         data.push(0x80);
-        data.push(0x10);
+        data.push(0x08);
 
         let decoder = BasicMemoryStatsDecoder;
         let result = decoder.decode(&data).unwrap();
@@ -187,15 +108,14 @@ mod tests {
     fn test_decode_all_fields_present() {
         // flags = 0b00000000 → all 5 bits clear → all fields present
         let mut data = vec![0x00u8];
-        // free = 100 → zigzag(100) = 200 = 0xC8 0x01
-        data.push(0xC8);
-        data.push(0x01);
-        // total = 512 → zigzag(512) = 1024 = 0x80 0x08
+        // free = 100 → varint 0x64
+        data.push(0x64);
+        // total = 512 → varint 0x80 0x04
         data.push(0x80);
-        data.push(0x08);
-        // max = 1024 → zigzag(1024) = 2048 = 0x80 0x10
+        data.push(0x04);
+        // max = 1024 → varint 0x80 0x08
         data.push(0x80);
-        data.push(0x10);
+        data.push(0x08);
         // peak_snapshots: count = 2
         data.push(0x02);
 
@@ -207,36 +127,35 @@ mod tests {
             data.push(ch);
         }
         // heap = true (bit 1 clear, no payload)
-        // init = 50 → zigzag(50) = 100 = 0x64
-        data.push(0x64);
-        // used = 200 → zigzag(200) = 400 = 0x90 0x03
-        data.push(0x90);
-        data.push(0x03);
-        // committed = 256 → zigzag(256) = 512 = 0x80 0x04
+        // init = 50 → varint 0x32
+        data.push(0x32);
+        // used = 200 → varint 0xC8 0x01
+        data.push(0xC8);
+        data.push(0x01);
+        // committed = 256 → varint 0x80 0x02
         data.push(0x80);
-        data.push(0x04);
-        // max = 512 → zigzag(512) = 1024 = 0x80 0x08
+        data.push(0x02);
+        // max = 512 → varint 0x80 0x04
         data.push(0x80);
-        data.push(0x08);
+        data.push(0x04);
 
         // Snapshot 2: name back-refs to "Eden Space", heap = false (bit 1 set)
         // flags: bit 1 set (heap=false), rest clear → 0b00000010 = 0x02
         data.push(0x02);
         // name = back-ref to index 0 → zigzag(-1) = 1
         data.push(0x01);
-        // init = 10 → zigzag(10) = 20 = 0x14
-        data.push(0x14);
-        // used = 30 → zigzag(30) = 60 = 0x3C
-        data.push(0x3C);
-        // committed = 64 → zigzag(64) = 128 = 0x80 0x01
+        // init = 10 → varint 0x0A
+        data.push(0x0A);
+        // used = 30 → varint 0x1E
+        data.push(0x1E);
+        // committed = 64 → varint 0x40
+        data.push(0x40);
+        // max = 128 → varint 0x80 0x01
         data.push(0x80);
         data.push(0x01);
-        // max = 128 → zigzag(128) = 256 = 0x80 0x02
-        data.push(0x80);
-        data.push(0x02);
 
-        // gc_time = 42 → zigzag(42) = 84 = 0x54
-        data.push(0x54);
+        // gc_time = 42 → varint 0x2A
+        data.push(0x2A);
 
         let decoder = BasicMemoryStatsDecoder;
         let result = decoder.decode(&data).unwrap();
@@ -248,7 +167,7 @@ mod tests {
             assert_eq!(e.peak_snapshots.len(), 2);
 
             let s1 = &e.peak_snapshots[0];
-            assert_eq!(s1.name, Some("Eden Space".to_string()));
+            assert_eq!(s1.name.as_deref(), Some("Eden Space"));
             assert!(s1.heap);
             assert_eq!(s1.init, Some(50));
             assert_eq!(s1.used, Some(200));
@@ -256,7 +175,7 @@ mod tests {
             assert_eq!(s1.max, Some(512));
 
             let s2 = &e.peak_snapshots[1];
-            assert_eq!(s2.name, Some("Eden Space".to_string())); // back-ref
+            assert_eq!(s2.name.as_deref(), Some("Eden Space")); // back-ref
             assert!(!s2.heap);
             assert_eq!(s2.init, Some(10));
             assert_eq!(s2.used, Some(30));
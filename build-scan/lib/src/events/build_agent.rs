@@ -1,52 +1,24 @@
-use error::ParseError;
-
-use super::{BodyDecoder, BuildAgentEvent, DecodedEvent};
-
-pub struct BuildAgentDecoder;
-
-/// Wire 2: BuildAgent_1_0 — 3 nullable strings + 1 list of strings.
-impl BodyDecoder for BuildAgentDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let username = if kryo::is_field_present(flags as u16, 0) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let local_hostname = if kryo::is_field_present(flags as u16, 1) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let public_hostname = if kryo::is_field_present(flags as u16, 2) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let ip_addresses = if kryo::is_field_present(flags as u16, 3) {
-            kryo::read_list_of_interned_strings(body, &mut pos, &mut table)?
-        } else {
-            vec![]
-        };
-
-        Ok(DecodedEvent::BuildAgent(BuildAgentEvent {
-            username,
-            local_hostname,
-            public_hostname,
-            ip_addresses,
-        }))
+use kryo::{InternedString, ListOf};
+
+use super::BuildAgentEvent;
+
+event_schema! {
+    /// Wire 2: BuildAgent_1_0 — 3 nullable strings + 1 list of strings.
+    BuildAgentDecoder => BuildAgent(BuildAgentEvent) {
+        flags: byte,
+        username: optional(InternedString, 0),
+        local_hostname: optional(InternedString, 1),
+        public_hostname: optional(InternedString, 2),
+        ip_addresses: or_default(ListOf<InternedString>, 3),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_present() {
@@ -71,7 +43,7 @@ mod tests {
             assert_eq!(e.username, Some("user1".into()));
             assert_eq!(e.local_hostname, Some("host".into()));
             assert_eq!(e.public_hostname, Some("pub".into()));
-            assert_eq!(e.ip_addresses, vec!["1.2.3.4"]);
+            assert_eq!(e.ip_addresses, vec![Arc::from("1.2.3.4")]);
         } else {
             panic!("expected BuildAgent");
         }
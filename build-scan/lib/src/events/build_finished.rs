@@ -1,30 +1,19 @@
-use error::ParseError;
+use kryo::ZigzagI64;
 
-use super::{BodyDecoder, BuildFinishedEvent, DecodedEvent};
+use super::BuildFinishedEvent;
 
-pub struct BuildFinishedDecoder;
-
-/// Wire 259: BuildFinished_1_1 — single nullable failureId.
-impl BodyDecoder for BuildFinishedDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-
-        let failure_id = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_zigzag_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::BuildFinished(BuildFinishedEvent {
-            failure_id,
-        }))
+event_schema! {
+    /// Wire 259: BuildFinished_1_1 — single nullable failureId.
+    BuildFinishedDecoder => BuildFinished(BuildFinishedEvent) {
+        flags: byte,
+        failure_id: optional(ZigzagI64, 0),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_no_failure() {
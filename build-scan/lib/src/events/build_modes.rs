@@ -1,41 +1,30 @@
-use error::ParseError;
+use kryo::PositiveI32;
 
-use super::{BodyDecoder, BuildModesEvent, DecodedEvent};
+use super::BuildModesEvent;
 
-pub struct BuildModesDecoder;
-
-/// Wire 516: BuildModes_1_2 — 9 booleans packed into flags bits + 1 int.
-/// Bits 0-8: boolean values (bit=0 means true, bit=1 means false).
-/// Bit 9: maxWorkers presence; if present, read a positive varint i32.
-impl BodyDecoder for BuildModesDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_u16_be(body, &mut pos)?;
-
-        let max_workers = if kryo::is_field_present(flags, 9) {
-            Some(kryo::read_positive_varint_i32(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::BuildModes(BuildModesEvent {
-            refresh_dependencies: kryo::is_field_present(flags, 0),
-            parallel_project_execution: kryo::is_field_present(flags, 1),
-            rerun_tasks: kryo::is_field_present(flags, 2),
-            continuous: kryo::is_field_present(flags, 3),
-            continue_on_failure: kryo::is_field_present(flags, 4),
-            configure_on_demand: kryo::is_field_present(flags, 5),
-            daemon: kryo::is_field_present(flags, 6),
-            offline: kryo::is_field_present(flags, 7),
-            dry_run: kryo::is_field_present(flags, 8),
-            max_workers,
-        }))
+event_schema! {
+    /// Wire 516: BuildModes_1_2 — 9 booleans packed into flags bits + 1 int.
+    /// Bits 0-8: boolean values (bit=0 means true, bit=1 means false).
+    /// Bit 9: maxWorkers presence; if present, read a positive varint i32.
+    BuildModesDecoder => BuildModes(BuildModesEvent) {
+        flags: u16,
+        refresh_dependencies: flag(0),
+        parallel_project_execution: flag(1),
+        rerun_tasks: flag(2),
+        continuous: flag(3),
+        continue_on_failure: flag(4),
+        configure_on_demand: flag(5),
+        daemon: flag(6),
+        offline: flag(7),
+        dry_run: flag(8),
+        max_workers: optional(PositiveI32, 9),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_daemon_only_with_workers() {
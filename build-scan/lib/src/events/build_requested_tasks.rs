@@ -1,40 +1,22 @@
-use error::ParseError;
+use kryo::{InternedString, ListOf};
 
-use super::{BodyDecoder, BuildRequestedTasksEvent, DecodedEvent};
+use super::BuildRequestedTasksEvent;
 
-pub struct BuildRequestedTasksDecoder;
-
-/// Wire 5: BuildRequestedTasks_1_0 — two lists of interned strings.
-impl BodyDecoder for BuildRequestedTasksDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let requested = if kryo::is_field_present(flags as u16, 0) {
-            kryo::read_list_of_interned_strings(body, &mut pos, &mut table)?
-        } else {
-            vec![]
-        };
-
-        let excluded = if kryo::is_field_present(flags as u16, 1) {
-            kryo::read_list_of_interned_strings(body, &mut pos, &mut table)?
-        } else {
-            vec![]
-        };
-
-        Ok(DecodedEvent::BuildRequestedTasks(
-            BuildRequestedTasksEvent {
-                requested,
-                excluded,
-            },
-        ))
+event_schema! {
+    /// Wire 5: BuildRequestedTasks_1_0 — two lists of interned strings.
+    BuildRequestedTasksDecoder => BuildRequestedTasks(BuildRequestedTasksEvent) {
+        flags: byte,
+        requested: or_default(ListOf<InternedString>, 0),
+        excluded: or_default(ListOf<InternedString>, 1),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_with_tasks() {
@@ -49,7 +31,7 @@ mod tests {
         let decoder = BuildRequestedTasksDecoder;
         let result = decoder.decode(&data).unwrap();
         if let DecodedEvent::BuildRequestedTasks(e) = result {
-            assert_eq!(e.requested, vec!["build"]);
+            assert_eq!(e.requested, vec![Arc::from("build")]);
             assert!(e.excluded.is_empty());
         } else {
             panic!("expected BuildRequestedTasks");
@@ -6,7 +6,7 @@ pub struct BuildStartedDecoder;
 
 /// Wire 6: BuildStarted_1_0 — marker event with empty body.
 impl BodyDecoder for BuildStartedDecoder {
-    fn decode(&self, _body: &[u8]) -> Result<DecodedEvent, ParseError> {
+    fn decode<'a>(&self, _body: &'a [u8]) -> Result<DecodedEvent<'a>, ParseError> {
         Ok(DecodedEvent::BuildStarted)
     }
 }
@@ -1,59 +1,24 @@
-use error::ParseError;
-
-use super::{BodyDecoder, DaemonStateEvent, DecodedEvent};
-
-pub struct DaemonStateDecoder;
-
-/// Wire 265: DaemonState_1_1 — 2 longs, 2 ints, 1 nullable boolean.
-impl BodyDecoder for DaemonStateDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-
-        let start_time = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_positive_varint_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let build_number = if kryo::is_field_present(flags as u16, 1) {
-            Some(kryo::read_positive_varint_i32(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let number_of_running_daemons = if kryo::is_field_present(flags as u16, 2) {
-            Some(kryo::read_positive_varint_i32(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let idle_timeout = if kryo::is_field_present(flags as u16, 3) {
-            Some(kryo::read_positive_varint_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
+use kryo::{PositiveI32, PositiveI64};
+
+use super::DaemonStateEvent;
+
+event_schema! {
+    /// Wire 265: DaemonState_1_1 — 2 longs, 2 ints, 1 nullable boolean.
+    DaemonStateDecoder => DaemonState(DaemonStateEvent) {
+        flags: byte,
+        start_time: optional(PositiveI64, 0),
+        build_number: optional(PositiveI32, 1),
+        number_of_running_daemons: optional(PositiveI32, 2),
+        idle_timeout: optional(PositiveI64, 3),
         // bit 4: singleUse — nullable Boolean, bit IS the value (no payload)
-        let single_use = if kryo::is_field_present(flags as u16, 4) {
-            Some(true)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::DaemonState(DaemonStateEvent {
-            start_time,
-            build_number,
-            number_of_running_daemons,
-            idle_timeout,
-            single_use,
-        }))
+        single_use: flag_or_none(4),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_present() {
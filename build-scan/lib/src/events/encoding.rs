@@ -1,23 +1,19 @@
-use error::ParseError;
+use kryo::InternedString;
 
-use super::{BodyDecoder, DecodedEvent, EncodingEvent};
+use super::EncodingEvent;
 
-pub struct EncodingDecoder;
-
-/// Wire 56: Encoding_1_0 — single unconditional interned string, no flags.
-impl BodyDecoder for EncodingDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let mut table = kryo::StringInternTable::new();
-        let default_charset = table.read_string(body, &mut pos)?;
-
-        Ok(DecodedEvent::Encoding(EncodingEvent { default_charset }))
+event_schema! {
+    /// Wire 56: Encoding_1_0 — single unconditional interned string, no flags.
+    EncodingDecoder => Encoding(EncodingEvent) {
+        flags: none,
+        default_charset: required(InternedString),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_utf8() {
@@ -29,7 +25,7 @@ mod tests {
         let decoder = EncodingDecoder;
         let result = decoder.decode(&data).unwrap();
         if let DecodedEvent::Encoding(e) = result {
-            assert_eq!(e.default_charset, "UTF-8");
+            assert_eq!(&*e.default_charset, "UTF-8");
         } else {
             panic!("expected Encoding");
         }
@@ -1,30 +1,27 @@
-use error::ParseError;
+use kryo::{EnumOrdinal, InternedString, ListOf};
 
-use super::{BodyDecoder, DecodedEvent, FileRefRootEntry, FileRefRootsEvent};
+use super::{FileRefRootEntry, FileRefRootsEvent};
 
-pub struct FileRefRootsDecoder;
-
-/// Wire 49: FileRefRoots_1_0 — Map<Enum, String> with sorted keys, no flags.
-impl BodyDecoder for FileRefRootsDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let mut table = kryo::StringInternTable::new();
-
-        let count = varint::read_unsigned_varint(body, &mut pos)? as usize;
-        let mut entries = Vec::with_capacity(count);
-        for _ in 0..count {
-            let root_type = kryo::read_enum_ordinal(body, &mut pos)?;
-            let path = table.read_string(body, &mut pos)?;
-            entries.push(FileRefRootEntry { root_type, path });
-        }
+event_schema! {
+    /// Wire 49: FileRefRoots_1_0 — Map<Enum, String> with sorted keys, no flags.
+    FileRefRootsDecoder => FileRefRoots(FileRefRootsEvent) {
+        flags: none,
+        entries: required(ListOf<FileRefRootEntry>),
+    }
+}
 
-        Ok(DecodedEvent::FileRefRoots(FileRefRootsEvent { entries }))
+record_schema! {
+    FileRefRootEntry {
+        flags: none,
+        root_type: required(EnumOrdinal),
+        path: required(InternedString),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_two_entries() {
@@ -45,9 +42,9 @@ mod tests {
         if let DecodedEvent::FileRefRoots(e) = result {
             assert_eq!(e.entries.len(), 2);
             assert_eq!(e.entries[0].root_type, 0);
-            assert_eq!(e.entries[0].path, "/home/user/project");
+            assert_eq!(&*e.entries[0].path, "/home/user/project");
             assert_eq!(e.entries[1].root_type, 1);
-            assert_eq!(e.entries[1].path, "/home/user/.gradle");
+            assert_eq!(&*e.entries[1].path, "/home/user/.gradle");
         } else {
             panic!("expected FileRefRoots");
         }
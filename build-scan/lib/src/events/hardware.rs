@@ -1,22 +1,19 @@
-use error::ParseError;
+use kryo::PositiveI32;
 
-use super::{BodyDecoder, DecodedEvent, HardwareEvent};
+use super::HardwareEvent;
 
-pub struct HardwareDecoder;
-
-/// Wire 12: Hardware_1_0 — single unconditional int field, no flags.
-impl BodyDecoder for HardwareDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let num_processors = kryo::read_positive_varint_i32(body, &mut pos)?;
-
-        Ok(DecodedEvent::Hardware(HardwareEvent { num_processors }))
+event_schema! {
+    /// Wire 12: Hardware_1_0 — single unconditional int field, no flags.
+    HardwareDecoder => Hardware(HardwareEvent) {
+        flags: none,
+        num_processors: required(PositiveI32),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_num_processors() {
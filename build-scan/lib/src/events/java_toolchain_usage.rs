@@ -1,52 +1,28 @@
-use error::ParseError;
+use kryo::{InternedString, TaskId};
 
-use super::{BodyDecoder, DecodedEvent, JavaToolchainUsageEvent};
+use super::JavaToolchainUsageEvent;
 
-pub struct JavaToolchainUsageDecoder;
-
-impl BodyDecoder for JavaToolchainUsageDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let task_id = if kryo::is_field_present(flags as u16, 0) {
-            kryo::read_zigzag_i64(body, &mut pos)?
-        } else {
-            0
-        };
-
-        let toolchain_id = if kryo::is_field_present(flags as u16, 1) {
-            kryo::read_zigzag_i64(body, &mut pos)?
-        } else {
-            0
-        };
-
-        let tool_name = if kryo::is_field_present(flags as u16, 2) {
-            table.read_string(body, &mut pos)?
-        } else {
-            String::new()
-        };
-
-        Ok(DecodedEvent::JavaToolchainUsage(JavaToolchainUsageEvent {
-            task_id,
-            toolchain_id,
-            tool_name,
-        }))
+event_schema! {
+    JavaToolchainUsageDecoder => JavaToolchainUsage(JavaToolchainUsageEvent) {
+        flags: byte,
+        task_id: or_default(TaskId, 0),
+        toolchain_id: or_default(TaskId, 1),
+        tool_name: or_default(InternedString, 2),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use kryo::encode_zigzag_i64;
+    use crate::{BodyDecoder, DecodedEvent};
+    use kryo::write_task_id;
 
     #[test]
     fn test_decode_all_present() {
         // flags = 0x00: all three bits present
         let mut data = vec![0x00];
-        data.extend_from_slice(&encode_zigzag_i64(42)); // task_id = 42
-        data.extend_from_slice(&encode_zigzag_i64(7)); // toolchain_id = 7
+        write_task_id(&mut data, 42); // task_id = 42
+        write_task_id(&mut data, 7); // toolchain_id = 7
         // tool_name = "javac" → zigzag(5)=10, then chars
         data.push(0x0A);
         for &c in b"javac" {
@@ -58,7 +34,7 @@ mod tests {
         if let DecodedEvent::JavaToolchainUsage(e) = result {
             assert_eq!(e.task_id, 42);
             assert_eq!(e.toolchain_id, 7);
-            assert_eq!(e.tool_name, "javac");
+            assert_eq!(&*e.tool_name, "javac");
         } else {
             panic!("expected JavaToolchainUsage");
         }
@@ -73,7 +49,7 @@ mod tests {
         if let DecodedEvent::JavaToolchainUsage(e) = result {
             assert_eq!(e.task_id, 0);
             assert_eq!(e.toolchain_id, 0);
-            assert_eq!(e.tool_name, "");
+            assert_eq!(&*e.tool_name, "");
         } else {
             panic!("expected JavaToolchainUsage");
         }
@@ -1,87 +1,27 @@
-use error::ParseError;
-
-use super::{BodyDecoder, DecodedEvent, JvmEvent};
-
-pub struct JvmDecoder;
-
-/// Wire 14: Jvm_1_0 — 9 interned strings, flags as u16 (9 bits).
-impl BodyDecoder for JvmDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_u16_be(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let version = if kryo::is_field_present(flags, 0) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let vendor = if kryo::is_field_present(flags, 1) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let runtime_name = if kryo::is_field_present(flags, 2) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let runtime_version = if kryo::is_field_present(flags, 3) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let class_version = if kryo::is_field_present(flags, 4) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let vm_info = if kryo::is_field_present(flags, 5) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let vm_name = if kryo::is_field_present(flags, 6) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let vm_version = if kryo::is_field_present(flags, 7) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let vm_vendor = if kryo::is_field_present(flags, 8) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::Jvm(JvmEvent {
-            version,
-            vendor,
-            runtime_name,
-            runtime_version,
-            class_version,
-            vm_info,
-            vm_name,
-            vm_version,
-            vm_vendor,
-        }))
+use kryo::InternedString;
+
+use super::JvmEvent;
+
+event_schema! {
+    /// Wire 14: Jvm_1_0 — 9 interned strings, flags as u16 (9 bits).
+    JvmDecoder => Jvm(JvmEvent) {
+        flags: u16,
+        version: optional(InternedString, 0),
+        vendor: optional(InternedString, 1),
+        runtime_name: optional(InternedString, 2),
+        runtime_version: optional(InternedString, 3),
+        class_version: optional(InternedString, 4),
+        vm_info: optional(InternedString, 5),
+        vm_name: optional(InternedString, 6),
+        vm_version: optional(InternedString, 7),
+        vm_vendor: optional(InternedString, 8),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_absent() {
@@ -1,23 +1,21 @@
-use error::ParseError;
+use kryo::{InternedString, ListOf};
 
-use super::{BodyDecoder, DecodedEvent, JvmArgsEvent};
+use super::JvmArgsEvent;
 
-pub struct JvmArgsDecoder;
-
-/// Wire 13: JvmArgs_1_0 — unconditional List<String>, no flags.
-impl BodyDecoder for JvmArgsDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let mut table = kryo::StringInternTable::new();
-        let effective = kryo::read_list_of_interned_strings(body, &mut pos, &mut table)?;
-
-        Ok(DecodedEvent::JvmArgs(JvmArgsEvent { effective }))
+event_schema! {
+    /// Wire 13: JvmArgs_1_0 — unconditional List<String>, no flags.
+    JvmArgsDecoder => JvmArgs(JvmArgsEvent) {
+        flags: none,
+        effective: required(ListOf<InternedString>),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_two_args() {
@@ -32,7 +30,10 @@ mod tests {
         let decoder = JvmArgsDecoder;
         let result = decoder.decode(&data).unwrap();
         if let DecodedEvent::JvmArgs(e) = result {
-            assert_eq!(e.effective, vec!["-Xmx512m", "-Xms256m"]);
+            assert_eq!(
+                e.effective,
+                vec![Arc::from("-Xmx512m"), Arc::from("-Xms256m")]
+            );
         } else {
             panic!("expected JvmArgs");
         }
@@ -1,59 +1,23 @@
-use error::ParseError;
+use kryo::{InternedString, PositiveI32};
 
-use super::{BodyDecoder, DecodedEvent, LocalityEvent};
+use super::LocalityEvent;
 
-pub struct LocalityDecoder;
-
-/// Wire 15: Locality_1_0 — 4 interned strings + 1 int.
-impl BodyDecoder for LocalityDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let locale_language = if kryo::is_field_present(flags as u16, 0) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let locale_country = if kryo::is_field_present(flags as u16, 1) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let locale_variant = if kryo::is_field_present(flags as u16, 2) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let time_zone_id = if kryo::is_field_present(flags as u16, 3) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let time_zone_offset_millis = if kryo::is_field_present(flags as u16, 4) {
-            Some(kryo::read_positive_varint_i32(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::Locality(LocalityEvent {
-            locale_language,
-            locale_country,
-            locale_variant,
-            time_zone_id,
-            time_zone_offset_millis,
-        }))
+event_schema! {
+    /// Wire 15: Locality_1_0 — 4 interned strings + 1 int.
+    LocalityDecoder => Locality(LocalityEvent) {
+        flags: byte,
+        locale_language: optional(InternedString, 0),
+        locale_country: optional(InternedString, 1),
+        locale_variant: optional(InternedString, 2),
+        time_zone_id: optional(InternedString, 3),
+        time_zone_offset_millis: optional(PositiveI32, 4),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
     use kryo::encode_unsigned_varint;
 
     #[test]
@@ -1,7 +1,11 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use error::ParseError;
 
+#[macro_use]
+mod schema;
+
 pub mod basic_memory_stats;
 pub mod build_agent;
 pub mod build_finished;
@@ -37,12 +41,21 @@ pub mod transform_execution_request;
 pub mod transform_execution_started;
 pub mod transform_identification;
 
+/// Decoded events borrow from the frame body only through `DecodedEvent::Raw`; every
+/// typed event owns its data, with interned strings shared as `Arc<str>`.
 pub trait BodyDecoder: Send + Sync {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError>;
+    fn decode<'a>(&self, body: &'a [u8]) -> Result<DecodedEvent<'a>, ParseError>;
+}
+
+/// Inverse of `BodyDecoder`, generated for every decoder declared with `event_schema!`.
+pub trait BodyEncoder {
+    type Event;
+
+    fn encode(&self, event: &Self::Event) -> Vec<u8>;
 }
 
-#[derive(Debug, Clone)]
-pub enum DecodedEvent {
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedEvent<'a> {
     TaskIdentity(TaskIdentityEvent),
     TaskStarted(TaskStartedEvent),
     TaskFinished(TaskFinishedEvent),
@@ -77,51 +90,61 @@ pub enum DecodedEvent {
     TaskRegistrationSummary(TaskRegistrationSummaryEvent),
     BasicMemoryStats(BasicMemoryStatsEvent),
     ResourceUsage(ResourceUsageEvent),
-    Raw(RawEvent),
+    Raw(RawEvent<'a>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskIdentityEvent {
     pub id: i64,
-    pub build_path: String,
-    pub task_path: String,
+    pub build_path: Arc<str>,
+    pub task_path: Arc<str>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskStartedEvent {
     pub id: i64,
-    pub build_path: String,
-    pub path: String,
-    pub class_name: Option<String>,
+    pub build_path: Arc<str>,
+    pub path: Arc<str>,
+    pub class_name: Option<Arc<str>>,
+    pub parent: Option<ConfigurationParentRef>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigurationParentRef {
+    pub parent_type: Option<u64>,
+    pub id: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskFinishedEvent {
     pub id: i64,
-    pub path: String,
+    pub path: Arc<str>,
     pub outcome: Option<u64>,
+    pub skip_message: Option<Arc<str>>,
     pub cacheable: Option<bool>,
-    pub caching_disabled_reason_category: Option<String>,
-    pub caching_disabled_explanation: Option<String>,
-    pub origin_build_invocation_id: Option<String>,
+    pub caching_disabled_reason_category: Option<Arc<str>>,
+    pub caching_disabled_explanation: Option<Arc<str>>,
+    pub origin_build_invocation_id: Option<Arc<str>>,
     pub origin_build_cache_key: Option<Vec<u8>>,
+    pub origin_execution_time: Option<i64>,
     pub actionable: Option<bool>,
-    pub skip_reason_message: Option<String>,
+    pub up_to_date_messages: Vec<Arc<str>>,
+    pub skip_reason_message: Option<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskInputsSnapshottingStartedEvent {
     pub task: i64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TransformExecutionRequestEvent {
     pub node_id: Option<i64>,
     pub identification_id: Option<i64>,
     pub execution_id: Option<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PlannedNodeEvent {
     pub id: Option<i64>,
     pub dependencies: Vec<i64>,
@@ -130,44 +153,44 @@ pub struct PlannedNodeEvent {
     pub finalized_by: Vec<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskInputsValuePropertiesEvent {
     pub id: Option<i64>,
     pub hashes: Vec<Vec<u8>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskInputsPropertyNamesEvent {
     pub id: Option<i64>,
-    pub value_inputs: Vec<String>,
-    pub file_inputs: Vec<String>,
-    pub outputs: Vec<String>,
+    pub value_inputs: Vec<Arc<str>>,
+    pub file_inputs: Vec<Arc<str>>,
+    pub outputs: Vec<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskInputsImplementationEvent {
     pub id: Option<i64>,
     pub class_loader_hash: Option<Vec<u8>>,
     pub action_class_loader_hashes: Vec<Vec<u8>>,
-    pub action_class_names: Vec<String>,
+    pub action_class_names: Vec<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskInputsFilePropertyEvent {
     pub id: Option<i64>,
-    pub attributes: Vec<String>,
+    pub attributes: Vec<Arc<str>>,
     pub hash: Option<Vec<u8>>,
     pub roots: Vec<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskInputsSnapshottingFinishedEvent {
     pub task: Option<i64>,
     pub result: Option<TaskInputsSnapshottingResult>,
     pub failure_id: Option<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskInputsSnapshottingResult {
     pub hash: Option<Vec<u8>>,
     pub implementation: Option<i64>,
@@ -176,7 +199,7 @@ pub struct TaskInputsSnapshottingResult {
     pub file_inputs: Vec<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskInputsFilePropertyRootEvent {
     pub id: Option<i64>,
     pub file: FileRef,
@@ -184,89 +207,89 @@ pub struct TaskInputsFilePropertyRootEvent {
     pub children: Vec<FilePropertyRootChild>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FileRef {
     pub root: Option<u64>,
-    pub path: Option<String>,
+    pub path: Option<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FilePropertyRootChild {
-    pub name: Option<String>,
+    pub name: Option<Arc<str>>,
     pub hash: Option<Vec<u8>>,
     pub parent: Option<i32>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct JavaToolchainUsageEvent {
     pub task_id: i64,
     pub toolchain_id: i64,
-    pub tool_name: String,
+    pub tool_name: Arc<str>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TransformExecutionStartedEvent {
     pub id: i64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TransformIdentificationEvent {
     pub id: i64,
     pub component_identity: i32,
-    pub input_artifact_name: String,
-    pub transform_action_class: String,
+    pub input_artifact_name: Arc<str>,
+    pub transform_action_class: Arc<str>,
     pub from_attributes: Vec<i32>,
     pub to_attributes: Vec<i32>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TransformExecutionFinishedEvent {
     pub id: i64,
     pub failure_id: Option<i64>,
     pub outcome: Option<u64>,
-    pub execution_reasons: Vec<String>,
-    pub caching_disabled_reason_category: Option<String>,
-    pub caching_disabled_explanation: Option<String>,
-    pub origin_build_invocation_id: Option<String>,
+    pub execution_reasons: Vec<Arc<str>>,
+    pub caching_disabled_reason_category: Option<Arc<str>>,
+    pub caching_disabled_explanation: Option<Arc<str>>,
+    pub origin_build_invocation_id: Option<Arc<str>>,
     pub origin_build_cache_key: Option<Vec<u8>>,
     pub origin_execution_time: Option<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct OutputStyledTextEvent {
-    pub category: Option<String>,
-    pub log_level: Option<String>,
+    pub category: Option<Arc<str>>,
+    pub log_level: Option<Arc<str>>,
     pub spans: Vec<OutputSpan>,
     pub owner_type: Option<u64>,
-    pub owner_id: Option<String>,
+    pub owner_id: Option<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct OutputSpan {
-    pub text: String,
-    pub style: Option<String>,
+    pub text: Arc<str>,
+    pub style: Option<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BuildAgentEvent {
-    pub username: Option<String>,
-    pub local_hostname: Option<String>,
-    pub public_hostname: Option<String>,
-    pub ip_addresses: Vec<String>,
+    pub username: Option<Arc<str>>,
+    pub local_hostname: Option<Arc<str>>,
+    pub public_hostname: Option<Arc<str>>,
+    pub ip_addresses: Vec<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BuildRequestedTasksEvent {
-    pub requested: Vec<String>,
-    pub excluded: Vec<String>,
+    pub requested: Vec<Arc<str>>,
+    pub excluded: Vec<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BuildFinishedEvent {
     pub failure_id: Option<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BuildModesEvent {
     pub refresh_dependencies: bool,
     pub parallel_project_execution: bool,
@@ -280,7 +303,7 @@ pub struct BuildModesEvent {
     pub max_workers: Option<i32>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DaemonStateEvent {
     pub start_time: Option<i64>,
     pub build_number: Option<i32>,
@@ -289,75 +312,75 @@ pub struct DaemonStateEvent {
     pub single_use: Option<bool>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EncodingEvent {
-    pub default_charset: String,
+    pub default_charset: Arc<str>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FileRefRootsEvent {
     pub entries: Vec<FileRefRootEntry>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FileRefRootEntry {
     pub root_type: u64,
-    pub path: String,
+    pub path: Arc<str>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct HardwareEvent {
     pub num_processors: i32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct JvmEvent {
-    pub version: Option<String>,
-    pub vendor: Option<String>,
-    pub runtime_name: Option<String>,
-    pub runtime_version: Option<String>,
-    pub class_version: Option<String>,
-    pub vm_info: Option<String>,
-    pub vm_name: Option<String>,
-    pub vm_version: Option<String>,
-    pub vm_vendor: Option<String>,
-}
-
-#[derive(Debug, Clone)]
+    pub version: Option<Arc<str>>,
+    pub vendor: Option<Arc<str>>,
+    pub runtime_name: Option<Arc<str>>,
+    pub runtime_version: Option<Arc<str>>,
+    pub class_version: Option<Arc<str>>,
+    pub vm_info: Option<Arc<str>>,
+    pub vm_name: Option<Arc<str>>,
+    pub vm_version: Option<Arc<str>>,
+    pub vm_vendor: Option<Arc<str>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct JvmArgsEvent {
-    pub effective: Vec<String>,
+    pub effective: Vec<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LocalityEvent {
-    pub locale_language: Option<String>,
-    pub locale_country: Option<String>,
-    pub locale_variant: Option<String>,
-    pub time_zone_id: Option<String>,
+    pub locale_language: Option<Arc<str>>,
+    pub locale_country: Option<Arc<str>>,
+    pub locale_variant: Option<Arc<str>>,
+    pub time_zone_id: Option<Arc<str>>,
     pub time_zone_offset_millis: Option<i32>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct OsEvent {
-    pub family: Option<String>,
-    pub name: Option<String>,
-    pub version: Option<String>,
-    pub arch: Option<String>,
+    pub family: Option<Arc<str>>,
+    pub name: Option<Arc<str>>,
+    pub version: Option<Arc<str>>,
+    pub arch: Option<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ScopeIdsEvent {
-    pub build_invocation_id: Option<String>,
-    pub workspace_id: Option<String>,
-    pub user_id: Option<String>,
+    pub build_invocation_id: Option<Arc<str>>,
+    pub workspace_id: Option<Arc<str>>,
+    pub user_id: Option<Arc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskRegistrationSummaryEvent {
     pub task_count: i32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BasicMemoryStatsEvent {
     pub free: Option<i64>,
     pub total: Option<i64>,
@@ -366,9 +389,9 @@ pub struct BasicMemoryStatsEvent {
     pub gc_time: Option<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MemoryPoolSnapshotEvent {
-    pub name: Option<String>,
+    pub name: Option<Arc<str>>,
     pub heap: bool,
     pub init: Option<i64>,
     pub used: Option<i64>,
@@ -376,7 +399,7 @@ pub struct MemoryPoolSnapshotEvent {
     pub max: Option<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ResourceUsageEvent {
     pub timestamps: Vec<Vec<u8>>,
     pub build_process_cpu: NormalizedSamplesEvent,
@@ -396,31 +419,31 @@ pub struct ResourceUsageEvent {
     pub top_processes_by_memory: IndexedNormalizedSamplesEvent,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct NormalizedSamplesEvent {
     pub samples: Option<Vec<u8>>,
     pub max: Option<i64>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct IndexedNormalizedSamplesEvent {
     pub indices: Vec<Vec<i32>>,
     pub samples: Vec<Vec<u8>>,
     pub max: Option<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ProcessEvent {
     pub id: Option<i64>,
-    pub name: Option<String>,
-    pub display_name: Option<String>,
+    pub name: Option<Arc<str>>,
+    pub display_name: Option<Arc<str>>,
     pub process_type: Option<u64>,
 }
 
-#[derive(Debug, Clone)]
-pub struct RawEvent {
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawEvent<'a> {
     pub wire_id: u16,
-    pub body: Vec<u8>,
+    pub body: &'a [u8],
 }
 
 pub struct DecoderRegistry {
@@ -524,13 +547,42 @@ impl DecoderRegistry {
         self.decoders.insert(wire_id, decoder);
     }
 
-    pub fn decode(&self, wire_id: u16, body: &[u8]) -> Result<DecodedEvent, ParseError> {
+    pub fn decode<'a>(&self, wire_id: u16, body: &'a [u8]) -> Result<DecodedEvent<'a>, ParseError> {
         match self.decoders.get(&wire_id) {
-            Some(decoder) => decoder.decode(body),
-            None => Ok(DecodedEvent::Raw(RawEvent {
-                wire_id,
-                body: body.to_vec(),
-            })),
+            Some(decoder) => decoder
+                .decode(body)
+                .map_err(|e| ParseError::InvalidEventBody {
+                    wire_id,
+                    event: wire_ids::describe(wire_id),
+                    source: Box::new(e),
+                }),
+            None => Ok(DecodedEvent::Raw(RawEvent { wire_id, body })),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_wire_ids_are_catalogued() {
+        let registry = DecoderRegistry::new();
+        for wire_id in registry.decoders.keys() {
+            assert!(
+                wire_ids::lookup(*wire_id).is_some(),
+                "wire {wire_id} has a decoder but no catalog entry"
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_error_names_event() {
+        let err = DecoderRegistry::new().decode(265, &[]).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::InvalidEventBody { wire_id: 265, .. }
+        ));
+        assert!(err.to_string().contains("DaemonState_1_1"), "{err}");
+    }
+}
@@ -1,52 +1,22 @@
-use error::ParseError;
-
-use super::{BodyDecoder, DecodedEvent, OsEvent};
-
-pub struct OsDecoder;
-
-/// Wire 16: Os_1_0 — 4 interned strings.
-impl BodyDecoder for OsDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let family = if kryo::is_field_present(flags as u16, 0) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let name = if kryo::is_field_present(flags as u16, 1) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let version = if kryo::is_field_present(flags as u16, 2) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let arch = if kryo::is_field_present(flags as u16, 3) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::Os(OsEvent {
-            family,
-            name,
-            version,
-            arch,
-        }))
+use kryo::InternedString;
+
+use super::OsEvent;
+
+event_schema! {
+    /// Wire 16: Os_1_0 — 4 interned strings.
+    OsDecoder => Os(OsEvent) {
+        flags: byte,
+        family: optional(InternedString, 0),
+        name: optional(InternedString, 1),
+        version: optional(InternedString, 2),
+        arch: optional(InternedString, 3),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, BodyEncoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_present() {
@@ -68,4 +38,23 @@ mod tests {
             panic!("expected Os");
         }
     }
+
+    #[test]
+    fn test_encode_matches_wire_bytes() {
+        let event = OsEvent {
+            family: Some("linux".into()),
+            name: None,
+            version: Some("6.1.0".into()),
+            arch: None,
+        };
+        // flags: bits 1 and 3 absent → 0x0A
+        let mut expected = vec![0x0A, 0x0A];
+        expected.extend_from_slice(b"linux");
+        expected.push(0x0A);
+        expected.extend_from_slice(b"6.1.0");
+
+        let encoded = OsDecoder.encode(&event);
+        assert_eq!(encoded, expected);
+        assert_eq!(OsDecoder.decode(&encoded).unwrap(), DecodedEvent::Os(event));
+    }
 }
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use error::ParseError;
 
 use super::{BodyDecoder, DecodedEvent, OutputSpan, OutputStyledTextEvent};
@@ -5,7 +7,7 @@ use super::{BodyDecoder, DecodedEvent, OutputSpan, OutputStyledTextEvent};
 pub struct OutputStyledTextEventDecoder;
 
 impl BodyDecoder for OutputStyledTextEventDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
+    fn decode<'a>(&self, body: &'a [u8]) -> Result<DecodedEvent<'a>, ParseError> {
         let mut pos = 0;
         // Shared intern table across the composite event
         let mut table = kryo::StringInternTable::new();
@@ -30,15 +32,15 @@ impl BodyDecoder for OutputStyledTextEventDecoder {
 
         // bit 0: spans → list of OutputSpan
         let spans = if kryo::is_field_present(flags as u16, 0) {
-            let len = varint::read_unsigned_varint(body, &mut pos)? as usize;
-            let mut result = Vec::with_capacity(len);
+            let len = kryo::read_length(body, &mut pos)?;
+            let mut result = Vec::new();
             for _ in 0..len {
                 // Each span has its own flags_byte (2 bits)
                 let span_flags = kryo::read_flags_byte(body, &mut pos)?;
                 let text = if kryo::is_field_present(span_flags as u16, 0) {
                     table.read_string(body, &mut pos)?
                 } else {
-                    String::new()
+                    Arc::from("")
                 };
                 let style = if kryo::is_field_present(span_flags as u16, 1) {
                     Some(table.read_string(body, &mut pos)?)
@@ -117,13 +119,13 @@ mod tests {
         let decoder = OutputStyledTextEventDecoder;
         let result = decoder.decode(&data).unwrap();
         if let DecodedEvent::OutputStyledText(e) = result {
-            assert_eq!(e.category, Some("LIFECYCLE".to_string()));
-            assert_eq!(e.log_level, Some("INFO".to_string()));
+            assert_eq!(e.category.as_deref(), Some("LIFECYCLE"));
+            assert_eq!(e.log_level.as_deref(), Some("INFO"));
             assert_eq!(e.spans.len(), 1);
-            assert_eq!(e.spans[0].text, "hello");
-            assert_eq!(e.spans[0].style, Some("bold".to_string()));
+            assert_eq!(&*e.spans[0].text, "hello");
+            assert_eq!(e.spans[0].style.as_deref(), Some("bold"));
             assert_eq!(e.owner_type, Some(1));
-            assert_eq!(e.owner_id, Some("task1".to_string()));
+            assert_eq!(e.owner_id.as_deref(), Some("task1"));
         } else {
             panic!("expected OutputStyledText");
         }
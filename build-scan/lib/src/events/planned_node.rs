@@ -1,53 +1,22 @@
-use error::ParseError;
-
-use super::{BodyDecoder, DecodedEvent, PlannedNodeEvent};
-
-pub struct PlannedNodeDecoder;
-
-impl BodyDecoder for PlannedNodeDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-
-        let id = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-        let dependencies = if kryo::is_field_present(flags as u16, 1) {
-            kryo::read_list_of_i64(body, &mut pos)?
-        } else {
-            vec![]
-        };
-        let must_run_after = if kryo::is_field_present(flags as u16, 2) {
-            kryo::read_list_of_i64(body, &mut pos)?
-        } else {
-            vec![]
-        };
-        let should_run_after = if kryo::is_field_present(flags as u16, 3) {
-            kryo::read_list_of_i64(body, &mut pos)?
-        } else {
-            vec![]
-        };
-        let finalized_by = if kryo::is_field_present(flags as u16, 4) {
-            kryo::read_list_of_i64(body, &mut pos)?
-        } else {
-            vec![]
-        };
-
-        Ok(DecodedEvent::PlannedNode(PlannedNodeEvent {
-            id,
-            dependencies,
-            must_run_after,
-            should_run_after,
-            finalized_by,
-        }))
+use kryo::{ListOf, TaskId};
+
+use super::PlannedNodeEvent;
+
+event_schema! {
+    PlannedNodeDecoder => PlannedNode(PlannedNodeEvent) {
+        flags: byte,
+        id: optional(TaskId, 0),
+        dependencies: or_default(ListOf<TaskId>, 1),
+        must_run_after: or_default(ListOf<TaskId>, 2),
+        should_run_after: or_default(ListOf<TaskId>, 3),
+        finalized_by: or_default(ListOf<TaskId>, 4),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_with_dependencies() {
@@ -1,179 +1,64 @@
-use error::ParseError;
+use kryo::{ByteArray, EnumOrdinal, InternedString, ListOf, PositiveI32, ZigzagI64};
 
 use super::{
-    BodyDecoder, DecodedEvent, IndexedNormalizedSamplesEvent, NormalizedSamplesEvent, ProcessEvent,
-    ResourceUsageEvent,
+    IndexedNormalizedSamplesEvent, NormalizedSamplesEvent, ProcessEvent, ResourceUsageEvent,
 };
 
-pub struct ResourceUsageDecoder;
-
-/// Wire 407: ResourceUsage_2_0 — complex event with 4 conditional + 12 unconditional sub-fields.
-impl BodyDecoder for ResourceUsageDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let timestamps = if kryo::is_field_present(flags as u16, 0) {
-            kryo::read_list_of_byte_arrays(body, &mut pos)?
-        } else {
-            vec![]
-        };
-
-        let build_process_cpu = decode_normalized_samples(body, &mut pos)?;
-        let build_child_processes_cpu = decode_normalized_samples(body, &mut pos)?;
-        let all_processes_cpu_sum = decode_normalized_samples(body, &mut pos)?;
-
-        let all_processes_cpu = if kryo::is_field_present(flags as u16, 1) {
-            Some(kryo::read_byte_array(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let build_process_memory = decode_normalized_samples(body, &mut pos)?;
-        let build_child_processes_memory = decode_normalized_samples(body, &mut pos)?;
-        let all_processes_memory = decode_normalized_samples(body, &mut pos)?;
-
-        let total_system_memory = if kryo::is_field_present(flags as u16, 2) {
-            Some(kryo::read_zigzag_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let disk_read_speed = decode_normalized_samples(body, &mut pos)?;
-        let disk_write_speed = decode_normalized_samples(body, &mut pos)?;
-        let network_download_speed = decode_normalized_samples(body, &mut pos)?;
-        let network_upload_speed = decode_normalized_samples(body, &mut pos)?;
-
-        let processes = if kryo::is_field_present(flags as u16, 3) {
-            let count = kryo::read_positive_varint_i32(body, &mut pos)? as usize;
-            let mut procs = Vec::with_capacity(count);
-            for _ in 0..count {
-                procs.push(decode_process(body, &mut pos, &mut table)?);
-            }
-            procs
-        } else {
-            vec![]
-        };
-
-        let top_processes_by_cpu = decode_indexed_normalized_samples(body, &mut pos)?;
-        let top_processes_by_memory = decode_indexed_normalized_samples(body, &mut pos)?;
-
-        Ok(DecodedEvent::ResourceUsage(ResourceUsageEvent {
-            timestamps,
-            build_process_cpu,
-            build_child_processes_cpu,
-            all_processes_cpu_sum,
-            all_processes_cpu,
-            build_process_memory,
-            build_child_processes_memory,
-            all_processes_memory,
-            total_system_memory,
-            disk_read_speed,
-            disk_write_speed,
-            network_download_speed,
-            network_upload_speed,
-            processes,
-            top_processes_by_cpu,
-            top_processes_by_memory,
-        }))
+event_schema! {
+    /// Wire 407: ResourceUsage_2_0 — complex event with 4 conditional + 12 unconditional sub-fields.
+    ResourceUsageDecoder => ResourceUsage(ResourceUsageEvent) {
+        flags: byte,
+        timestamps: or_default(ListOf<ByteArray>, 0),
+        build_process_cpu: required(NormalizedSamplesEvent),
+        build_child_processes_cpu: required(NormalizedSamplesEvent),
+        all_processes_cpu_sum: required(NormalizedSamplesEvent),
+        all_processes_cpu: optional(ByteArray, 1),
+        build_process_memory: required(NormalizedSamplesEvent),
+        build_child_processes_memory: required(NormalizedSamplesEvent),
+        all_processes_memory: required(NormalizedSamplesEvent),
+        total_system_memory: optional(ZigzagI64, 2),
+        disk_read_speed: required(NormalizedSamplesEvent),
+        disk_write_speed: required(NormalizedSamplesEvent),
+        network_download_speed: required(NormalizedSamplesEvent),
+        network_upload_speed: required(NormalizedSamplesEvent),
+        processes: or_default(ListOf<ProcessEvent>, 3),
+        top_processes_by_cpu: required(IndexedNormalizedSamplesEvent),
+        top_processes_by_memory: required(IndexedNormalizedSamplesEvent),
     }
 }
 
-fn decode_normalized_samples(
-    body: &[u8],
-    pos: &mut usize,
-) -> Result<NormalizedSamplesEvent, ParseError> {
-    let flags = kryo::read_flags_byte(body, pos)?;
-
-    let samples = if kryo::is_field_present(flags as u16, 0) {
-        Some(kryo::read_byte_array(body, pos)?)
-    } else {
-        None
-    };
-
-    let max = if kryo::is_field_present(flags as u16, 1) {
-        Some(kryo::read_zigzag_i64(body, pos)?)
-    } else {
-        None
-    };
-
-    Ok(NormalizedSamplesEvent { samples, max })
+record_schema! {
+    NormalizedSamplesEvent {
+        flags: byte,
+        samples: optional(ByteArray, 0),
+        max: optional(ZigzagI64, 1),
+    }
 }
 
-fn decode_indexed_normalized_samples(
-    body: &[u8],
-    pos: &mut usize,
-) -> Result<IndexedNormalizedSamplesEvent, ParseError> {
-    let flags = kryo::read_flags_byte(body, pos)?;
-
-    let indices = if kryo::is_field_present(flags as u16, 0) {
-        kryo::read_list_of_list_of_i32(body, pos)?
-    } else {
-        vec![]
-    };
-
-    let samples = if kryo::is_field_present(flags as u16, 1) {
-        kryo::read_list_of_byte_arrays(body, pos)?
-    } else {
-        vec![]
-    };
-
-    let max = if kryo::is_field_present(flags as u16, 2) {
-        Some(kryo::read_zigzag_i64(body, pos)?)
-    } else {
-        None
-    };
-
-    Ok(IndexedNormalizedSamplesEvent {
-        indices,
-        samples,
-        max,
-    })
+record_schema! {
+    IndexedNormalizedSamplesEvent {
+        flags: byte,
+        indices: or_default(ListOf<ListOf<PositiveI32>>, 0),
+        samples: or_default(ListOf<ByteArray>, 1),
+        max: optional(ZigzagI64, 2),
+    }
 }
 
-fn decode_process(
-    body: &[u8],
-    pos: &mut usize,
-    table: &mut kryo::StringInternTable,
-) -> Result<ProcessEvent, ParseError> {
-    let flags = kryo::read_flags_byte(body, pos)?;
-
-    let id = if kryo::is_field_present(flags as u16, 0) {
-        Some(kryo::read_zigzag_i64(body, pos)?)
-    } else {
-        None
-    };
-
-    let name = if kryo::is_field_present(flags as u16, 1) {
-        Some(table.read_string(body, pos)?)
-    } else {
-        None
-    };
-
-    let display_name = if kryo::is_field_present(flags as u16, 2) {
-        Some(table.read_string(body, pos)?)
-    } else {
-        None
-    };
-
-    let process_type = if kryo::is_field_present(flags as u16, 3) {
-        Some(kryo::read_enum_ordinal(body, pos)?)
-    } else {
-        None
-    };
-
-    Ok(ProcessEvent {
-        id,
-        name,
-        display_name,
-        process_type,
-    })
+record_schema! {
+    ProcessEvent {
+        flags: byte,
+        id: optional(ZigzagI64, 0),
+        name: optional(InternedString, 1),
+        display_name: optional(InternedString, 2),
+        process_type: optional(EnumOrdinal, 3),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
+    use kryo::Codec;
 
     fn make_normalized_samples_all_absent() -> Vec<u8> {
         // flags = 0b11 → both bits set → samples absent, max absent
@@ -281,7 +166,7 @@ mod tests {
             assert_eq!(e.all_processes_cpu, Some(vec![0xAA]));
             assert_eq!(e.total_system_memory, Some(8192));
             assert_eq!(e.processes.len(), 1);
-            assert_eq!(e.processes[0].name, Some("java".to_string()));
+            assert_eq!(e.processes[0].name.as_deref(), Some("java"));
             assert_eq!(e.processes[0].id, None);
             assert_eq!(e.processes[0].display_name, None);
             assert_eq!(e.processes[0].process_type, None);
@@ -302,7 +187,9 @@ mod tests {
         data.push(0x54);
 
         let mut pos = 0;
-        let result = decode_normalized_samples(&data, &mut pos).unwrap();
+        let result =
+            NormalizedSamplesEvent::read(&data, &mut pos, &mut kryo::StringInternTable::new())
+                .unwrap();
         assert_eq!(result.samples, Some(vec![0xAA, 0xBB]));
         assert_eq!(result.max, Some(42));
         assert_eq!(pos, 5); // flags(1) + len_prefix(1) + 2 bytes + zigzag_max(1) = 5
@@ -327,7 +214,12 @@ mod tests {
         data.push(0x01);
 
         let mut pos = 0;
-        let result = decode_indexed_normalized_samples(&data, &mut pos).unwrap();
+        let result = IndexedNormalizedSamplesEvent::read(
+            &data,
+            &mut pos,
+            &mut kryo::StringInternTable::new(),
+        )
+        .unwrap();
         assert_eq!(result.indices, vec![vec![10, 20]]);
         assert_eq!(result.samples, vec![vec![0xAA, 0xBB]]);
         assert_eq!(result.max, Some(99));
@@ -351,10 +243,10 @@ mod tests {
 
         let mut pos = 0;
         let mut table = kryo::StringInternTable::new();
-        let result = decode_process(&data, &mut pos, &mut table).unwrap();
+        let result = ProcessEvent::read(&data, &mut pos, &mut table).unwrap();
         assert_eq!(result.id, Some(5));
-        assert_eq!(result.name, Some("foo".to_string()));
-        assert_eq!(result.display_name, Some("foo".to_string()));
+        assert_eq!(result.name.as_deref(), Some("foo"));
+        assert_eq!(result.display_name.as_deref(), Some("foo"));
         assert_eq!(result.process_type, Some(2));
     }
 
@@ -364,7 +256,7 @@ mod tests {
         let data = vec![0x0Fu8];
         let mut pos = 0;
         let mut table = kryo::StringInternTable::new();
-        let result = decode_process(&data, &mut pos, &mut table).unwrap();
+        let result = ProcessEvent::read(&data, &mut pos, &mut table).unwrap();
         assert_eq!(result.id, None);
         assert_eq!(result.name, None);
         assert_eq!(result.display_name, None);
@@ -0,0 +1,208 @@
+//! Declarative body layouts.
+//!
+//! `record_schema!` describes a Kryo-serialized struct as an ordered list of fields and
+//! implements `kryo::Codec` for it, so the same table drives both reading and writing.
+//! `event_schema!` does the same for a top-level event and additionally generates its
+//! `BodyDecoder` / `BodyEncoder` pair.
+//!
+//! ```ignore
+//! event_schema! {
+//!     /// Wire 16: Os_1_0 — 4 interned strings.
+//!     OsDecoder => Os(OsEvent) {
+//!         flags: byte,
+//!         family: optional(InternedString, 0),
+//!         name: optional(InternedString, 1),
+//!     }
+//! }
+//! ```
+//!
+//! `flags` is the presence bitmap width: `byte` (`read_flags_byte`), `u16`
+//! (`read_flags_u16_be`) or `none` (every field unconditional). Fields are read in
+//! declaration order, one of:
+//!
+//! - `required(Codec)` — always written, no flag bit
+//! - `optional(Codec, bit)` — `Option<T>`, `None` when the bit marks it absent
+//! - `or_default(Codec, bit)` — `T`, `Default::default()` when absent; written as absent
+//!   when equal to the default
+//! - `flag(bit)` — `bool` carried by the bit itself, no payload
+//! - `flag_or_none(bit)` — `Option<bool>`: `Some(true)` when the bit is clear, else `None`
+//! - `flag_or_false(bit)` — `Option<bool>`: always `Some`, value carried by the bit
+//!
+//! Bodies that don't map onto a single struct (the empty `BuildStarted` marker,
+//! `OutputStyledText`'s flattened sub-object) stay hand-written.
+
+macro_rules! event_schema {
+    (
+        $(#[$meta:meta])*
+        $decoder:ident => $variant:ident($event:ident) { $($layout:tt)* }
+    ) => {
+        $(#[$meta])*
+        pub struct $decoder;
+
+        impl $crate::BodyDecoder for $decoder {
+            fn decode<'a>(
+                &self,
+                body: &'a [u8],
+            ) -> Result<$crate::DecodedEvent<'a>, ::error::ParseError> {
+                let mut pos = 0;
+                let mut table = ::kryo::StringInternTable::new();
+                let event = <$event as ::kryo::Codec>::read(body, &mut pos, &mut table)?;
+                Ok($crate::DecodedEvent::$variant(event))
+            }
+        }
+
+        impl $crate::BodyEncoder for $decoder {
+            type Event = $event;
+
+            fn encode(&self, event: &$event) -> Vec<u8> {
+                let mut out = Vec::new();
+                let mut table = ::kryo::StringInternWriter::new();
+                <$event as ::kryo::Codec>::write(event, &mut out, &mut table);
+                out
+            }
+        }
+
+        record_schema! { $event { $($layout)* } }
+    };
+}
+
+macro_rules! record_schema {
+    (
+        $record:ident {
+            flags: none,
+            $($field:ident: required($codec:ty)),* $(,)?
+        }
+    ) => {
+        impl ::kryo::Codec for $record {
+            type Value = Self;
+
+            fn read(
+                data: &[u8],
+                pos: &mut usize,
+                table: &mut ::kryo::StringInternTable,
+            ) -> Result<Self, ::error::ParseError> {
+                $(let $field = <$codec as ::kryo::Codec>::read(data, pos, table)?;)*
+                Ok(Self { $($field),* })
+            }
+
+            fn write(value: &Self, out: &mut Vec<u8>, table: &mut ::kryo::StringInternWriter) {
+                $(<$codec as ::kryo::Codec>::write(&value.$field, out, table);)*
+            }
+        }
+    };
+    (
+        $record:ident {
+            flags: $width:ident,
+            $($field:ident: $kind:ident($($arg:tt)*)),* $(,)?
+        }
+    ) => {
+        impl ::kryo::Codec for $record {
+            type Value = Self;
+
+            fn read(
+                data: &[u8],
+                pos: &mut usize,
+                table: &mut ::kryo::StringInternTable,
+            ) -> Result<Self, ::error::ParseError> {
+                let flags = schema_flags!(read $width, data, pos);
+                $(let $field = schema_field!(read $kind($($arg)*), flags, data, pos, table);)*
+                Ok(Self { $($field),* })
+            }
+
+            fn write(value: &Self, out: &mut Vec<u8>, table: &mut ::kryo::StringInternWriter) {
+                let mut flags: u16 = 0;
+                $(schema_field!(mark $kind($($arg)*), flags, value.$field);)*
+                schema_flags!(write $width, out, flags);
+                $(schema_field!(write $kind($($arg)*), flags, out, table, value.$field);)*
+            }
+        }
+    };
+}
+
+macro_rules! schema_flags {
+    (read byte, $data:expr, $pos:expr) => {
+        ::kryo::read_flags_byte($data, $pos)? as u16
+    };
+    (read u16, $data:expr, $pos:expr) => {
+        ::kryo::read_flags_u16_be($data, $pos)?
+    };
+    (write byte, $out:expr, $flags:expr) => {
+        ::kryo::write_flags_byte($out, $flags as u8)
+    };
+    (write u16, $out:expr, $flags:expr) => {
+        ::kryo::write_flags_u16_be($out, $flags)
+    };
+}
+
+/// Per-field read / mark-absent / write rules. `mark` runs for every field before the
+/// flags are written; `write` then emits payloads for the fields left present.
+macro_rules! schema_field {
+    (read required($codec:ty), $flags:expr, $data:expr, $pos:expr, $table:expr) => {
+        <$codec as ::kryo::Codec>::read($data, $pos, $table)?
+    };
+    (read optional($codec:ty, $bit:literal), $flags:expr, $data:expr, $pos:expr, $table:expr) => {
+        if ::kryo::is_field_present($flags, $bit) {
+            Some(<$codec as ::kryo::Codec>::read($data, $pos, $table)?)
+        } else {
+            None
+        }
+    };
+    (read or_default($codec:ty, $bit:literal), $flags:expr, $data:expr, $pos:expr, $table:expr) => {
+        if ::kryo::is_field_present($flags, $bit) {
+            <$codec as ::kryo::Codec>::read($data, $pos, $table)?
+        } else {
+            Default::default()
+        }
+    };
+    (read flag($bit:literal), $flags:expr, $data:expr, $pos:expr, $table:expr) => {
+        ::kryo::is_field_present($flags, $bit)
+    };
+    (read flag_or_none($bit:literal), $flags:expr, $data:expr, $pos:expr, $table:expr) => {
+        ::kryo::is_field_present($flags, $bit).then_some(true)
+    };
+    (read flag_or_false($bit:literal), $flags:expr, $data:expr, $pos:expr, $table:expr) => {
+        Some(::kryo::is_field_present($flags, $bit))
+    };
+
+    (mark required($codec:ty), $flags:expr, $value:expr) => {};
+    (mark optional($codec:ty, $bit:literal), $flags:expr, $value:expr) => {
+        if $value.is_none() {
+            $flags |= 1 << $bit;
+        }
+    };
+    (mark or_default($codec:ty, $bit:literal), $flags:expr, $value:expr) => {
+        if $value == <<$codec as ::kryo::Codec>::Value as Default>::default() {
+            $flags |= 1 << $bit;
+        }
+    };
+    (mark flag($bit:literal), $flags:expr, $value:expr) => {
+        if !$value {
+            $flags |= 1 << $bit;
+        }
+    };
+    (mark flag_or_none($bit:literal), $flags:expr, $value:expr) => {
+        if $value != Some(true) {
+            $flags |= 1 << $bit;
+        }
+    };
+    (mark flag_or_false($bit:literal), $flags:expr, $value:expr) => {
+        if $value != Some(true) {
+            $flags |= 1 << $bit;
+        }
+    };
+
+    (write required($codec:ty), $flags:expr, $out:expr, $table:expr, $value:expr) => {
+        <$codec as ::kryo::Codec>::write(&$value, $out, $table)
+    };
+    (write optional($codec:ty, $bit:literal), $flags:expr, $out:expr, $table:expr, $value:expr) => {
+        if let Some(v) = &$value {
+            <$codec as ::kryo::Codec>::write(v, $out, $table);
+        }
+    };
+    (write or_default($codec:ty, $bit:literal), $flags:expr, $out:expr, $table:expr, $value:expr) => {
+        if ::kryo::is_field_present($flags, $bit) {
+            <$codec as ::kryo::Codec>::write(&$value, $out, $table);
+        }
+    };
+    (write $kind:ident($bit:literal), $flags:expr, $out:expr, $table:expr, $value:expr) => {};
+}
@@ -1,45 +1,21 @@
-use error::ParseError;
+use kryo::InternedString;
 
-use super::{BodyDecoder, DecodedEvent, ScopeIdsEvent};
+use super::ScopeIdsEvent;
 
-pub struct ScopeIdsDecoder;
-
-/// Wire 39: ScopeIds_1_0 — 3 interned strings.
-impl BodyDecoder for ScopeIdsDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let build_invocation_id = if kryo::is_field_present(flags as u16, 0) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let workspace_id = if kryo::is_field_present(flags as u16, 1) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let user_id = if kryo::is_field_present(flags as u16, 2) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::ScopeIds(ScopeIdsEvent {
-            build_invocation_id,
-            workspace_id,
-            user_id,
-        }))
+event_schema! {
+    /// Wire 39: ScopeIds_1_0 — 3 interned strings.
+    ScopeIdsDecoder => ScopeIds(ScopeIdsEvent) {
+        flags: byte,
+        build_invocation_id: optional(InternedString, 0),
+        workspace_id: optional(InternedString, 1),
+        user_id: optional(InternedString, 2),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_present() {
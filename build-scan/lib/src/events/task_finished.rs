@@ -1,114 +1,32 @@
-use error::ParseError;
-
-use super::{BodyDecoder, DecodedEvent, TaskFinishedEvent};
-
-pub struct TaskFinishedDecoder;
-
-impl BodyDecoder for TaskFinishedDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_u16_be(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let id = if kryo::is_field_present(flags, 0) {
-            kryo::read_task_id(body, &mut pos)?
-        } else {
-            0
-        };
-
-        let path = if kryo::is_field_present(flags, 1) {
-            table.read_string(body, &mut pos)?
-        } else {
-            String::new()
-        };
-
-        let outcome = if kryo::is_field_present(flags, 2) {
-            Some(kryo::read_enum_ordinal(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let _skip_message = if kryo::is_field_present(flags, 3) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        // bit 4: cacheable (boolean — value IS the bit, no payload)
-        let cacheable = if kryo::is_field_present(flags, 4) {
-            Some(true)
-        } else {
-            Some(false)
-        };
-
-        let caching_disabled_reason_category = if kryo::is_field_present(flags, 5) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let caching_disabled_explanation = if kryo::is_field_present(flags, 6) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let origin_build_invocation_id = if kryo::is_field_present(flags, 7) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let origin_build_cache_key = if kryo::is_field_present(flags, 8) {
-            Some(kryo::read_byte_array(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        if kryo::is_field_present(flags, 9) {
-            // originExecutionTime — read and discard (zigzag long)
-            let _ = varint::read_zigzag_i64(body, &mut pos)?;
-        }
-
-        // bit 10: actionable (boolean — value IS the bit, no payload)
-        let actionable = if kryo::is_field_present(flags, 10) {
-            Some(true)
-        } else {
-            Some(false)
-        };
-
-        if kryo::is_field_present(flags, 11) {
-            // upToDateMessages list — read count then skip strings
-            let count = varint::read_unsigned_varint(body, &mut pos)? as usize;
-            for _ in 0..count {
-                let _ = table.read_string(body, &mut pos)?;
-            }
-        }
-
-        let skip_reason_message = if kryo::is_field_present(flags, 12) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::TaskFinished(TaskFinishedEvent {
-            id,
-            path,
-            outcome,
-            cacheable,
-            caching_disabled_reason_category,
-            caching_disabled_explanation,
-            origin_build_invocation_id,
-            origin_build_cache_key,
-            actionable,
-            skip_reason_message,
-        }))
+use kryo::{ByteArray, EnumOrdinal, InternedString, ListOf, TaskId, ZigzagI64};
+
+use super::TaskFinishedEvent;
+
+event_schema! {
+    TaskFinishedDecoder => TaskFinished(TaskFinishedEvent) {
+        flags: u16,
+        id: or_default(TaskId, 0),
+        path: or_default(InternedString, 1),
+        outcome: optional(EnumOrdinal, 2),
+        skip_message: optional(InternedString, 3),
+        // cacheable (boolean — value IS the bit, no payload)
+        cacheable: flag_or_false(4),
+        caching_disabled_reason_category: optional(InternedString, 5),
+        caching_disabled_explanation: optional(InternedString, 6),
+        origin_build_invocation_id: optional(InternedString, 7),
+        origin_build_cache_key: optional(ByteArray, 8),
+        origin_execution_time: optional(ZigzagI64, 9),
+        // actionable (boolean — value IS the bit, no payload)
+        actionable: flag_or_false(10),
+        up_to_date_messages: or_default(ListOf<InternedString>, 11),
+        skip_reason_message: optional(InternedString, 12),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, BodyEncoder, DecodedEvent};
 
     #[test]
     fn test_decode_success_not_cacheable() {
@@ -132,11 +50,34 @@ mod tests {
         let result = decoder.decode(&data).unwrap();
         if let DecodedEvent::TaskFinished(e) = result {
             assert_eq!(e.id, 1);
-            assert_eq!(e.path, ":app:build");
+            assert_eq!(&*e.path, ":app:build");
             assert_eq!(e.outcome, Some(3)); // SUCCESS
             assert_eq!(e.cacheable, Some(false)); // bit4=1 means absent → false
         } else {
             panic!("expected TaskFinished");
         }
     }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let event = TaskFinishedEvent {
+            id: -6048516917597647557,
+            path: ":app:test".into(),
+            outcome: Some(4),
+            skip_message: None,
+            cacheable: Some(true),
+            caching_disabled_reason_category: None,
+            caching_disabled_explanation: None,
+            origin_build_invocation_id: Some("abc123".into()),
+            origin_build_cache_key: Some(vec![0xDE, 0xAD]),
+            origin_execution_time: Some(1234),
+            actionable: Some(false),
+            up_to_date_messages: vec![":app:test".into(), "Input changed".into()],
+            skip_reason_message: None,
+        };
+
+        let encoded = TaskFinishedDecoder.encode(&event);
+        let decoded = TaskFinishedDecoder.decode(&encoded).unwrap();
+        assert_eq!(decoded, DecodedEvent::TaskFinished(event));
+    }
 }
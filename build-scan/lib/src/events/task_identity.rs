@@ -1,42 +1,20 @@
-use error::ParseError;
+use kryo::{InternedString, TaskId};
 
-use super::{BodyDecoder, DecodedEvent, TaskIdentityEvent};
+use super::TaskIdentityEvent;
 
-pub struct TaskIdentityDecoder;
-
-impl BodyDecoder for TaskIdentityDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let id = if kryo::is_field_present(flags as u16, 0) {
-            kryo::read_task_id(body, &mut pos)?
-        } else {
-            0
-        };
-        let build_path = if kryo::is_field_present(flags as u16, 1) {
-            table.read_string(body, &mut pos)?
-        } else {
-            String::new()
-        };
-        let task_path = if kryo::is_field_present(flags as u16, 2) {
-            table.read_string(body, &mut pos)?
-        } else {
-            String::new()
-        };
-
-        Ok(DecodedEvent::TaskIdentity(TaskIdentityEvent {
-            id,
-            build_path,
-            task_path,
-        }))
+event_schema! {
+    TaskIdentityDecoder => TaskIdentity(TaskIdentityEvent) {
+        flags: byte,
+        id: or_default(TaskId, 0),
+        build_path: or_default(InternedString, 1),
+        task_path: or_default(InternedString, 2),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_fields_present() {
@@ -57,8 +35,8 @@ mod tests {
         let result = decoder.decode(&data).unwrap();
         if let DecodedEvent::TaskIdentity(e) = result {
             assert_eq!(e.id, 1);
-            assert_eq!(e.build_path, ":");
-            assert_eq!(e.task_path, ":app:build");
+            assert_eq!(&*e.build_path, ":");
+            assert_eq!(&*e.task_path, ":app:build");
         } else {
             panic!("expected TaskIdentity");
         }
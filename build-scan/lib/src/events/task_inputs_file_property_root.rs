@@ -1,96 +1,38 @@
-use error::ParseError;
+use kryo::{ByteArray, EnumOrdinal, InternedString, ListOf, TaskId, ZigzagI32};
 
-use super::{
-    BodyDecoder, DecodedEvent, FilePropertyRootChild, FileRef, TaskInputsFilePropertyRootEvent,
-};
+use super::{FilePropertyRootChild, FileRef, TaskInputsFilePropertyRootEvent};
 
-pub struct TaskInputsFilePropertyRootDecoder;
-
-fn decode_file_ref(
-    body: &[u8],
-    pos: &mut usize,
-    table: &mut kryo::StringInternTable,
-) -> Result<FileRef, ParseError> {
-    let flags = kryo::read_flags_byte(body, pos)?;
-    let root = if kryo::is_field_present(flags as u16, 0) {
-        Some(kryo::read_enum_ordinal(body, pos)?)
-    } else {
-        None
-    };
-    let path = if kryo::is_field_present(flags as u16, 1) {
-        Some(table.read_string(body, pos)?)
-    } else {
-        None
-    };
-    Ok(FileRef { root, path })
+event_schema! {
+    TaskInputsFilePropertyRootDecoder => TaskInputsFilePropertyRoot(TaskInputsFilePropertyRootEvent) {
+        flags: byte,
+        id: optional(TaskId, 0),
+        file: required(FileRef),
+        root_hash: optional(ByteArray, 1),
+        children: or_default(ListOf<FilePropertyRootChild>, 2),
+    }
 }
 
-fn decode_child(
-    body: &[u8],
-    pos: &mut usize,
-    table: &mut kryo::StringInternTable,
-) -> Result<FilePropertyRootChild, ParseError> {
-    let flags = kryo::read_flags_byte(body, pos)?;
-    let name = if kryo::is_field_present(flags as u16, 0) {
-        Some(table.read_string(body, pos)?)
-    } else {
-        None
-    };
-    let hash = if kryo::is_field_present(flags as u16, 1) {
-        Some(kryo::read_byte_array(body, pos)?)
-    } else {
-        None
-    };
-    let parent = if kryo::is_field_present(flags as u16, 2) {
-        Some(varint::read_zigzag_i32(body, pos)?)
-    } else {
-        None
-    };
-    Ok(FilePropertyRootChild { name, hash, parent })
+record_schema! {
+    FileRef {
+        flags: byte,
+        root: optional(EnumOrdinal, 0),
+        path: optional(InternedString, 1),
+    }
 }
 
-impl BodyDecoder for TaskInputsFilePropertyRootDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let id = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-        let file = decode_file_ref(body, &mut pos, &mut table)?;
-        let root_hash = if kryo::is_field_present(flags as u16, 1) {
-            Some(kryo::read_byte_array(body, &mut pos)?)
-        } else {
-            None
-        };
-        let children = if kryo::is_field_present(flags as u16, 2) {
-            let count = varint::read_unsigned_varint(body, &mut pos)? as usize;
-            let mut children = Vec::with_capacity(count);
-            for _ in 0..count {
-                children.push(decode_child(body, &mut pos, &mut table)?);
-            }
-            children
-        } else {
-            vec![]
-        };
-
-        Ok(DecodedEvent::TaskInputsFilePropertyRoot(
-            TaskInputsFilePropertyRootEvent {
-                id,
-                file,
-                root_hash,
-                children,
-            },
-        ))
+record_schema! {
+    FilePropertyRootChild {
+        flags: byte,
+        name: optional(InternedString, 0),
+        hash: optional(ByteArray, 1),
+        parent: optional(ZigzagI32, 2),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_with_file_and_children() {
@@ -1,50 +1,24 @@
-use error::ParseError;
+use kryo::{ByteArray, InternedString, ListOf, PrefixedListOf, TaskId};
 
-use super::{BodyDecoder, DecodedEvent, TaskInputsImplementationEvent};
+use super::TaskInputsImplementationEvent;
 
-pub struct TaskInputsImplementationDecoder;
-
-impl BodyDecoder for TaskInputsImplementationDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let id = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-        let class_loader_hash = if kryo::is_field_present(flags as u16, 1) {
-            Some(kryo::read_byte_array(body, &mut pos)?)
-        } else {
-            None
-        };
-        let action_class_loader_hashes = if kryo::is_field_present(flags as u16, 2) {
-            kryo::read_list_of_byte_arrays(body, &mut pos)?
-        } else {
-            vec![]
-        };
-        let action_class_names = if kryo::is_field_present(flags as u16, 3) {
-            kryo::read_list_of_interned_strings(body, &mut pos, &mut table)?
-        } else {
-            vec![]
-        };
-
-        Ok(DecodedEvent::TaskInputsImplementation(
-            TaskInputsImplementationEvent {
-                id,
-                class_loader_hash,
-                action_class_loader_hashes,
-                action_class_names,
-            },
-        ))
+event_schema! {
+    TaskInputsImplementationDecoder => TaskInputsImplementation(TaskInputsImplementationEvent) {
+        flags: byte,
+        id: optional(TaskId, 0),
+        class_loader_hash: optional(ByteArray, 1),
+        action_class_loader_hashes: or_default(PrefixedListOf<ByteArray>, 2),
+        action_class_names: or_default(ListOf<InternedString>, 3),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
+    use error::ParseError;
 
     #[test]
     fn test_decode_all_present() {
@@ -52,6 +26,7 @@ mod tests {
         data.extend_from_slice(&9i64.to_le_bytes());
         data.push(0x02);
         data.extend_from_slice(&[0xDE, 0xAD]);
+        data.push(0x00);
         data.push(0x01);
         data.push(0x02);
         data.extend_from_slice(&[0xBE, 0xEF]);
@@ -64,9 +39,24 @@ mod tests {
             assert_eq!(e.id, Some(9));
             assert_eq!(e.class_loader_hash, Some(vec![0xDE, 0xAD]));
             assert_eq!(e.action_class_loader_hashes, vec![vec![0xBE, 0xEF]]);
-            assert_eq!(e.action_class_names, vec!["MyAction"]);
+            assert_eq!(e.action_class_names, vec![Arc::from("MyAction")]);
         } else {
             panic!("expected TaskInputsImplementation");
         }
     }
+
+    #[test]
+    fn test_rejects_unknown_hash_list_prefix() {
+        let mut data = vec![0x0A];
+        data.extend_from_slice(&9i64.to_le_bytes());
+        data.extend_from_slice(&[0x01, 0x00]);
+        let decoder = TaskInputsImplementationDecoder;
+        assert!(matches!(
+            decoder.decode(&data),
+            Err(ParseError::UnsupportedListPrefix {
+                prefix: 1,
+                offset: 9
+            })
+        ));
+    }
 }
@@ -1,50 +1,23 @@
-use error::ParseError;
+use kryo::{InternedString, ListOf, TaskId};
 
-use super::{BodyDecoder, DecodedEvent, TaskInputsPropertyNamesEvent};
+use super::TaskInputsPropertyNamesEvent;
 
-pub struct TaskInputsPropertyNamesDecoder;
-
-impl BodyDecoder for TaskInputsPropertyNamesDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let id = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-        let value_inputs = if kryo::is_field_present(flags as u16, 1) {
-            kryo::read_list_of_interned_strings(body, &mut pos, &mut table)?
-        } else {
-            vec![]
-        };
-        let file_inputs = if kryo::is_field_present(flags as u16, 2) {
-            kryo::read_list_of_interned_strings(body, &mut pos, &mut table)?
-        } else {
-            vec![]
-        };
-        let outputs = if kryo::is_field_present(flags as u16, 3) {
-            kryo::read_list_of_interned_strings(body, &mut pos, &mut table)?
-        } else {
-            vec![]
-        };
-
-        Ok(DecodedEvent::TaskInputsPropertyNames(
-            TaskInputsPropertyNamesEvent {
-                id,
-                value_inputs,
-                file_inputs,
-                outputs,
-            },
-        ))
+event_schema! {
+    TaskInputsPropertyNamesDecoder => TaskInputsPropertyNames(TaskInputsPropertyNamesEvent) {
+        flags: byte,
+        id: optional(TaskId, 0),
+        value_inputs: or_default(ListOf<InternedString>, 1),
+        file_inputs: or_default(ListOf<InternedString>, 2),
+        outputs: or_default(ListOf<InternedString>, 3),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_present() {
@@ -63,9 +36,9 @@ mod tests {
         let result = decoder.decode(&data).unwrap();
         if let DecodedEvent::TaskInputsPropertyNames(e) = result {
             assert_eq!(e.id, Some(3));
-            assert_eq!(e.value_inputs, vec!["enabled"]);
-            assert_eq!(e.file_inputs, vec!["classpath"]);
-            assert_eq!(e.outputs, vec!["outputDir"]);
+            assert_eq!(e.value_inputs, vec![Arc::from("enabled")]);
+            assert_eq!(e.file_inputs, vec![Arc::from("classpath")]);
+            assert_eq!(e.outputs, vec![Arc::from("outputDir")]);
         } else {
             panic!("expected TaskInputsPropertyNames");
         }
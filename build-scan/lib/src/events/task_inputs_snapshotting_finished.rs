@@ -1,79 +1,31 @@
-use error::ParseError;
+use kryo::{ByteArray, ListOf, TaskId};
 
-use super::{
-    BodyDecoder, DecodedEvent, TaskInputsSnapshottingFinishedEvent, TaskInputsSnapshottingResult,
-};
+use super::{TaskInputsSnapshottingFinishedEvent, TaskInputsSnapshottingResult};
 
-pub struct TaskInputsSnapshottingFinishedDecoder;
-
-fn decode_result(body: &[u8], pos: &mut usize) -> Result<TaskInputsSnapshottingResult, ParseError> {
-    let flags = kryo::read_flags_byte(body, pos)?;
-    let hash = if kryo::is_field_present(flags as u16, 0) {
-        Some(kryo::read_byte_array(body, pos)?)
-    } else {
-        None
-    };
-    let implementation = if kryo::is_field_present(flags as u16, 1) {
-        Some(kryo::read_task_id(body, pos)?)
-    } else {
-        None
-    };
-    let property_names = if kryo::is_field_present(flags as u16, 2) {
-        Some(kryo::read_task_id(body, pos)?)
-    } else {
-        None
-    };
-    let value_inputs = if kryo::is_field_present(flags as u16, 3) {
-        Some(kryo::read_task_id(body, pos)?)
-    } else {
-        None
-    };
-    let file_inputs = if kryo::is_field_present(flags as u16, 4) {
-        kryo::read_list_of_i64(body, pos)?
-    } else {
-        vec![]
-    };
-    Ok(TaskInputsSnapshottingResult {
-        hash,
-        implementation,
-        property_names,
-        value_inputs,
-        file_inputs,
-    })
+event_schema! {
+    TaskInputsSnapshottingFinishedDecoder => TaskInputsSnapshottingFinished(TaskInputsSnapshottingFinishedEvent) {
+        flags: byte,
+        task: optional(TaskId, 0),
+        result: optional(TaskInputsSnapshottingResult, 1),
+        failure_id: optional(TaskId, 2),
+    }
 }
 
-impl BodyDecoder for TaskInputsSnapshottingFinishedDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let task = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-        let result = if kryo::is_field_present(flags as u16, 1) {
-            Some(decode_result(body, &mut pos)?)
-        } else {
-            None
-        };
-        let failure_id = if kryo::is_field_present(flags as u16, 2) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-        Ok(DecodedEvent::TaskInputsSnapshottingFinished(
-            TaskInputsSnapshottingFinishedEvent {
-                task,
-                result,
-                failure_id,
-            },
-        ))
+record_schema! {
+    TaskInputsSnapshottingResult {
+        flags: byte,
+        hash: optional(ByteArray, 0),
+        implementation: optional(TaskId, 1),
+        property_names: optional(TaskId, 2),
+        value_inputs: optional(TaskId, 3),
+        file_inputs: or_default(ListOf<TaskId>, 4),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_with_result() {
@@ -1,22 +1,18 @@
-use error::ParseError;
+use kryo::TaskId;
 
-use super::{BodyDecoder, DecodedEvent, TaskInputsSnapshottingStartedEvent};
+use super::TaskInputsSnapshottingStartedEvent;
 
-pub struct TaskInputsSnapshottingStartedDecoder;
-
-impl BodyDecoder for TaskInputsSnapshottingStartedDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let task = kryo::read_task_id(body, &mut pos)?;
-        Ok(DecodedEvent::TaskInputsSnapshottingStarted(
-            TaskInputsSnapshottingStartedEvent { task },
-        ))
+event_schema! {
+    TaskInputsSnapshottingStartedDecoder => TaskInputsSnapshottingStarted(TaskInputsSnapshottingStartedEvent) {
+        flags: none,
+        task: required(TaskId),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode() {
@@ -1,34 +1,19 @@
-use error::ParseError;
+use kryo::{ByteArray, ListOf, TaskId};
 
-use super::{BodyDecoder, DecodedEvent, TaskInputsValuePropertiesEvent};
+use super::TaskInputsValuePropertiesEvent;
 
-pub struct TaskInputsValuePropertiesDecoder;
-
-impl BodyDecoder for TaskInputsValuePropertiesDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-
-        let id = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-        let hashes = if kryo::is_field_present(flags as u16, 1) {
-            kryo::read_list_of_byte_arrays(body, &mut pos)?
-        } else {
-            vec![]
-        };
-
-        Ok(DecodedEvent::TaskInputsValueProperties(
-            TaskInputsValuePropertiesEvent { id, hashes },
-        ))
+event_schema! {
+    TaskInputsValuePropertiesDecoder => TaskInputsValueProperties(TaskInputsValuePropertiesEvent) {
+        flags: byte,
+        id: optional(TaskId, 0),
+        hashes: or_default(ListOf<ByteArray>, 1),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_present() {
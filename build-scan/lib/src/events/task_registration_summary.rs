@@ -1,24 +1,19 @@
-use error::ParseError;
+use kryo::PositiveI32;
 
-use super::{BodyDecoder, DecodedEvent, TaskRegistrationSummaryEvent};
+use super::TaskRegistrationSummaryEvent;
 
-pub struct TaskRegistrationSummaryDecoder;
-
-/// Wire 122: TaskRegistrationSummary_1_0 — single unconditional int, no flags.
-impl BodyDecoder for TaskRegistrationSummaryDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let task_count = kryo::read_positive_varint_i32(body, &mut pos)?;
-
-        Ok(DecodedEvent::TaskRegistrationSummary(
-            TaskRegistrationSummaryEvent { task_count },
-        ))
+event_schema! {
+    /// Wire 122: TaskRegistrationSummary_1_0 — single unconditional int, no flags.
+    TaskRegistrationSummaryDecoder => TaskRegistrationSummary(TaskRegistrationSummaryEvent) {
+        flags: none,
+        task_count: required(PositiveI32),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_task_count() {
@@ -1,59 +1,30 @@
-use error::ParseError;
+use kryo::{EnumOrdinal, InternedString, TaskId, ZigzagI64};
 
-use super::{BodyDecoder, DecodedEvent, TaskStartedEvent};
+use super::{ConfigurationParentRef, TaskStartedEvent};
 
-pub struct TaskStartedDecoder;
-
-impl BodyDecoder for TaskStartedDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let id = if kryo::is_field_present(flags as u16, 0) {
-            kryo::read_task_id(body, &mut pos)?
-        } else {
-            0
-        };
-        let build_path = if kryo::is_field_present(flags as u16, 1) {
-            table.read_string(body, &mut pos)?
-        } else {
-            String::new()
-        };
-        let path = if kryo::is_field_present(flags as u16, 2) {
-            table.read_string(body, &mut pos)?
-        } else {
-            String::new()
-        };
-        let class_name = if kryo::is_field_present(flags as u16, 3) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-        // bit 4: parent (ConfigurationParentRef) — skip if present
-        if kryo::is_field_present(flags as u16, 4) {
-            // Read and discard: flags byte + optional enum + optional long
-            let parent_flags = kryo::read_flags_byte(body, &mut pos)?;
-            if kryo::is_field_present(parent_flags as u16, 0) {
-                let _ = varint::read_unsigned_varint(body, &mut pos)?; // enum ordinal
-            }
-            if kryo::is_field_present(parent_flags as u16, 1) {
-                let _ = varint::read_zigzag_i64(body, &mut pos)?; // id
-            }
-        }
+event_schema! {
+    TaskStartedDecoder => TaskStarted(TaskStartedEvent) {
+        flags: byte,
+        id: or_default(TaskId, 0),
+        build_path: or_default(InternedString, 1),
+        path: or_default(InternedString, 2),
+        class_name: optional(InternedString, 3),
+        parent: optional(ConfigurationParentRef, 4),
+    }
+}
 
-        Ok(DecodedEvent::TaskStarted(TaskStartedEvent {
-            id,
-            build_path,
-            path,
-            class_name,
-        }))
+record_schema! {
+    ConfigurationParentRef {
+        flags: byte,
+        parent_type: optional(EnumOrdinal, 0),
+        id: optional(ZigzagI64, 1),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_without_parent() {
@@ -78,7 +49,7 @@ mod tests {
         let result = decoder.decode(&data).unwrap();
         if let DecodedEvent::TaskStarted(e) = result {
             assert_eq!(e.id, 1);
-            assert_eq!(e.path, ":app:compileKotlin");
+            assert_eq!(&*e.path, ":app:compileKotlin");
             assert_eq!(
                 e.class_name.as_deref(),
                 Some("org.jetbrains.kotlin.gradle.tasks.KotlinCompile")
@@ -87,4 +58,33 @@ mod tests {
             panic!("expected TaskStarted");
         }
     }
+
+    #[test]
+    fn test_decode_with_parent() {
+        let mut data = vec![0x00]; // all present
+        data.extend_from_slice(&2i64.to_le_bytes());
+        data.push(0x02);
+        data.push(58); // buildPath ":"
+        data.push(0x0A);
+        data.extend_from_slice(b":help"); // path
+        data.push(0x04);
+        data.extend_from_slice(b"Xy"); // className
+        data.push(0x00); // parent flags: both present
+        data.push(0x01); // parent type ordinal
+        data.push(0x0E); // parent id = zigzag(7)
+
+        let result = TaskStartedDecoder.decode(&data).unwrap();
+        if let DecodedEvent::TaskStarted(e) = result {
+            assert_eq!(&*e.path, ":help");
+            assert_eq!(
+                e.parent,
+                Some(ConfigurationParentRef {
+                    parent_type: Some(1),
+                    id: Some(7),
+                })
+            );
+        } else {
+            panic!("expected TaskStarted");
+        }
+    }
 }
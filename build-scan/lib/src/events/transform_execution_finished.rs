@@ -1,88 +1,26 @@
-use error::ParseError;
-
-use super::{BodyDecoder, DecodedEvent, TransformExecutionFinishedEvent};
-
-pub struct TransformExecutionFinishedDecoder;
-
-impl BodyDecoder for TransformExecutionFinishedDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_u16_be(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let id = if kryo::is_field_present(flags, 0) {
-            kryo::read_zigzag_i64(body, &mut pos)?
-        } else {
-            0
-        };
-
-        let failure_id = if kryo::is_field_present(flags, 1) {
-            Some(kryo::read_positive_varint_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let outcome = if kryo::is_field_present(flags, 2) {
-            Some(kryo::read_enum_ordinal(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let execution_reasons = if kryo::is_field_present(flags, 3) {
-            kryo::read_list_of_interned_strings(body, &mut pos, &mut table)?
-        } else {
-            vec![]
-        };
-
-        let caching_disabled_reason_category = if kryo::is_field_present(flags, 4) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let caching_disabled_explanation = if kryo::is_field_present(flags, 5) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let origin_build_invocation_id = if kryo::is_field_present(flags, 6) {
-            Some(table.read_string(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let origin_build_cache_key = if kryo::is_field_present(flags, 7) {
-            Some(kryo::read_byte_array(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        let origin_execution_time = if kryo::is_field_present(flags, 8) {
-            Some(kryo::read_positive_varint_i64(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::TransformExecutionFinished(
-            TransformExecutionFinishedEvent {
-                id,
-                failure_id,
-                outcome,
-                execution_reasons,
-                caching_disabled_reason_category,
-                caching_disabled_explanation,
-                origin_build_invocation_id,
-                origin_build_cache_key,
-                origin_execution_time,
-            },
-        ))
+use kryo::{ByteArray, EnumOrdinal, InternedString, ListOf, PositiveI64, ZigzagI64};
+
+use super::TransformExecutionFinishedEvent;
+
+event_schema! {
+    TransformExecutionFinishedDecoder => TransformExecutionFinished(TransformExecutionFinishedEvent) {
+        flags: u16,
+        id: or_default(ZigzagI64, 0),
+        failure_id: optional(PositiveI64, 1),
+        outcome: optional(EnumOrdinal, 2),
+        execution_reasons: or_default(ListOf<InternedString>, 3),
+        caching_disabled_reason_category: optional(InternedString, 4),
+        caching_disabled_explanation: optional(InternedString, 5),
+        origin_build_invocation_id: optional(InternedString, 6),
+        origin_build_cache_key: optional(ByteArray, 7),
+        origin_execution_time: optional(PositiveI64, 8),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
     use kryo::encode_zigzag_i64;
 
     #[test]
@@ -1,43 +1,20 @@
-use error::ParseError;
+use kryo::TaskId;
 
-use super::{BodyDecoder, DecodedEvent, TransformExecutionRequestEvent};
+use super::TransformExecutionRequestEvent;
 
-pub struct TransformExecutionRequestDecoder;
-
-impl BodyDecoder for TransformExecutionRequestDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-
-        let node_id = if kryo::is_field_present(flags as u16, 0) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-        let identification_id = if kryo::is_field_present(flags as u16, 1) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-        let execution_id = if kryo::is_field_present(flags as u16, 2) {
-            Some(kryo::read_task_id(body, &mut pos)?)
-        } else {
-            None
-        };
-
-        Ok(DecodedEvent::TransformExecutionRequest(
-            TransformExecutionRequestEvent {
-                node_id,
-                identification_id,
-                execution_id,
-            },
-        ))
+event_schema! {
+    TransformExecutionRequestDecoder => TransformExecutionRequest(TransformExecutionRequestEvent) {
+        flags: byte,
+        node_id: optional(TaskId, 0),
+        identification_id: optional(TaskId, 1),
+        execution_id: optional(TaskId, 2),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
 
     #[test]
     fn test_decode_all_present() {
@@ -1,25 +1,19 @@
-use error::ParseError;
+use kryo::ZigzagI64;
 
-use super::{BodyDecoder, DecodedEvent, TransformExecutionStartedEvent};
-
-pub struct TransformExecutionStartedDecoder;
-
-impl BodyDecoder for TransformExecutionStartedDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
+use super::TransformExecutionStartedEvent;
 
+event_schema! {
+    TransformExecutionStartedDecoder => TransformExecutionStarted(TransformExecutionStartedEvent) {
         // No flags byte. Single field always present.
-        let id = kryo::read_zigzag_i64(body, &mut pos)?;
-
-        Ok(DecodedEvent::TransformExecutionStarted(
-            TransformExecutionStartedEvent { id },
-        ))
+        flags: none,
+        id: required(ZigzagI64),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{BodyDecoder, DecodedEvent};
     use kryo::encode_zigzag_i64;
 
     #[test]
@@ -1,74 +1,30 @@
-use error::ParseError;
+use kryo::{InternedString, ListOf, PositiveI32, TaskId};
 
-use super::{BodyDecoder, DecodedEvent, TransformIdentificationEvent};
+use super::TransformIdentificationEvent;
 
-pub struct TransformIdentificationDecoder;
-
-impl BodyDecoder for TransformIdentificationDecoder {
-    fn decode(&self, body: &[u8]) -> Result<DecodedEvent, ParseError> {
-        let mut pos = 0;
-        let flags = kryo::read_flags_byte(body, &mut pos)?;
-        let mut table = kryo::StringInternTable::new();
-
-        let id = if kryo::is_field_present(flags as u16, 0) {
-            kryo::read_zigzag_i64(body, &mut pos)?
-        } else {
-            0
-        };
-
-        let component_identity = if kryo::is_field_present(flags as u16, 1) {
-            kryo::read_positive_varint_i32(body, &mut pos)?
-        } else {
-            0
-        };
-
-        let input_artifact_name = if kryo::is_field_present(flags as u16, 2) {
-            table.read_string(body, &mut pos)?
-        } else {
-            String::new()
-        };
-
-        let transform_action_class = if kryo::is_field_present(flags as u16, 3) {
-            table.read_string(body, &mut pos)?
-        } else {
-            String::new()
-        };
-
-        let from_attributes = if kryo::is_field_present(flags as u16, 4) {
-            kryo::read_list_of_positive_varint_i32(body, &mut pos)?
-        } else {
-            vec![]
-        };
-
-        let to_attributes = if kryo::is_field_present(flags as u16, 5) {
-            kryo::read_list_of_positive_varint_i32(body, &mut pos)?
-        } else {
-            vec![]
-        };
-
-        Ok(DecodedEvent::TransformIdentification(
-            TransformIdentificationEvent {
-                id,
-                component_identity,
-                input_artifact_name,
-                transform_action_class,
-                from_attributes,
-                to_attributes,
-            },
-        ))
+event_schema! {
+    TransformIdentificationDecoder => TransformIdentification(TransformIdentificationEvent) {
+        flags: byte,
+        id: or_default(TaskId, 0),
+        component_identity: or_default(PositiveI32, 1),
+        input_artifact_name: or_default(InternedString, 2),
+        transform_action_class: or_default(InternedString, 3),
+        from_attributes: or_default(ListOf<PositiveI32>, 4),
+        to_attributes: or_default(ListOf<PositiveI32>, 5),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use kryo::encode_zigzag_i64;
+    use crate::{BodyDecoder, DecodedEvent};
+    use kryo::write_task_id;
 
     #[test]
     fn test_decode_all_present() {
         // flags = 0x00: all 6 bits present
         let mut data = vec![0x00];
-        data.extend_from_slice(&encode_zigzag_i64(10)); // id = 10
+        write_task_id(&mut data, 10); // id = 10
         data.push(0x03); // component_identity = 3 (unsigned varint)
         // input_artifact_name = "in" → zigzag(2)=4, then 'i'=105, 'n'=110
         data.push(0x04);
@@ -91,8 +47,8 @@ mod tests {
         if let DecodedEvent::TransformIdentification(e) = result {
             assert_eq!(e.id, 10);
             assert_eq!(e.component_identity, 3);
-            assert_eq!(e.input_artifact_name, "in");
-            assert_eq!(e.transform_action_class, "TC");
+            assert_eq!(&*e.input_artifact_name, "in");
+            assert_eq!(&*e.transform_action_class, "TC");
             assert_eq!(e.from_attributes, vec![1, 2]);
             assert_eq!(e.to_attributes, vec![5]);
         } else {
@@ -109,8 +65,8 @@ mod tests {
         if let DecodedEvent::TransformIdentification(e) = result {
             assert_eq!(e.id, 0);
             assert_eq!(e.component_identity, 0);
-            assert_eq!(e.input_artifact_name, "");
-            assert_eq!(e.transform_action_class, "");
+            assert_eq!(&*e.input_artifact_name, "");
+            assert_eq!(&*e.transform_action_class, "");
             assert!(e.from_attributes.is_empty());
             assert!(e.to_attributes.is_empty());
         } else {
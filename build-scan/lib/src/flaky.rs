@@ -0,0 +1,182 @@
+//! Flaky task detection across build scans.
+//!
+//! A task whose snapshotted inputs hash to the same value ran with identical inputs, so
+//! if it both succeeded and failed under one hash the difference came from outside its
+//! inputs: the task is flaky rather than broken by a change. Runs without an inputs
+//! hash can't be compared this way and are ignored.
+
+use std::collections::BTreeMap;
+
+use models::{BuildScanPayload, TaskOutcome};
+use serde::Serialize;
+
+/// `(scan id, outcome)` per `(task path, inputs hash)`, in scan order.
+type RunsByInputs<'a> = BTreeMap<(&'a str, &'a [u8]), Vec<(&'a str, TaskOutcome)>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlakyTask {
+    pub task_path: String,
+    /// Hex-encoded `TaskInputsSnapshottingResultData::hash`.
+    pub inputs_hash: String,
+    pub successes: usize,
+    pub failures: usize,
+    /// Times the outcome changed between consecutive runs, in scan order.
+    pub flips: usize,
+    pub passing_scans: Vec<String>,
+    pub failing_scans: Vec<String>,
+}
+
+/// Finds tasks that both succeeded and failed with the same inputs hash among
+/// `(scan id, payload)` pairs. Scans are taken in build start order and at most
+/// `max_examples` scan ids are kept per outcome. Tasks with the most failures come first.
+pub fn detect(scans: &[(String, &BuildScanPayload)], max_examples: usize) -> Vec<FlakyTask> {
+    let mut runs = RunsByInputs::new();
+    for i in BuildScanPayload::chronological(scans, |(_, payload)| payload) {
+        let (scan, payload) = &scans[i];
+        for task in &payload.tasks {
+            let Some(outcome @ (TaskOutcome::Success | TaskOutcome::Failed)) = task.outcome else {
+                continue;
+            };
+            let Some(hash) = task
+                .inputs
+                .as_ref()
+                .and_then(|i| i.snapshotting_result.as_ref())
+                .and_then(|r| r.hash.as_deref())
+            else {
+                continue;
+            };
+            runs.entry((&task.task_path, hash))
+                .or_default()
+                .push((scan, outcome));
+        }
+    }
+
+    let mut flaky: Vec<FlakyTask> = runs
+        .into_iter()
+        .filter_map(|((task_path, hash), runs)| {
+            let examples = |wanted: TaskOutcome| -> Vec<String> {
+                runs.iter()
+                    .filter(|(_, outcome)| *outcome == wanted)
+                    .take(max_examples)
+                    .map(|(scan, _)| scan.to_string())
+                    .collect()
+            };
+            let failures = runs
+                .iter()
+                .filter(|(_, outcome)| *outcome == TaskOutcome::Failed)
+                .count();
+            let successes = runs.len() - failures;
+            (failures > 0 && successes > 0).then(|| FlakyTask {
+                task_path: task_path.to_string(),
+                inputs_hash: hash.iter().map(|b| format!("{b:02x}")).collect(),
+                successes,
+                failures,
+                flips: runs.windows(2).filter(|w| w[0].1 != w[1].1).count(),
+                passing_scans: examples(TaskOutcome::Success),
+                failing_scans: examples(TaskOutcome::Failed),
+            })
+        })
+        .collect();
+
+    flaky.sort_by(|a, b| {
+        b.failures
+            .cmp(&a.failures)
+            .then(b.flips.cmp(&a.flips))
+            .then(a.task_path.cmp(&b.task_path))
+    });
+    flaky
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::{Task, TaskInputs, TaskInputsSnapshottingResultData};
+
+    fn task(path: &str, outcome: TaskOutcome, hash: Option<&[u8]>) -> Task {
+        Task {
+            id: 1,
+            build_path: ":".into(),
+            task_path: path.into(),
+            class_name: None,
+            outcome: Some(outcome),
+            cacheable: None,
+            caching_disabled_reason: None,
+            caching_disabled_explanation: None,
+            origin_build_cache_key: None,
+            actionable: None,
+            started_at: None,
+            finished_at: None,
+            duration_ms: None,
+            inputs: hash.map(|hash| TaskInputs {
+                snapshotting_result: Some(TaskInputsSnapshottingResultData {
+                    hash: Some(hash.to_vec()),
+                    implementation: None,
+                    property_names: None,
+                    value_inputs: None,
+                    file_inputs: Vec::new(),
+                }),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn scans(payloads: &[BuildScanPayload]) -> Vec<(String, &BuildScanPayload)> {
+        payloads
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (format!("scan{i}"), p))
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_flip_with_same_inputs() {
+        let outcomes = [
+            TaskOutcome::Success,
+            TaskOutcome::Failed,
+            TaskOutcome::Success,
+            TaskOutcome::Failed,
+        ];
+        let payloads: Vec<BuildScanPayload> = outcomes
+            .iter()
+            .map(|&outcome| BuildScanPayload {
+                tasks: vec![
+                    task(":app:test", outcome, Some(&[0xab, 0x01])),
+                    task(":app:jar", TaskOutcome::Success, Some(&[0x02])),
+                ],
+                ..Default::default()
+            })
+            .collect();
+
+        let flaky = detect(&scans(&payloads), 1);
+
+        assert_eq!(flaky.len(), 1);
+        let test = &flaky[0];
+        assert_eq!(test.task_path, ":app:test");
+        assert_eq!(test.inputs_hash, "ab01");
+        assert_eq!((test.successes, test.failures, test.flips), (2, 2, 3));
+        assert_eq!(test.passing_scans, vec!["scan0"]);
+        assert_eq!(test.failing_scans, vec!["scan1"]);
+    }
+
+    #[test]
+    fn test_ignores_failures_with_changed_or_unknown_inputs() {
+        let payloads = vec![
+            BuildScanPayload {
+                tasks: vec![
+                    task(":compile", TaskOutcome::Success, Some(&[1])),
+                    task(":lint", TaskOutcome::Success, None),
+                ],
+                ..Default::default()
+            },
+            BuildScanPayload {
+                tasks: vec![
+                    task(":compile", TaskOutcome::Failed, Some(&[2])),
+                    task(":lint", TaskOutcome::Failed, None),
+                ],
+                ..Default::default()
+            },
+        ];
+
+        assert!(detect(&scans(&payloads), 3).is_empty());
+    }
+}
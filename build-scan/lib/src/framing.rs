@@ -1,10 +1,21 @@
 use error::ParseError;
 
-pub struct FramedEvent {
+#[derive(Debug, Clone)]
+pub struct FramedEvent<'a> {
     pub wire_id: u16,
     pub timestamp: i64,
+    /// Wall-clock time of the event. Differs from `timestamp` only after the plugin has
+    /// reported a clock adjustment through the bit-2 delta.
+    pub actual_timestamp: i64,
     pub ordinal: i32,
-    pub body: Vec<u8>,
+    /// Raw frame flags; a set bit means the corresponding delta was omitted.
+    pub flags: u8,
+    /// Offset of the flags varint in the decompressed stream.
+    pub offset: usize,
+    /// Encoded length of the whole frame, header and body included.
+    pub frame_len: usize,
+    /// Borrowed from the decompressed stream.
+    pub body: &'a [u8],
 }
 
 pub struct EventFrameReader<'a> {
@@ -12,6 +23,8 @@ pub struct EventFrameReader<'a> {
     pos: usize,
     wire_id: i64,
     timestamp: i64,
+    /// `actual_timestamp - timestamp`, carried forward like the other deltas.
+    clock_offset: i64,
     ordinal: i32,
 }
 
@@ -22,53 +35,60 @@ impl<'a> EventFrameReader<'a> {
             pos: 0,
             wire_id: 0,
             timestamp: 0,
+            clock_offset: 0,
             ordinal: 0,
         }
     }
 
-    fn read_next(&mut self) -> Result<FramedEvent, ParseError> {
+    fn read_next(&mut self) -> Result<FramedEvent<'a>, ParseError> {
+        let offset = self.pos;
         let flags = varint::read_unsigned_varint(self.data, &mut self.pos)? as u8;
 
         // bit0=0 → type delta present
         if flags & 1 == 0 {
             let delta = varint::read_zigzag_i32(self.data, &mut self.pos)?;
-            self.wire_id += delta as i64;
+            self.wire_id = self.wire_id.wrapping_add(delta as i64);
         }
         // bit1=0 → timestamp delta present
         if flags & 2 == 0 {
             let delta = varint::read_zigzag_i64(self.data, &mut self.pos)?;
-            self.timestamp += delta;
+            self.timestamp = self.timestamp.wrapping_add(delta);
         }
-        // bit2=0 → actual-timestamp delta present (read and discard)
+        // bit2=0 → actual-timestamp delta present
         if flags & 4 == 0 {
-            let _actual_delta = varint::read_zigzag_i64(self.data, &mut self.pos)?;
+            let delta = varint::read_zigzag_i64(self.data, &mut self.pos)?;
+            self.clock_offset = self.clock_offset.wrapping_add(delta);
         }
         // bit3=0 → ordinal delta present; bit3=1 → default +1
         if flags & 8 == 0 {
             let delta = varint::read_zigzag_i32(self.data, &mut self.pos)?;
-            self.ordinal += delta;
+            self.ordinal = self.ordinal.wrapping_add(delta);
         } else {
-            self.ordinal += 1;
+            self.ordinal = self.ordinal.wrapping_add(1);
         }
 
         let body_length = varint::read_unsigned_varint(self.data, &mut self.pos)? as usize;
-        if self.pos + body_length > self.data.len() {
+        if body_length > self.data.len() - self.pos {
             return Err(ParseError::UnexpectedEof { offset: self.pos });
         }
-        let body = self.data[self.pos..self.pos + body_length].to_vec();
+        let body = &self.data[self.pos..self.pos + body_length];
         self.pos += body_length;
 
         Ok(FramedEvent {
             wire_id: self.wire_id as u16,
             timestamp: self.timestamp,
+            actual_timestamp: self.timestamp.wrapping_add(self.clock_offset),
             ordinal: self.ordinal,
+            flags,
+            offset,
+            frame_len: self.pos - offset,
             body,
         })
     }
 }
 
 impl<'a> Iterator for EventFrameReader<'a> {
-    type Item = Result<FramedEvent, ParseError>;
+    type Item = Result<FramedEvent<'a>, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.pos >= self.data.len() {
@@ -97,7 +117,7 @@ mod tests {
         let data = first_five_events_bytes();
         let mut reader = EventFrameReader::new(&data);
         let event = reader.next().unwrap().unwrap();
-        assert_eq!(event.wire_id, 265); // DAEMON_STATE_v1
+        assert_eq!(event.wire_id, 265); // DaemonState_1_1
         assert_eq!(event.body.len(), 14);
     }
 
@@ -108,11 +128,11 @@ mod tests {
             .collect::<Result<Vec<_>, _>>()
             .unwrap();
         assert_eq!(events.len(), 5);
-        assert_eq!(events[0].wire_id, 265); // DAEMON_STATE_v1
-        assert_eq!(events[1].wire_id, 6); // BUILD_STARTED
-        assert_eq!(events[2].wire_id, 5); // BUILD_REQUESTED_TASKS
-        assert_eq!(events[3].wire_id, 516); // BUILD_MODES_v2
-        assert_eq!(events[4].wire_id, 12); // HARDWARE
+        assert_eq!(events[0].wire_id, 265); // DaemonState_1_1
+        assert_eq!(events[1].wire_id, 6); // BuildStarted_1_0
+        assert_eq!(events[2].wire_id, 5); // BuildRequestedTasks_1_0
+        assert_eq!(events[3].wire_id, 516); // BuildModes_1_2
+        assert_eq!(events[4].wire_id, 12); // Hardware_1_0
     }
 
     #[test]
@@ -135,4 +155,63 @@ mod tests {
         assert!(events[1].body.is_empty());
         assert_eq!(events[2].body.len(), 8);
     }
+
+    #[test]
+    fn test_frame_offsets_cover_stream() {
+        let data = first_five_events_bytes();
+        let events: Vec<_> = EventFrameReader::new(&data)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(events[0].offset, 0);
+        for pair in events.windows(2) {
+            assert_eq!(pair[0].offset + pair[0].frame_len, pair[1].offset);
+        }
+        let last = events.last().unwrap();
+        assert_eq!(last.offset + last.frame_len, data.len());
+        assert_eq!(events[0].flags, 0x0c);
+    }
+
+    #[test]
+    fn test_actual_timestamp_follows_clock_adjustment() {
+        // Frame 1: flags=0x08 → type, timestamp and actual deltas present.
+        //   type +2 (zigzag 4), timestamp +100 (zigzag 200), actual +5 (zigzag 10), empty body.
+        // Frame 2: flags=0x0f → all deltas omitted, empty body.
+        // Frame 3: flags=0x09 → timestamp +10 (zigzag 20), actual -5 (zigzag 9), empty body.
+        let data = [
+            0x08, 0x04, 0xc8, 0x01, 0x0a, 0x00, //
+            0x0f, 0x00, //
+            0x09, 0x14, 0x09, 0x00,
+        ];
+        let events: Vec<_> = EventFrameReader::new(&data)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(events[0].timestamp, 100);
+        assert_eq!(events[0].actual_timestamp, 105);
+        assert_eq!(events[1].actual_timestamp, 105);
+        assert_eq!(events[2].timestamp, 110);
+        assert_eq!(events[2].actual_timestamp, 110);
+        assert_eq!(events[2].frame_len, 4);
+    }
+
+    #[test]
+    fn test_hostile_lengths_and_deltas_do_not_panic() {
+        // Body length u64::MAX, in Kryo's nine-byte form.
+        let data = [0x0f, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        let result = EventFrameReader::new(&data).next().unwrap();
+        assert!(matches!(result, Err(ParseError::UnexpectedEof { .. })));
+
+        // Two timestamp deltas of i64::MAX overflow the running timestamp.
+        let mut data = Vec::new();
+        for _ in 0..2 {
+            data.push(0x0d);
+            data.extend_from_slice(&[0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+            data.push(0x00);
+        }
+        let events: Vec<_> = EventFrameReader::new(&data)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].timestamp, i64::MAX);
+        assert_eq!(events[1].timestamp, i64::MAX.wrapping_add(i64::MAX));
+    }
 }
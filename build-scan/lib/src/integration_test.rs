@@ -93,7 +93,7 @@ fn test_parse_reference_payload() {
     );
 
     // Check known task paths from the reference build
-    let paths: Vec<&str> = result.tasks.iter().map(|t| t.task_path.as_str()).collect();
+    let paths: Vec<&str> = result.tasks.iter().map(|t| &*t.task_path).collect();
     assert!(
         paths.contains(&":app:compileKotlin"),
         "missing :app:compileKotlin; found paths: {paths:?}"
@@ -128,3 +128,53 @@ fn test_parse_reference_payload() {
         "raw_events must not be empty"
     );
 }
+
+/// `captured-output/payloads`, under Bazel's runfiles or the workspace.
+fn find_payload_dir() -> Option<std::path::PathBuf> {
+    if let Ok(srcdir) = std::env::var("TEST_SRCDIR") {
+        let bazel_path = std::path::Path::new(&srcdir)
+            .join("_main")
+            .join("captured-output")
+            .join("payloads");
+        if bazel_path.is_dir() {
+            return Some(bazel_path);
+        }
+    }
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .ancestors()
+        .map(|ancestor| ancestor.join("captured-output").join("payloads"))
+        .find(|candidate| candidate.is_dir())
+}
+
+#[test]
+fn test_parse_captured_uploads() {
+    let Some(dir) = find_payload_dir() else {
+        info!("Skipping integration test: captured payloads not found.");
+        return;
+    };
+
+    let mut parsed = 0;
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let uri = json["request"]["uri"].as_str().unwrap_or_default();
+        let Some(b64) = json["request"]["body"]["base64"].as_str() else {
+            continue;
+        };
+        if !uri.ends_with("/upload") {
+            continue;
+        }
+        let raw_bytes = base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .unwrap();
+        if let Err(e) = lib::parse(&raw_bytes) {
+            panic!("{} doesn't parse: {e}", path.display());
+        }
+        parsed += 1;
+    }
+    assert!(parsed > 0, "no captured uploads in {}", dir.display());
+}
@@ -1,7 +1,11 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
 use error::ParseError;
 
 pub struct StringInternTable {
-    strings: Vec<String>,
+    strings: Vec<Arc<str>>,
 }
 
 impl Default for StringInternTable {
@@ -20,7 +24,8 @@ impl StringInternTable {
     /// ZigZag varint: >= 0 = new string (char count), < 0 = back-ref (index = -1 - value)
     /// Characters: unsigned LEB128 varints (ASCII = 1 byte each)
     /// Scope: per-event body (fresh table per decode call)
-    pub fn read_string(&mut self, data: &[u8], pos: &mut usize) -> Result<String, ParseError> {
+    /// Back-references share the table's allocation instead of copying the string.
+    pub fn read_string(&mut self, data: &[u8], pos: &mut usize) -> Result<Arc<str>, ParseError> {
         let raw = varint::read_zigzag_i32(data, pos)?;
         if raw < 0 {
             // Back-reference: index = -1 - raw
@@ -30,20 +35,72 @@ impl StringInternTable {
                 .cloned()
                 .ok_or(ParseError::InvalidStringRef { index })
         } else {
-            // New string: raw = character count
+            // New string: raw = character count; every char takes at least one byte
             let char_count = raw as usize;
+            if char_count > data.len() - *pos {
+                return Err(ParseError::StringLengthOutOfBounds {
+                    len: char_count,
+                    offset: *pos,
+                });
+            }
             let mut s = String::with_capacity(char_count);
             for _ in 0..char_count {
                 let ch = varint::read_unsigned_varint(data, pos)? as u32;
                 let c = char::from_u32(ch).ok_or(ParseError::InvalidUtf8)?;
                 s.push(c);
             }
-            self.strings.push(s.clone());
+            let s: Arc<str> = Arc::from(s);
+            self.strings.push(Arc::clone(&s));
             Ok(s)
         }
     }
 }
 
+/// Writer-side counterpart of `StringInternTable`: repeated strings within one body
+/// are emitted as back-references.
+#[derive(Default)]
+pub struct StringInternWriter {
+    indices: HashMap<String, usize>,
+}
+
+impl StringInternWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_string(&mut self, out: &mut Vec<u8>, s: &str) {
+        if let Some(&index) = self.indices.get(s) {
+            write_zigzag_i32(out, -1 - index as i32);
+            return;
+        }
+        write_zigzag_i32(out, s.chars().count() as i32);
+        for c in s.chars() {
+            write_unsigned_varint(out, c as u64);
+        }
+        let index = self.indices.len();
+        self.indices.insert(s.to_string(), index);
+    }
+}
+
+/// Upper bound on elements reserved up front for a decoded list. Longer lists still
+/// decode, growing as they go, so a forged length can't force a large allocation.
+const MAX_PREALLOCATED_ELEMENTS: usize = 1024;
+
+/// Read a varint length prefix for a list or byte array. Every element occupies at least
+/// one byte, so a length beyond the remaining input is rejected before allocating.
+pub fn read_length(data: &[u8], pos: &mut usize) -> Result<usize, ParseError> {
+    let offset = *pos;
+    let len = varint::read_unsigned_varint(data, pos)?;
+    if len > (data.len() - *pos) as u64 {
+        return Err(ParseError::LengthOutOfBounds { len, offset });
+    }
+    Ok(len as usize)
+}
+
+fn list_with_capacity<T>(len: usize) -> Vec<T> {
+    Vec::with_capacity(len.min(MAX_PREALLOCATED_ELEMENTS))
+}
+
 /// Read flags as unsigned varint, return as u8 (for <= 8 fields)
 pub fn read_flags_byte(data: &[u8], pos: &mut usize) -> Result<u8, ParseError> {
     Ok(varint::read_unsigned_varint(data, pos)? as u8)
@@ -90,10 +147,7 @@ pub fn read_enum_ordinal(data: &[u8], pos: &mut usize) -> Result<u64, ParseError
 
 /// Read a byte array: unsigned varint length, then that many bytes
 pub fn read_byte_array(data: &[u8], pos: &mut usize) -> Result<Vec<u8>, ParseError> {
-    let len = varint::read_unsigned_varint(data, pos)? as usize;
-    if *pos + len > data.len() {
-        return Err(ParseError::UnexpectedEof { offset: *pos });
-    }
+    let len = read_length(data, pos)?;
     let bytes = data[*pos..*pos + len].to_vec();
     *pos += len;
     Ok(bytes)
@@ -101,8 +155,8 @@ pub fn read_byte_array(data: &[u8], pos: &mut usize) -> Result<Vec<u8>, ParseErr
 
 /// Read a list of fixed 8-byte LE i64 values: varint length prefix, then N × 8 bytes
 pub fn read_list_of_i64(data: &[u8], pos: &mut usize) -> Result<Vec<i64>, ParseError> {
-    let len = varint::read_unsigned_varint(data, pos)? as usize;
-    let mut result = Vec::with_capacity(len);
+    let len = read_length(data, pos)?;
+    let mut result = list_with_capacity(len);
     for _ in 0..len {
         result.push(read_task_id(data, pos)?);
     }
@@ -111,8 +165,8 @@ pub fn read_list_of_i64(data: &[u8], pos: &mut usize) -> Result<Vec<i64>, ParseE
 
 /// Read a list of byte arrays: varint length prefix, then N byte arrays
 pub fn read_list_of_byte_arrays(data: &[u8], pos: &mut usize) -> Result<Vec<Vec<u8>>, ParseError> {
-    let len = varint::read_unsigned_varint(data, pos)? as usize;
-    let mut result = Vec::with_capacity(len);
+    let len = read_length(data, pos)?;
+    let mut result = list_with_capacity(len);
     for _ in 0..len {
         result.push(read_byte_array(data, pos)?);
     }
@@ -124,9 +178,9 @@ pub fn read_list_of_interned_strings(
     data: &[u8],
     pos: &mut usize,
     table: &mut StringInternTable,
-) -> Result<Vec<String>, ParseError> {
-    let len = varint::read_unsigned_varint(data, pos)? as usize;
-    let mut result = Vec::with_capacity(len);
+) -> Result<Vec<Arc<str>>, ParseError> {
+    let len = read_length(data, pos)?;
+    let mut result = list_with_capacity(len);
     for _ in 0..len {
         result.push(table.read_string(data, pos)?);
     }
@@ -153,8 +207,8 @@ pub fn read_list_of_positive_varint_i32(
     data: &[u8],
     pos: &mut usize,
 ) -> Result<Vec<i32>, ParseError> {
-    let len = varint::read_unsigned_varint(data, pos)? as usize;
-    let mut result = Vec::with_capacity(len);
+    let len = read_length(data, pos)?;
+    let mut result = list_with_capacity(len);
     for _ in 0..len {
         result.push(read_positive_varint_i32(data, pos)?);
     }
@@ -164,8 +218,8 @@ pub fn read_list_of_positive_varint_i32(
 /// Read a nested list of varint i32 (e.g. IndexedNormalizedSamples.indices = List<List<Integer>>):
 /// outer varint count, then for each inner list: varint count + N varints
 pub fn read_list_of_list_of_i32(data: &[u8], pos: &mut usize) -> Result<Vec<Vec<i32>>, ParseError> {
-    let outer_len = varint::read_unsigned_varint(data, pos)? as usize;
-    let mut result = Vec::with_capacity(outer_len);
+    let outer_len = read_length(data, pos)?;
+    let mut result = list_with_capacity(outer_len);
     for _ in 0..outer_len {
         result.push(read_list_of_positive_varint_i32(data, pos)?);
     }
@@ -173,38 +227,259 @@ pub fn read_list_of_list_of_i32(data: &[u8], pos: &mut usize) -> Result<Vec<Vec<
 }
 
 pub fn encode_zigzag_i64(n: i64) -> Vec<u8> {
-    let zigzag = ((n << 1) ^ (n >> 63)) as u64;
     let mut buf = Vec::new();
-    let mut value = zigzag;
-    loop {
-        let mut byte = (value & 0x7F) as u8;
-        value >>= 7;
-        if value != 0 {
-            byte |= 0x80;
-        }
-        buf.push(byte);
-        if value == 0 {
-            break;
-        }
-    }
+    write_zigzag_i64(&mut buf, n);
     buf
 }
 
 pub fn encode_unsigned_varint(n: u64) -> Vec<u8> {
     let mut buf = Vec::new();
+    write_unsigned_varint(&mut buf, n);
+    buf
+}
+
+/// Append a Kryo varlong (inverse of `varint::read_unsigned_varint`): LEB128, with a
+/// ninth byte holding the top eight bits in full
+pub fn write_unsigned_varint(out: &mut Vec<u8>, n: u64) {
     let mut value = n;
-    loop {
+    for _ in 0..8 {
         let mut byte = (value & 0x7F) as u8;
         value >>= 7;
         if value != 0 {
             byte |= 0x80;
         }
-        buf.push(byte);
+        out.push(byte);
         if value == 0 {
-            break;
+            return;
         }
     }
-    buf
+    out.push(value as u8);
+}
+
+/// Append a zigzag-encoded varint i32
+pub fn write_zigzag_i32(out: &mut Vec<u8>, n: i32) {
+    write_unsigned_varint(out, ((n << 1) ^ (n >> 31)) as u32 as u64);
+}
+
+/// Append a zigzag-encoded varint i64
+pub fn write_zigzag_i64(out: &mut Vec<u8>, n: i64) {
+    write_unsigned_varint(out, ((n << 1) ^ (n >> 63)) as u64);
+}
+
+/// Append flags as an unsigned varint (inverse of `read_flags_byte`)
+pub fn write_flags_byte(out: &mut Vec<u8>, flags: u8) {
+    write_unsigned_varint(out, flags as u64);
+}
+
+/// Append flags as fixed big-endian u16 (inverse of `read_flags_u16_be`)
+pub fn write_flags_u16_be(out: &mut Vec<u8>, flags: u16) {
+    out.extend_from_slice(&flags.to_be_bytes());
+}
+
+/// Append a task identity/correlation id as a fixed little-endian i64 (8 bytes)
+pub fn write_task_id(out: &mut Vec<u8>, id: i64) {
+    out.extend_from_slice(&id.to_le_bytes());
+}
+
+/// Append a byte array: unsigned varint length, then the bytes
+pub fn write_byte_array(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_unsigned_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Wire encoding of a single field value. Implemented by the marker types below and by
+/// schema-described records, so decoders and encoders share one description per field.
+pub trait Codec {
+    type Value;
+
+    fn read(
+        data: &[u8],
+        pos: &mut usize,
+        table: &mut StringInternTable,
+    ) -> Result<Self::Value, ParseError>;
+
+    fn write(value: &Self::Value, out: &mut Vec<u8>, table: &mut StringInternWriter);
+}
+
+/// Fixed 8-byte little-endian i64 (task ids, correlation ids)
+pub struct TaskId;
+
+impl Codec for TaskId {
+    type Value = i64;
+
+    fn read(data: &[u8], pos: &mut usize, _: &mut StringInternTable) -> Result<i64, ParseError> {
+        read_task_id(data, pos)
+    }
+
+    fn write(value: &i64, out: &mut Vec<u8>, _: &mut StringInternWriter) {
+        write_task_id(out, *value);
+    }
+}
+
+/// String through the per-body intern table
+pub struct InternedString;
+
+impl Codec for InternedString {
+    type Value = Arc<str>;
+
+    fn read(
+        data: &[u8],
+        pos: &mut usize,
+        table: &mut StringInternTable,
+    ) -> Result<Arc<str>, ParseError> {
+        table.read_string(data, pos)
+    }
+
+    fn write(value: &Arc<str>, out: &mut Vec<u8>, table: &mut StringInternWriter) {
+        table.write_string(out, value);
+    }
+}
+
+/// Enum ordinal as unsigned varint
+pub struct EnumOrdinal;
+
+impl Codec for EnumOrdinal {
+    type Value = u64;
+
+    fn read(data: &[u8], pos: &mut usize, _: &mut StringInternTable) -> Result<u64, ParseError> {
+        read_enum_ordinal(data, pos)
+    }
+
+    fn write(value: &u64, out: &mut Vec<u8>, _: &mut StringInternWriter) {
+        write_unsigned_varint(out, *value);
+    }
+}
+
+/// Zigzag varint i64 (Kryo `writeLong(optimizePositive=false)`)
+pub struct ZigzagI64;
+
+impl Codec for ZigzagI64 {
+    type Value = i64;
+
+    fn read(data: &[u8], pos: &mut usize, _: &mut StringInternTable) -> Result<i64, ParseError> {
+        read_zigzag_i64(data, pos)
+    }
+
+    fn write(value: &i64, out: &mut Vec<u8>, _: &mut StringInternWriter) {
+        write_zigzag_i64(out, *value);
+    }
+}
+
+/// Zigzag varint i32 (Kryo `writeInt(optimizePositive=false)`)
+pub struct ZigzagI32;
+
+impl Codec for ZigzagI32 {
+    type Value = i32;
+
+    fn read(data: &[u8], pos: &mut usize, _: &mut StringInternTable) -> Result<i32, ParseError> {
+        varint::read_zigzag_i32(data, pos)
+    }
+
+    fn write(value: &i32, out: &mut Vec<u8>, _: &mut StringInternWriter) {
+        write_zigzag_i32(out, *value);
+    }
+}
+
+/// Unsigned varint i64 (Kryo `writeLong(optimizePositive=true)`)
+pub struct PositiveI64;
+
+impl Codec for PositiveI64 {
+    type Value = i64;
+
+    fn read(data: &[u8], pos: &mut usize, _: &mut StringInternTable) -> Result<i64, ParseError> {
+        read_positive_varint_i64(data, pos)
+    }
+
+    fn write(value: &i64, out: &mut Vec<u8>, _: &mut StringInternWriter) {
+        write_unsigned_varint(out, *value as u64);
+    }
+}
+
+/// Unsigned varint i32 (Kryo `writeInt(optimizePositive=true)`)
+pub struct PositiveI32;
+
+impl Codec for PositiveI32 {
+    type Value = i32;
+
+    fn read(data: &[u8], pos: &mut usize, _: &mut StringInternTable) -> Result<i32, ParseError> {
+        read_positive_varint_i32(data, pos)
+    }
+
+    fn write(value: &i32, out: &mut Vec<u8>, _: &mut StringInternWriter) {
+        write_unsigned_varint(out, *value as u32 as u64);
+    }
+}
+
+/// Length-prefixed byte array
+pub struct ByteArray;
+
+impl Codec for ByteArray {
+    type Value = Vec<u8>;
+
+    fn read(
+        data: &[u8],
+        pos: &mut usize,
+        _: &mut StringInternTable,
+    ) -> Result<Vec<u8>, ParseError> {
+        read_byte_array(data, pos)
+    }
+
+    fn write(value: &Vec<u8>, out: &mut Vec<u8>, _: &mut StringInternWriter) {
+        write_byte_array(out, value);
+    }
+}
+
+/// Varint length prefix, then N values of the element codec
+pub struct ListOf<C>(PhantomData<C>);
+
+impl<C: Codec> Codec for ListOf<C> {
+    type Value = Vec<C::Value>;
+
+    fn read(
+        data: &[u8],
+        pos: &mut usize,
+        table: &mut StringInternTable,
+    ) -> Result<Self::Value, ParseError> {
+        let len = read_length(data, pos)?;
+        let mut result = list_with_capacity(len);
+        for _ in 0..len {
+            result.push(C::read(data, pos, table)?);
+        }
+        Ok(result)
+    }
+
+    fn write(value: &Self::Value, out: &mut Vec<u8>, table: &mut StringInternWriter) {
+        write_unsigned_varint(out, value.len() as u64);
+        for item in value {
+            C::write(item, out, table);
+        }
+    }
+}
+
+/// `ListOf` preceded by a varint that is zero in every capture seen so far (likely
+/// null-element bookkeeping). Any other value is rejected rather than guessed at.
+pub struct PrefixedListOf<C>(PhantomData<C>);
+
+impl<C: Codec> Codec for PrefixedListOf<C> {
+    type Value = Vec<C::Value>;
+
+    fn read(
+        data: &[u8],
+        pos: &mut usize,
+        table: &mut StringInternTable,
+    ) -> Result<Self::Value, ParseError> {
+        let offset = *pos;
+        let prefix = varint::read_unsigned_varint(data, pos)?;
+        if prefix != 0 {
+            return Err(ParseError::UnsupportedListPrefix { prefix, offset });
+        }
+        ListOf::<C>::read(data, pos, table)
+    }
+
+    fn write(value: &Self::Value, out: &mut Vec<u8>, table: &mut StringInternWriter) {
+        write_unsigned_varint(out, 0);
+        ListOf::<C>::write(value, out, table);
+    }
 }
 
 #[cfg(test)]
@@ -225,7 +500,7 @@ mod tests {
         let data = [0x06, 0x66, 0x6f, 0x6f];
         let mut pos = 0;
         let mut table = StringInternTable::new();
-        assert_eq!(table.read_string(&data, &mut pos).unwrap(), "foo");
+        assert_eq!(&*table.read_string(&data, &mut pos).unwrap(), "foo");
         assert_eq!(pos, 4);
     }
 
@@ -236,8 +511,8 @@ mod tests {
         let data = [0x06, 0x66, 0x6f, 0x6f, 0x01];
         let mut pos = 0;
         let mut table = StringInternTable::new();
-        assert_eq!(table.read_string(&data, &mut pos).unwrap(), "foo");
-        assert_eq!(table.read_string(&data, &mut pos).unwrap(), "foo");
+        assert_eq!(&*table.read_string(&data, &mut pos).unwrap(), "foo");
+        assert_eq!(&*table.read_string(&data, &mut pos).unwrap(), "foo");
         assert_eq!(pos, 5);
     }
 
@@ -247,7 +522,7 @@ mod tests {
         let data = [0x00];
         let mut pos = 0;
         let mut table = StringInternTable::new();
-        assert_eq!(table.read_string(&data, &mut pos).unwrap(), "");
+        assert_eq!(&*table.read_string(&data, &mut pos).unwrap(), "");
     }
 
     #[test]
@@ -261,10 +536,10 @@ mod tests {
         ];
         let mut pos = 0;
         let mut table = StringInternTable::new();
-        assert_eq!(table.read_string(&data, &mut pos).unwrap(), "abc");
-        assert_eq!(table.read_string(&data, &mut pos).unwrap(), "xyz");
-        assert_eq!(table.read_string(&data, &mut pos).unwrap(), "abc");
-        assert_eq!(table.read_string(&data, &mut pos).unwrap(), "xyz");
+        assert_eq!(&*table.read_string(&data, &mut pos).unwrap(), "abc");
+        assert_eq!(&*table.read_string(&data, &mut pos).unwrap(), "xyz");
+        assert_eq!(&*table.read_string(&data, &mut pos).unwrap(), "abc");
+        assert_eq!(&*table.read_string(&data, &mut pos).unwrap(), "xyz");
     }
 
     #[test]
@@ -365,7 +640,7 @@ mod tests {
         let mut table = StringInternTable::new();
         assert_eq!(
             read_list_of_interned_strings(&data, &mut pos, &mut table).unwrap(),
-            Vec::<String>::new()
+            Vec::<Arc<str>>::new()
         );
     }
 
@@ -380,7 +655,8 @@ mod tests {
         let mut pos = 0;
         let mut table = StringInternTable::new();
         let result = read_list_of_interned_strings(&data, &mut pos, &mut table).unwrap();
-        assert_eq!(result, vec!["foo".to_string(), "foo".to_string()]);
+        assert_eq!(result, vec![Arc::from("foo"), Arc::from("foo")]);
+        assert!(Arc::ptr_eq(&result[0], &result[1]));
     }
 
     #[test]
@@ -454,4 +730,73 @@ mod tests {
             vec![10, 20, 30]
         );
     }
+
+    #[test]
+    fn test_string_intern_writer_back_reference() {
+        let mut out = Vec::new();
+        let mut writer = StringInternWriter::new();
+        writer.write_string(&mut out, "abc");
+        writer.write_string(&mut out, "xyz");
+        writer.write_string(&mut out, "abc");
+        assert_eq!(out, vec![0x06, 97, 98, 99, 0x06, 120, 121, 122, 0x01]);
+
+        let mut pos = 0;
+        let mut table = StringInternTable::new();
+        assert_eq!(&*table.read_string(&out, &mut pos).unwrap(), "abc");
+        assert_eq!(&*table.read_string(&out, &mut pos).unwrap(), "xyz");
+        assert_eq!(&*table.read_string(&out, &mut pos).unwrap(), "abc");
+    }
+
+    #[test]
+    fn test_codec_roundtrip_nested_list() {
+        let value = vec![vec![1, -2], vec![], vec![i32::MAX]];
+        let mut out = Vec::new();
+        ListOf::<ListOf<PositiveI32>>::write(&value, &mut out, &mut StringInternWriter::new());
+        let mut pos = 0;
+        let decoded =
+            ListOf::<ListOf<PositiveI32>>::read(&out, &mut pos, &mut StringInternTable::new())
+                .unwrap();
+        assert_eq!(decoded, value);
+        assert_eq!(pos, out.len());
+    }
+
+    #[test]
+    fn test_list_length_beyond_input_rejected() {
+        // length = u32::MAX, followed by a single byte
+        let data = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x00];
+        let mut pos = 0;
+        let mut table = StringInternTable::new();
+        let result = read_list_of_interned_strings(&data, &mut pos, &mut table);
+        assert!(matches!(
+            result,
+            Err(ParseError::LengthOutOfBounds {
+                len: 0xFFFF_FFFF,
+                offset: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_byte_array_length_overflow_rejected() {
+        // length = u64::MAX would overflow `pos + len`
+        let data = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+        let mut pos = 0;
+        assert!(matches!(
+            read_byte_array(&data, &mut pos),
+            Err(ParseError::LengthOutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_string_char_count_beyond_input_rejected() {
+        // zigzag(1_000_000) with only two chars following
+        let mut data = encode_zigzag_i64(1_000_000);
+        data.extend_from_slice(b"ab");
+        let mut pos = 0;
+        let mut table = StringInternTable::new();
+        assert!(matches!(
+            table.read_string(&data, &mut pos),
+            Err(ParseError::StringLengthOutOfBounds { len: 1_000_000, .. })
+        ));
+    }
 }
@@ -1,21 +1,147 @@
 use error::ParseError;
-use events::DecodedEvent;
+use events::{DecodedEvent, DecoderRegistry};
 use framing::FramedEvent;
 use models::BuildScanPayload;
+use rayon::prelude::*;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// Frame the whole stream first, then decode bodies on the rayon thread pool.
+    /// Output and error reporting are identical to sequential decoding.
+    pub parallel_decode: bool,
+}
 
 pub fn parse(raw_bytes: &[u8]) -> Result<BuildScanPayload, ParseError> {
+    parse_with(raw_bytes, ParseOptions::default())
+}
+
+pub fn parse_with(raw_bytes: &[u8], options: ParseOptions) -> Result<BuildScanPayload, ParseError> {
     let header = outer_header::OuterHeader::parse(raw_bytes)?;
-    let decompressed = decompress::Decompressor::decompress(&raw_bytes[header.gzip_offset..])?;
-    let registry = events::DecoderRegistry::new();
+    let mut payload = parse_gzip_with(&raw_bytes[header.gzip_offset..], options)?;
+    payload.header = Some(models::HeaderData {
+        version: header.version,
+        tool_type: header.tool_type,
+        tool_version: header.tool_version,
+        plugin_version: header.plugin_version,
+    });
+    Ok(payload)
+}
 
-    let decoded_events: Result<Vec<(FramedEvent, DecodedEvent)>, ParseError> =
+/// Parses the gzip event stream on its own, for inputs that arrive without the outer
+/// header.
+pub fn parse_gzip_with(gzip: &[u8], options: ParseOptions) -> Result<BuildScanPayload, ParseError> {
+    let decompressed = decompress::Decompressor::decompress(gzip)?;
+    let registry = DecoderRegistry::new();
+
+    let decoded_events = if options.parallel_decode {
+        decode_parallel(&registry, &decompressed)?
+    } else {
         framing::EventFrameReader::new(&decompressed)
             .map(|frame_result| {
                 let frame = frame_result?;
-                let decoded = registry.decode(frame.wire_id, &frame.body)?;
+                let decoded = registry.decode(frame.wire_id, frame.body)?;
                 Ok((frame, decoded))
             })
+            .collect::<Result<Vec<_>, ParseError>>()?
+    };
+
+    Ok(assembly::assemble(decoded_events))
+}
+
+/// Frames sequentially up to the first framing error, decodes the frames read so far in
+/// parallel, and reports whichever error comes first in stream order.
+fn decode_parallel<'a>(
+    registry: &DecoderRegistry,
+    stream: &'a [u8],
+) -> Result<Vec<(FramedEvent<'a>, DecodedEvent<'a>)>, ParseError> {
+    let mut frames = Vec::new();
+    let mut framing_error = None;
+    for frame_result in framing::EventFrameReader::new(stream) {
+        match frame_result {
+            Ok(frame) => frames.push(frame),
+            Err(e) => {
+                framing_error = Some(e);
+                break;
+            }
+        }
+    }
+
+    let decoded = decode_frames(registry, frames)?;
+    match framing_error {
+        Some(e) => Err(e),
+        None => Ok(decoded),
+    }
+}
+
+/// Decodes already-framed events across the rayon pool, preserving frame order. On
+/// failure returns the error of the earliest frame that failed.
+pub fn decode_frames<'a>(
+    registry: &DecoderRegistry,
+    frames: Vec<FramedEvent<'a>>,
+) -> Result<Vec<(FramedEvent<'a>, DecodedEvent<'a>)>, ParseError> {
+    let decoded: Vec<Result<DecodedEvent<'a>, ParseError>> = frames
+        .par_iter()
+        .map(|frame| registry.decode(frame.wire_id, frame.body))
+        .collect();
+
+    frames
+        .into_iter()
+        .zip(decoded)
+        .map(|(frame, decoded)| Ok((frame, decoded?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(wire_id: u16, ordinal: i32, body: &[u8]) -> FramedEvent<'_> {
+        FramedEvent {
+            wire_id,
+            timestamp: ordinal as i64,
+            actual_timestamp: ordinal as i64,
+            ordinal,
+            flags: 0,
+            offset: 0,
+            frame_len: 0,
+            body,
+        }
+    }
+
+    #[test]
+    fn test_decode_frames_preserves_order() {
+        let registry = DecoderRegistry::new();
+        // Hardware_1_0 bodies carry a single positive varint.
+        let bodies: Vec<[u8; 1]> = (0..200u8).map(|n| [n & 0x7f]).collect();
+        let frames: Vec<_> = bodies
+            .iter()
+            .enumerate()
+            .map(|(i, body)| frame(12, i as i32, body))
             .collect();
 
-    Ok(assembly::assemble(decoded_events?))
+        let decoded = decode_frames(&registry, frames).unwrap();
+        assert_eq!(decoded.len(), 200);
+        for (i, (frame, event)) in decoded.iter().enumerate() {
+            assert_eq!(frame.ordinal, i as i32);
+            let expected = registry.decode(12, &bodies[i]).unwrap();
+            assert_eq!(event, &expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_frames_reports_earliest_error() {
+        let registry = DecoderRegistry::new();
+        // Empty DaemonState / BuildAgent bodies are truncated; wire 999 is undecoded.
+        let frames = vec![
+            frame(999, 1, &[1, 2, 3]),
+            frame(265, 2, &[]),
+            frame(2, 3, &[]),
+        ];
+
+        let err = decode_frames(&registry, frames).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::InvalidEventBody { wire_id: 265, .. }
+        ));
+    }
 }
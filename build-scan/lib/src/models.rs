@@ -1,12 +1,22 @@
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct BuildScanPayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<HeaderData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentData>,
     pub tasks: Vec<Task>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub planned_nodes: Vec<PlannedNodeData>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub transform_execution_requests: Vec<TransformExecutionRequestData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transform_executions: Vec<TransformExecutionData>,
     pub raw_events: Vec<RawEventSummary>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub task_registration_summary: Option<TaskRegistrationSummaryData>,
@@ -16,21 +26,119 @@ pub struct BuildScanPayload {
     pub resource_usage: Option<ResourceUsageData>,
 }
 
+impl BuildScanPayload {
+    /// Build start, falling back to the earliest task start.
+    pub fn started_at(&self) -> Option<i64> {
+        self.build
+            .as_ref()
+            .and_then(|b| b.started_at)
+            .or_else(|| self.tasks.iter().filter_map(|t| t.started_at).min())
+    }
+
+    /// Indices of `scans` in chronological order. Scans without a start time keep their
+    /// relative order after the timed ones.
+    pub fn chronological<T>(scans: &[T], payload: impl Fn(&T) -> &Self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..scans.len()).collect();
+        order.sort_by_key(|&i| {
+            let started_at = payload(&scans[i]).started_at();
+            (started_at.is_none(), started_at, i)
+        });
+        order
+    }
+}
+
+/// Versions from the outer upload header. Absent when only the gzip stream was parsed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderData {
+    pub version: u16,
+    pub tool_type: String,
+    pub tool_version: String,
+    pub plugin_version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BuildData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<BuildOutcome>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requested_tasks: Vec<Arc<str>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_tasks: Vec<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modes: Option<BuildModesData>,
+}
+
+/// Command-line modes of the invocation. `max_workers` lives in `EnvironmentData`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildModesData {
+    pub refresh_dependencies: bool,
+    pub parallel_project_execution: bool,
+    pub rerun_tasks: bool,
+    pub continuous: bool,
+    pub continue_on_failure: bool,
+    pub configure_on_demand: bool,
+    pub daemon: bool,
+    pub offline: bool,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildOutcome {
+    Success,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EnvironmentData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_family: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_name: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_version: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_arch: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jvm_version: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jvm_vendor: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jvm_vm_name: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_processors: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_workers: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_charset: Option<Arc<str>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: i64,
-    pub build_path: String,
-    pub task_path: String,
+    pub build_path: Arc<str>,
+    pub task_path: Arc<str>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub class_name: Option<String>,
+    pub class_name: Option<Arc<str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub outcome: Option<TaskOutcome>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cacheable: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub caching_disabled_reason: Option<String>,
+    pub caching_disabled_reason: Option<Arc<str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub caching_disabled_explanation: Option<String>,
+    pub caching_disabled_explanation: Option<Arc<str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub origin_build_cache_key: Option<Vec<u8>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -45,7 +153,7 @@ pub struct Task {
     pub inputs: Option<TaskInputs>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum TaskOutcome {
     UpToDate,
     Skipped,
@@ -69,11 +177,28 @@ impl TaskOutcome {
             _ => None,
         }
     }
+
+    /// Gradle's own spelling, e.g. `UP_TO_DATE`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UpToDate => "UP_TO_DATE",
+            Self::Skipped => "SKIPPED",
+            Self::Failed => "FAILED",
+            Self::Success => "SUCCESS",
+            Self::FromCache => "FROM_CACHE",
+            Self::NoSource => "NO_SOURCE",
+            Self::AvoidedForUnknownReason => "AVOIDED_FOR_UNKNOWN_REASON",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawEventSummary {
     pub wire_id: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
     pub count: usize,
 }
 
@@ -85,9 +210,9 @@ pub struct TaskInputs {
     pub implementation: Option<TaskInputsImplementationData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub value_properties: Option<TaskInputsValuePropertiesData>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub file_property_roots: Vec<TaskInputsFilePropertyRootData>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub file_properties: Vec<TaskInputsFilePropertyData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub snapshotting_result: Option<TaskInputsSnapshottingResultData>,
@@ -95,9 +220,9 @@ pub struct TaskInputs {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskInputsPropertyNamesData {
-    pub value_inputs: Vec<String>,
-    pub file_inputs: Vec<String>,
-    pub outputs: Vec<String>,
+    pub value_inputs: Vec<Arc<str>>,
+    pub file_inputs: Vec<Arc<str>>,
+    pub outputs: Vec<Arc<str>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,7 +230,7 @@ pub struct TaskInputsImplementationData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub class_loader_hash: Option<Vec<u8>>,
     pub action_class_loader_hashes: Vec<Vec<u8>>,
-    pub action_class_names: Vec<String>,
+    pub action_class_names: Vec<Arc<str>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,7 +241,7 @@ pub struct TaskInputsValuePropertiesData {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskInputsFilePropertyRootData {
     pub file_root: Option<u64>,
-    pub file_path: Option<String>,
+    pub file_path: Option<Arc<str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub root_hash: Option<Vec<u8>>,
     pub children: Vec<FilePropertyRootChildData>,
@@ -125,7 +250,7 @@ pub struct TaskInputsFilePropertyRootData {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilePropertyRootChildData {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub name: Option<String>,
+    pub name: Option<Arc<str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hash: Option<Vec<u8>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -134,7 +259,7 @@ pub struct FilePropertyRootChildData {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskInputsFilePropertyData {
-    pub attributes: Vec<String>,
+    pub attributes: Vec<Arc<str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hash: Option<Vec<u8>>,
     pub roots: Vec<i64>,
@@ -173,6 +298,29 @@ pub struct TransformExecutionRequestData {
     pub execution_id: Option<i64>,
 }
 
+/// A transform execution joined with its request and identification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformExecutionData {
+    pub id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identification_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transform_action_class: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_artifact_name: Option<Arc<str>>,
+    /// Raw outcome ordinal; the enum it indexes is not yet mapped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskRegistrationSummaryData {
     pub task_count: i32,
@@ -186,7 +334,7 @@ pub struct BasicMemoryStatsData {
     pub total: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max: Option<i64>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub peak_snapshots: Vec<MemoryPoolSnapshotData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gc_time: Option<i64>,
@@ -195,7 +343,7 @@ pub struct BasicMemoryStatsData {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryPoolSnapshotData {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub name: Option<String>,
+    pub name: Option<Arc<str>>,
     pub heap: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub init: Option<i64>,
@@ -209,7 +357,7 @@ pub struct MemoryPoolSnapshotData {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceUsageData {
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub timestamps: Vec<Vec<u8>>,
     pub build_process_cpu: NormalizedSamplesData,
     pub build_child_processes_cpu: NormalizedSamplesData,
@@ -225,7 +373,7 @@ pub struct ResourceUsageData {
     pub disk_write_speed: NormalizedSamplesData,
     pub network_download_speed: NormalizedSamplesData,
     pub network_upload_speed: NormalizedSamplesData,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub processes: Vec<ProcessData>,
     pub top_processes_by_cpu: IndexedNormalizedSamplesData,
     pub top_processes_by_memory: IndexedNormalizedSamplesData,
@@ -241,9 +389,9 @@ pub struct NormalizedSamplesData {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexedNormalizedSamplesData {
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub indices: Vec<Vec<i32>>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub samples: Vec<Vec<u8>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max: Option<i64>,
@@ -254,9 +402,9 @@ pub struct ProcessData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub name: Option<String>,
+    pub name: Option<Arc<str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub display_name: Option<String>,
+    pub display_name: Option<Arc<str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub process_type: Option<String>,
 }
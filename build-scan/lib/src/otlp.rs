@@ -0,0 +1,413 @@
+//! OTLP/JSON trace export of a parsed build scan.
+//!
+//! Produces an `ExportTraceServiceRequest` in the protobuf JSON mapping that OTLP/HTTP
+//! collectors accept on `/v1/traces`. The build is the root span; configuration, tasks
+//! and transform executions are its children. Configuration isn't reported directly, so
+//! its span covers the time from build start until the first task or transform starts.
+//!
+//! Span ids are assigned sequentially in export order, which keeps output for a given
+//! trace id reproducible.
+
+use models::{BuildOutcome, BuildScanPayload, TaskOutcome};
+use serde::Serialize;
+
+const SCOPE_NAME: &str = "gradle-build-scan";
+const SPAN_KIND_INTERNAL: u8 = 1;
+const STATUS_OK: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportTraceServiceRequest {
+    pub resource_spans: Vec<ResourceSpans>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceSpans {
+    pub resource: Resource,
+    pub scope_spans: Vec<ScopeSpans>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Resource {
+    pub attributes: Vec<KeyValue>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeSpans {
+    pub scope: Scope,
+    pub spans: Vec<Span>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Scope {
+    pub name: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Span {
+    pub trace_id: String,
+    pub span_id: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub parent_span_id: String,
+    pub name: String,
+    pub kind: u8,
+    /// Decimal string, as the JSON mapping requires for 64-bit integers.
+    pub start_time_unix_nano: String,
+    pub end_time_unix_nano: String,
+    pub attributes: Vec<KeyValue>,
+    pub status: Status,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub code: u8,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeyValue {
+    pub key: &'static str,
+    pub value: AnyValue,
+}
+
+#[derive(Debug, Serialize)]
+pub enum AnyValue {
+    #[serde(rename = "stringValue")]
+    String(String),
+    #[serde(rename = "boolValue")]
+    Bool(bool),
+    #[serde(rename = "intValue")]
+    Int(String),
+}
+
+fn string(key: &'static str, value: impl Into<String>) -> KeyValue {
+    KeyValue {
+        key,
+        value: AnyValue::String(value.into()),
+    }
+}
+
+fn boolean(key: &'static str, value: bool) -> KeyValue {
+    KeyValue {
+        key,
+        value: AnyValue::Bool(value),
+    }
+}
+
+fn int(key: &'static str, value: i64) -> KeyValue {
+    KeyValue {
+        key,
+        value: AnyValue::Int(value.to_string()),
+    }
+}
+
+struct SpanBuilder {
+    trace_id: String,
+    next_id: u64,
+    spans: Vec<Span>,
+}
+
+impl SpanBuilder {
+    fn push(
+        &mut self,
+        parent: &str,
+        name: String,
+        (start_ms, end_ms): (i64, i64),
+        attributes: Vec<KeyValue>,
+        failed: bool,
+    ) -> String {
+        self.next_id += 1;
+        let span_id = format!("{:016x}", self.next_id);
+        self.spans.push(Span {
+            trace_id: self.trace_id.clone(),
+            span_id: span_id.clone(),
+            parent_span_id: parent.to_string(),
+            name,
+            kind: SPAN_KIND_INTERNAL,
+            start_time_unix_nano: unix_nanos(start_ms),
+            end_time_unix_nano: unix_nanos(end_ms.max(start_ms)),
+            attributes,
+            status: Status {
+                code: if failed { STATUS_ERROR } else { STATUS_OK },
+            },
+        });
+        span_id
+    }
+}
+
+fn unix_nanos(ms: i64) -> String {
+    (ms.max(0) as u64).saturating_mul(1_000_000).to_string()
+}
+
+/// Builds the trace. Returns `None` when the scan has nothing with both a start and an
+/// end time to anchor the root span.
+pub fn export(payload: &BuildScanPayload, trace_id: [u8; 16]) -> Option<ExportTraceServiceRequest> {
+    let task_times = payload
+        .tasks
+        .iter()
+        .filter_map(|t| Some((t.started_at?, t.finished_at?)));
+    let transform_times = payload
+        .transform_executions
+        .iter()
+        .filter_map(|t| Some((t.started_at?, t.finished_at?)));
+    let work: Vec<(i64, i64)> = task_times.chain(transform_times).collect();
+    let first_work = work.iter().map(|w| w.0).min();
+
+    let build = payload.build.as_ref();
+    let start = build.and_then(|b| b.started_at).or(first_work)?;
+    let end = build
+        .and_then(|b| b.finished_at)
+        .or_else(|| work.iter().map(|w| w.1).max())?;
+
+    let mut builder = SpanBuilder {
+        trace_id: trace_id.iter().map(|b| format!("{b:02x}")).collect(),
+        next_id: 0,
+        spans: Vec::new(),
+    };
+
+    let mut build_attributes = Vec::new();
+    let mut name = "Gradle build".to_string();
+    let mut failed = false;
+    if let Some(build) = build {
+        if !build.requested_tasks.is_empty() {
+            name = format!("gradle {}", build.requested_tasks.join(" "));
+            build_attributes.push(string(
+                "gradle.requested_tasks",
+                build.requested_tasks.join(" "),
+            ));
+        }
+        if let Some(outcome) = build.outcome {
+            failed = outcome == BuildOutcome::Failed;
+            build_attributes.push(string(
+                "gradle.build.outcome",
+                if failed { "FAILED" } else { "SUCCESS" },
+            ));
+        }
+        if let Some(modes) = &build.modes {
+            build_attributes.extend([
+                boolean(
+                    "gradle.mode.refresh_dependencies",
+                    modes.refresh_dependencies,
+                ),
+                boolean("gradle.mode.parallel", modes.parallel_project_execution),
+                boolean("gradle.mode.rerun_tasks", modes.rerun_tasks),
+                boolean("gradle.mode.continuous", modes.continuous),
+                boolean("gradle.mode.continue_on_failure", modes.continue_on_failure),
+                boolean("gradle.mode.configure_on_demand", modes.configure_on_demand),
+                boolean("gradle.mode.daemon", modes.daemon),
+                boolean("gradle.mode.offline", modes.offline),
+                boolean("gradle.mode.dry_run", modes.dry_run),
+            ]);
+        }
+    }
+    if let Some(workers) = payload.environment.as_ref().and_then(|e| e.max_workers) {
+        build_attributes.push(int("gradle.max_workers", workers.into()));
+    }
+    let root = builder.push("", name, (start, end), build_attributes, failed);
+
+    if let Some(first) = first_work.filter(|&first| first > start) {
+        builder.push(
+            &root,
+            "Configuration".to_string(),
+            (start, first),
+            Vec::new(),
+            false,
+        );
+    }
+
+    for task in &payload.tasks {
+        let (Some(s), Some(e)) = (task.started_at, task.finished_at) else {
+            continue;
+        };
+        let mut attributes = vec![
+            string("gradle.task.path", &*task.task_path),
+            string("gradle.task.build_path", &*task.build_path),
+        ];
+        if let Some(class_name) = &task.class_name {
+            attributes.push(string("gradle.task.class", &**class_name));
+        }
+        if let Some(outcome) = task.outcome {
+            attributes.push(string("gradle.task.outcome", outcome.as_str()));
+        }
+        if let Some(cacheable) = task.cacheable {
+            attributes.push(boolean("gradle.task.cacheable", cacheable));
+        }
+        let failed = task.outcome == Some(TaskOutcome::Failed);
+        builder.push(
+            &root,
+            task.task_path.to_string(),
+            (s, e),
+            attributes,
+            failed,
+        );
+    }
+
+    for transform in &payload.transform_executions {
+        let (Some(s), Some(e)) = (transform.started_at, transform.finished_at) else {
+            continue;
+        };
+        let mut attributes = Vec::new();
+        if let Some(action) = &transform.transform_action_class {
+            attributes.push(string("gradle.transform.action", &**action));
+        }
+        if let Some(artifact) = &transform.input_artifact_name {
+            attributes.push(string("gradle.transform.input_artifact", &**artifact));
+        }
+        if let Some(outcome) = transform.outcome {
+            attributes.push(int("gradle.transform.outcome_ordinal", outcome as i64));
+        }
+        let name = match &transform.input_artifact_name {
+            Some(artifact) => format!("transform {artifact}"),
+            None => "transform".to_string(),
+        };
+        builder.push(
+            &root,
+            name,
+            (s, e),
+            attributes,
+            transform.failure_id.is_some(),
+        );
+    }
+
+    Some(ExportTraceServiceRequest {
+        resource_spans: vec![ResourceSpans {
+            resource: Resource {
+                attributes: resource_attributes(payload),
+            },
+            scope_spans: vec![ScopeSpans {
+                scope: Scope { name: SCOPE_NAME },
+                spans: builder.spans,
+            }],
+        }],
+    })
+}
+
+fn resource_attributes(payload: &BuildScanPayload) -> Vec<KeyValue> {
+    let mut attributes = vec![string("service.name", "gradle")];
+    if let Some(header) = &payload.header {
+        attributes.push(string("gradle.version", header.tool_version.clone()));
+        attributes.push(string(
+            "gradle.plugin.version",
+            header.plugin_version.clone(),
+        ));
+    }
+    let Some(env) = &payload.environment else {
+        return attributes;
+    };
+    let optional = [
+        ("os.type", &env.os_family),
+        ("os.name", &env.os_name),
+        ("os.version", &env.os_version),
+        ("host.arch", &env.os_arch),
+        ("host.name", &env.hostname),
+        ("process.runtime.version", &env.jvm_version),
+        ("process.runtime.name", &env.jvm_vm_name),
+        ("process.runtime.vendor", &env.jvm_vendor),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            attributes.push(string(key, &**value));
+        }
+    }
+    if let Some(cpus) = env.num_processors {
+        attributes.push(int("host.cpu.count", cpus.into()));
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::{BuildData, BuildModesData, EnvironmentData, Task};
+
+    fn task(id: i64, outcome: TaskOutcome, start: i64, end: i64) -> Task {
+        Task {
+            id,
+            build_path: ":".into(),
+            task_path: format!(":t{id}").into(),
+            class_name: None,
+            outcome: Some(outcome),
+            cacheable: Some(false),
+            caching_disabled_reason: None,
+            caching_disabled_explanation: None,
+            origin_build_cache_key: None,
+            actionable: None,
+            started_at: Some(start),
+            finished_at: Some(end),
+            duration_ms: Some(end - start),
+            inputs: None,
+        }
+    }
+
+    #[test]
+    fn test_export_span_tree() {
+        let payload = BuildScanPayload {
+            build: Some(BuildData {
+                started_at: Some(1_000),
+                finished_at: Some(2_000),
+                outcome: Some(BuildOutcome::Failed),
+                requested_tasks: vec!["build".into()],
+                modes: Some(BuildModesData {
+                    offline: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            environment: Some(EnvironmentData {
+                os_name: Some("Linux".into()),
+                jvm_version: Some("21.0.2".into()),
+                ..Default::default()
+            }),
+            tasks: vec![
+                task(1, TaskOutcome::Success, 1_200, 1_500),
+                task(2, TaskOutcome::Failed, 1_300, 1_900),
+            ],
+            ..Default::default()
+        };
+        let request = export(&payload, [0xab; 16]).unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+
+        let resource = &json["resourceSpans"][0]["resource"]["attributes"];
+        assert!(
+            resource
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|kv| kv["key"] == "os.name" && kv["value"]["stringValue"] == "Linux")
+        );
+
+        let spans = json["resourceSpans"][0]["scopeSpans"][0]["spans"]
+            .as_array()
+            .unwrap();
+        assert_eq!(spans.len(), 4);
+        let root = &spans[0];
+        assert_eq!(root["name"], "gradle build");
+        assert_eq!(root["traceId"], "ab".repeat(16));
+        assert_eq!(root["spanId"], "0000000000000001");
+        assert!(root.get("parentSpanId").is_none());
+        assert_eq!(root["startTimeUnixNano"], "1000000000");
+        assert_eq!(root["status"]["code"], STATUS_ERROR);
+        assert!(
+            root["attributes"].as_array().unwrap().iter().any(|kv| {
+                kv["key"] == "gradle.mode.offline" && kv["value"]["boolValue"] == true
+            })
+        );
+
+        assert_eq!(spans[1]["name"], "Configuration");
+        assert_eq!(spans[1]["endTimeUnixNano"], "1200000000");
+        for child in &spans[1..] {
+            assert_eq!(child["parentSpanId"], "0000000000000001");
+        }
+        assert_eq!(spans[2]["status"]["code"], STATUS_OK);
+        assert_eq!(spans[3]["status"]["code"], STATUS_ERROR);
+        assert_eq!(spans[3]["attributes"][2]["value"]["stringValue"], "FAILED");
+    }
+
+    #[test]
+    fn test_export_without_timing() {
+        assert!(export(&BuildScanPayload::default(), [0; 16]).is_none());
+    }
+}
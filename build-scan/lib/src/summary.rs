@@ -0,0 +1,145 @@
+//! Aggregate figures over a parsed build scan: task counts by outcome, the slowest
+//! tasks and build cache effectiveness.
+
+use models::{BuildScanPayload, Task, TaskOutcome};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Tasks restored from the build cache.
+    pub hits: usize,
+    /// Cacheable tasks that executed anyway.
+    pub misses: usize,
+    /// Executed tasks that were not cacheable.
+    pub not_cacheable: usize,
+    pub up_to_date: usize,
+}
+
+impl CacheStats {
+    /// Share of cacheable work served from the cache, `None` when nothing was cacheable.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let cacheable = self.hits + self.misses;
+        (cacheable > 0).then(|| self.hits as f64 / cacheable as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanSummary<'a> {
+    pub task_count: usize,
+    /// Non-zero counts in `TaskOutcome` declaration order.
+    pub outcomes: Vec<(TaskOutcome, usize)>,
+    /// Tasks that never reported a `TaskFinished` outcome.
+    pub without_outcome: usize,
+    /// Longest first, ties broken by task id.
+    pub slowest: Vec<&'a Task>,
+    pub cache: CacheStats,
+}
+
+pub fn summarize(payload: &BuildScanPayload, slowest: usize) -> ScanSummary<'_> {
+    let mut counts = std::collections::BTreeMap::new();
+    let mut without_outcome = 0;
+    let mut cache = CacheStats::default();
+
+    for task in &payload.tasks {
+        let Some(outcome) = task.outcome else {
+            without_outcome += 1;
+            continue;
+        };
+        *counts.entry(outcome).or_insert(0) += 1;
+        match outcome {
+            TaskOutcome::FromCache => cache.hits += 1,
+            TaskOutcome::UpToDate => cache.up_to_date += 1,
+            TaskOutcome::Success | TaskOutcome::Failed => {
+                if task.cacheable == Some(true) {
+                    cache.misses += 1;
+                } else {
+                    cache.not_cacheable += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut timed: Vec<&Task> = payload
+        .tasks
+        .iter()
+        .filter(|t| t.duration_ms.is_some())
+        .collect();
+    timed.sort_by(|a, b| b.duration_ms.cmp(&a.duration_ms).then(a.id.cmp(&b.id)));
+    timed.truncate(slowest);
+
+    ScanSummary {
+        task_count: payload.tasks.len(),
+        outcomes: counts.into_iter().collect(),
+        without_outcome,
+        slowest: timed,
+        cache,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: i64, outcome: Option<TaskOutcome>, cacheable: bool, duration: i64) -> Task {
+        Task {
+            id,
+            build_path: ":".into(),
+            task_path: format!(":t{id}").into(),
+            class_name: None,
+            outcome,
+            cacheable: Some(cacheable),
+            caching_disabled_reason: None,
+            caching_disabled_explanation: None,
+            origin_build_cache_key: None,
+            actionable: None,
+            started_at: Some(0),
+            finished_at: Some(duration),
+            duration_ms: Some(duration),
+            inputs: None,
+        }
+    }
+
+    #[test]
+    fn test_summarize_counts_and_cache() {
+        let payload = BuildScanPayload {
+            tasks: vec![
+                task(1, Some(TaskOutcome::Success), true, 300),
+                task(2, Some(TaskOutcome::FromCache), true, 20),
+                task(3, Some(TaskOutcome::FromCache), true, 10),
+                task(4, Some(TaskOutcome::Success), false, 500),
+                task(5, Some(TaskOutcome::UpToDate), false, 300),
+                task(6, None, false, 1),
+            ],
+            ..Default::default()
+        };
+        let summary = summarize(&payload, 3);
+
+        assert_eq!(summary.task_count, 6);
+        assert_eq!(
+            summary.outcomes,
+            vec![
+                (TaskOutcome::UpToDate, 1),
+                (TaskOutcome::Success, 2),
+                (TaskOutcome::FromCache, 2),
+            ]
+        );
+        assert_eq!(summary.without_outcome, 1);
+        let slowest: Vec<i64> = summary.slowest.iter().map(|t| t.id).collect();
+        assert_eq!(slowest, vec![4, 1, 5]);
+        assert_eq!(
+            summary.cache,
+            CacheStats {
+                hits: 2,
+                misses: 1,
+                not_cacheable: 1,
+                up_to_date: 1,
+            }
+        );
+        assert_eq!(summary.cache.hit_rate(), Some(2.0 / 3.0));
+    }
+
+    #[test]
+    fn test_hit_rate_without_cacheable_work() {
+        assert_eq!(CacheStats::default().hit_rate(), None);
+    }
+}
@@ -0,0 +1,357 @@
+//! Task duration trends across many build scans.
+//!
+//! Scans are ordered by build start time. For each task, identified by its build path
+//! and task path so same-named tasks of included builds stay apart, the durations of its
+//! executed runs (`Success` outcome; cache hits, up-to-date and failed runs would skew
+//! the numbers) are summarized with nearest-rank percentiles, a least-squares slope per
+//! scan, Tukey outliers, and a regression check comparing the most recent runs against
+//! the ones before them.
+
+use models::{BuildScanPayload, TaskOutcome};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrendOptions {
+    /// Relative slowdown of the recent median over the baseline median that counts as a
+    /// regression, e.g. `0.2` for 20 %.
+    pub regression_threshold: f64,
+    /// Number of latest runs compared against all earlier ones.
+    pub recent_runs: usize,
+    /// Tasks with fewer runs are left out.
+    pub min_runs: usize,
+}
+
+impl Default for TrendOptions {
+    fn default() -> Self {
+        Self {
+            regression_threshold: 0.2,
+            recent_runs: 3,
+            min_runs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Run {
+    /// Caller-supplied scan identifier, usually the file name.
+    pub scan: String,
+    pub started_at: Option<i64>,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Regression {
+    pub baseline_p50_ms: i64,
+    pub recent_p50_ms: i64,
+    /// `recent / baseline - 1`.
+    pub change: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskTrend {
+    pub build_path: String,
+    pub task_path: String,
+    pub runs: usize,
+    pub p50_ms: i64,
+    pub p90_ms: i64,
+    /// Least-squares change in duration per scan, in milliseconds.
+    pub slope_ms_per_scan: f64,
+    pub outliers: Vec<Run>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regression: Option<Regression>,
+}
+
+impl TaskTrend {
+    /// The task's path from the root build, e.g. `:build-logic:compileKotlin` for a task
+    /// of the included build `:build-logic`.
+    pub fn identity_path(&self) -> String {
+        if self.build_path == ":" {
+            self.task_path.clone()
+        } else {
+            format!("{}{}", self.build_path, self.task_path)
+        }
+    }
+}
+
+/// Analyzes `(scan id, payload)` pairs. Regressed tasks come first, largest change
+/// first; the rest follow by descending median.
+pub fn analyze(scans: &[(String, &BuildScanPayload)], options: &TrendOptions) -> Vec<TaskTrend> {
+    let mut by_task: std::collections::BTreeMap<(&str, &str), Vec<Run>> = Default::default();
+    for i in BuildScanPayload::chronological(scans, |(_, payload)| payload) {
+        let (scan, payload) = &scans[i];
+        for task in &payload.tasks {
+            if task.outcome != Some(TaskOutcome::Success) {
+                continue;
+            }
+            let Some(duration_ms) = task.duration_ms else {
+                continue;
+            };
+            by_task
+                .entry((&task.build_path, &task.task_path))
+                .or_default()
+                .push(Run {
+                    scan: scan.clone(),
+                    started_at: payload.started_at(),
+                    duration_ms,
+                });
+        }
+    }
+
+    let mut trends: Vec<TaskTrend> = by_task
+        .into_iter()
+        .filter(|(_, runs)| runs.len() >= options.min_runs.max(1))
+        .map(|((build_path, task_path), runs)| task_trend(build_path, task_path, runs, options))
+        .collect();
+
+    trends.sort_by(|a, b| {
+        let change = |t: &TaskTrend| t.regression.as_ref().map(|r| r.change);
+        change(b)
+            .is_some()
+            .cmp(&change(a).is_some())
+            .then(
+                change(b)
+                    .partial_cmp(&change(a))
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+            .then(b.p50_ms.cmp(&a.p50_ms))
+            .then(a.build_path.cmp(&b.build_path))
+            .then(a.task_path.cmp(&b.task_path))
+    });
+    trends
+}
+
+fn task_trend(
+    build_path: &str,
+    task_path: &str,
+    runs: Vec<Run>,
+    options: &TrendOptions,
+) -> TaskTrend {
+    let durations: Vec<i64> = runs.iter().map(|r| r.duration_ms).collect();
+    let mut sorted = durations.clone();
+    sorted.sort_unstable();
+
+    let outliers = if sorted.len() >= 4 {
+        let q1 = percentile(&sorted, 25.0) as f64;
+        let q3 = percentile(&sorted, 75.0) as f64;
+        let fence = 1.5 * (q3 - q1);
+        runs.iter()
+            .filter(|r| (r.duration_ms as f64) < q1 - fence || (r.duration_ms as f64) > q3 + fence)
+            .cloned()
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let regression = (durations.len() > options.recent_runs && options.recent_runs > 0)
+        .then(|| {
+            let (baseline, recent) = durations.split_at(durations.len() - options.recent_runs);
+            let median = |values: &[i64]| {
+                let mut values = values.to_vec();
+                values.sort_unstable();
+                percentile(&values, 50.0)
+            };
+            let (baseline_p50_ms, recent_p50_ms) = (median(baseline), median(recent));
+            let change = recent_p50_ms as f64 / baseline_p50_ms.max(1) as f64 - 1.0;
+            (change > options.regression_threshold).then_some(Regression {
+                baseline_p50_ms,
+                recent_p50_ms,
+                change,
+            })
+        })
+        .flatten();
+
+    TaskTrend {
+        build_path: build_path.to_string(),
+        task_path: task_path.to_string(),
+        runs: runs.len(),
+        p50_ms: percentile(&sorted, 50.0),
+        p90_ms: percentile(&sorted, 90.0),
+        slope_ms_per_scan: slope(&durations),
+        outliers,
+        regression,
+    }
+}
+
+/// Nearest-rank percentile of ascending `sorted`, which must not be empty.
+pub fn percentile(sorted: &[i64], p: f64) -> i64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Least-squares slope of `values` against their index.
+fn slope(values: &[i64]) -> f64 {
+    let n = values.len() as f64;
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().sum::<i64>() as f64 / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (i, &y) in values.iter().enumerate() {
+        let dx = i as f64 - mean_x;
+        covariance += dx * (y as f64 - mean_y);
+        variance += dx * dx;
+    }
+    covariance / variance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::{BuildData, Task};
+
+    fn scan(started_at: i64, tasks: &[(&str, TaskOutcome, i64)]) -> BuildScanPayload {
+        scan_of_build(":", started_at, tasks)
+    }
+
+    fn scan_of_build(
+        build_path: &str,
+        started_at: i64,
+        tasks: &[(&str, TaskOutcome, i64)],
+    ) -> BuildScanPayload {
+        BuildScanPayload {
+            build: Some(BuildData {
+                started_at: Some(started_at),
+                ..Default::default()
+            }),
+            tasks: tasks
+                .iter()
+                .enumerate()
+                .map(|(i, (path, outcome, duration))| Task {
+                    id: i as i64,
+                    build_path: build_path.into(),
+                    task_path: (*path).into(),
+                    class_name: None,
+                    outcome: Some(*outcome),
+                    cacheable: None,
+                    caching_disabled_reason: None,
+                    caching_disabled_explanation: None,
+                    origin_build_cache_key: None,
+                    actionable: None,
+                    started_at: Some(started_at),
+                    finished_at: Some(started_at + duration),
+                    duration_ms: Some(*duration),
+                    inputs: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let values = [10, 20, 30, 40, 50, 60, 70, 80, 90, 100];
+        assert_eq!(percentile(&values, 50.0), 50);
+        assert_eq!(percentile(&values, 90.0), 90);
+        assert_eq!(percentile(&[7], 90.0), 7);
+    }
+
+    #[test]
+    fn test_slope() {
+        assert_eq!(slope(&[100, 110, 120, 130]), 10.0);
+        assert_eq!(slope(&[5]), 0.0);
+    }
+
+    #[test]
+    fn test_flags_regression_and_orders_by_time() {
+        // Given out of order; the slow runs are the three most recent by start time.
+        let durations = [
+            (6, 300),
+            (1, 100),
+            (2, 110),
+            (7, 320),
+            (3, 90),
+            (4, 105),
+            (5, 310),
+        ];
+        let payloads: Vec<BuildScanPayload> = durations
+            .iter()
+            .map(|&(t, d)| {
+                scan(
+                    t * 1000,
+                    &[
+                        (":app:test", TaskOutcome::Success, d),
+                        (":app:jar", TaskOutcome::Success, 50),
+                        (":app:compileJava", TaskOutcome::FromCache, 5),
+                    ],
+                )
+            })
+            .collect();
+        let scans: Vec<(String, &BuildScanPayload)> = payloads
+            .iter()
+            .zip(&durations)
+            .map(|(p, (t, _))| (format!("scan{t}"), p))
+            .collect();
+
+        let trends = analyze(&scans, &TrendOptions::default());
+
+        assert_eq!(trends.len(), 2, "cache hits are not runs");
+        let test = &trends[0];
+        assert_eq!(test.task_path, ":app:test");
+        assert_eq!(test.runs, 7);
+        let regression = test.regression.as_ref().unwrap();
+        assert_eq!(regression.baseline_p50_ms, 100);
+        assert_eq!(regression.recent_p50_ms, 310);
+        assert!(test.slope_ms_per_scan > 0.0);
+
+        let jar = &trends[1];
+        assert!(jar.regression.is_none());
+        assert_eq!(jar.p50_ms, 50);
+        assert!(jar.outliers.is_empty());
+    }
+
+    #[test]
+    fn test_outliers() {
+        let payloads: Vec<BuildScanPayload> = [100, 102, 98, 101, 99, 1000]
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| scan(i as i64, &[(":a", TaskOutcome::Success, d)]))
+            .collect();
+        let scans: Vec<(String, &BuildScanPayload)> = payloads
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (format!("s{i}"), p))
+            .collect();
+        let options = TrendOptions {
+            recent_runs: 0,
+            ..Default::default()
+        };
+
+        let trends = analyze(&scans, &options);
+        assert_eq!(trends[0].outliers.len(), 1);
+        assert_eq!(trends[0].outliers[0].scan, "s5");
+        assert!(trends[0].regression.is_none());
+    }
+
+    #[test]
+    fn test_keeps_included_build_tasks_apart() {
+        let payloads: Vec<BuildScanPayload> = (0..5)
+            .map(|i| {
+                let mut payload = scan(i, &[(":compileKotlin", TaskOutcome::Success, 1000)]);
+                payload.tasks.extend(
+                    scan_of_build(
+                        ":build-logic",
+                        i,
+                        &[(":compileKotlin", TaskOutcome::Success, 200)],
+                    )
+                    .tasks,
+                );
+                payload
+            })
+            .collect();
+        let scans: Vec<(String, &BuildScanPayload)> = payloads
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (format!("s{i}"), p))
+            .collect();
+
+        let trends = analyze(&scans, &TrendOptions::default());
+
+        assert_eq!(trends.len(), 2);
+        assert_eq!(trends[0].identity_path(), ":compileKotlin");
+        assert_eq!(trends[0].p50_ms, 1000);
+        assert_eq!(trends[1].identity_path(), ":build-logic:compileKotlin");
+        assert_eq!(trends[1].p50_ms, 200);
+        assert_eq!(trends[1].runs, 5);
+    }
+}
@@ -1,7 +1,8 @@
 use error::ParseError;
 
+/// Read a Kryo varlong: LEB128 for the first eight bytes, after which a ninth byte, if
+/// any, carries all eight of the remaining bits. No valid encoding is longer than that.
 pub fn read_unsigned_varint(data: &[u8], pos: &mut usize) -> Result<u64, ParseError> {
-    let start = *pos;
     let mut result: u64 = 0;
     let mut shift: u32 = 0;
     loop {
@@ -10,14 +11,14 @@ pub fn read_unsigned_varint(data: &[u8], pos: &mut usize) -> Result<u64, ParseEr
         }
         let byte = data[*pos];
         *pos += 1;
+        if shift == 56 {
+            return Ok(result | (byte as u64) << 56);
+        }
         result |= ((byte & 0x7F) as u64) << shift;
         if byte & 0x80 == 0 {
             return Ok(result);
         }
         shift += 7;
-        if shift >= 64 {
-            return Err(ParseError::MalformedLeb128 { offset: start });
-        }
     }
 }
 
@@ -66,6 +67,15 @@ mod tests {
         assert!(read_unsigned_varint(&data, &mut pos).is_err());
     }
 
+    #[test]
+    fn test_read_unsigned_varint_nine_bytes() {
+        // Kryo writes -1 as eight continuation bytes and a full ninth byte
+        let data = [0xFF; 10];
+        let mut pos = 0;
+        assert_eq!(read_unsigned_varint(&data, &mut pos).unwrap(), u64::MAX);
+        assert_eq!(pos, 9);
+    }
+
     #[test]
     fn test_zigzag_decode_i32() {
         assert_eq!(zigzag_decode_i32(0), 0);
@@ -96,7 +106,7 @@ mod tests {
 
         fn encode_unsigned_varint(mut value: u64) -> Vec<u8> {
             let mut buf = Vec::new();
-            loop {
+            for _ in 0..8 {
                 let mut byte = (value & 0x7F) as u8;
                 value >>= 7;
                 if value != 0 {
@@ -104,9 +114,10 @@ mod tests {
                 }
                 buf.push(byte);
                 if value == 0 {
-                    break;
+                    return buf;
                 }
             }
+            buf.push(value as u8);
             buf
         }
 
@@ -0,0 +1,101 @@
+//! Catalog of known event wire ids.
+//!
+//! A wire id packs the event type's ordinal in the low byte and the index of its schema
+//! version in the high byte, so the same event name can appear under several ids. The
+//! catalog names the ids we have decoders for. The undecoded ids seen in captured
+//! payloads (66, 149, 307 and friends) are not named yet, so raw event summaries carry
+//! only their wire id until an entry is added here.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WireEventType {
+    pub wire_id: u16,
+    pub name: &'static str,
+    pub version: &'static str,
+}
+
+const fn entry(wire_id: u16, name: &'static str, version: &'static str) -> WireEventType {
+    WireEventType {
+        wire_id,
+        name,
+        version,
+    }
+}
+
+/// Sorted by `wire_id`.
+pub static CATALOG: &[WireEventType] = &[
+    entry(2, "BuildAgent", "1_0"),
+    entry(5, "BuildRequestedTasks", "1_0"),
+    entry(6, "BuildStarted", "1_0"),
+    entry(12, "Hardware", "1_0"),
+    entry(13, "JvmArgs", "1_0"),
+    entry(14, "Jvm", "1_0"),
+    entry(15, "Locality", "1_0"),
+    entry(16, "Os", "1_0"),
+    entry(39, "ScopeIds", "1_0"),
+    entry(49, "FileRefRoots", "1_0"),
+    entry(56, "Encoding", "1_0"),
+    entry(88, "TaskInputsFilePropertyRoot", "1_0"),
+    entry(91, "TaskInputsImplementation", "1_0"),
+    entry(92, "TaskInputsPropertyNames", "1_0"),
+    entry(94, "TaskInputsSnapshottingStarted", "1_0"),
+    entry(95, "TaskInputsValueProperties", "1_0"),
+    entry(115, "JavaToolchainUsage", "1_0"),
+    entry(117, "TaskIdentity", "1_0"),
+    entry(119, "PlannedNode", "1_0"),
+    entry(122, "TaskRegistrationSummary", "1_0"),
+    entry(136, "TransformIdentification", "1_0"),
+    entry(137, "TransformExecutionRequest", "1_0"),
+    entry(138, "TransformExecutionStarted", "1_0"),
+    entry(257, "BasicMemoryStats", "1_1"),
+    entry(259, "BuildFinished", "1_1"),
+    entry(265, "DaemonState", "1_1"),
+    entry(274, "OutputStyledText", "1_1"),
+    entry(345, "TaskInputsFileProperty", "1_1"),
+    entry(349, "TaskInputsSnapshottingFinished", "2_0"),
+    entry(395, "TransformExecutionFinished", "1_1"),
+    entry(407, "ResourceUsage", "2_0"),
+    entry(516, "BuildModes", "1_2"),
+    entry(1563, "TaskStarted", "1_6"),
+    entry(2074, "TaskFinished", "1_8"),
+];
+
+pub fn lookup(wire_id: u16) -> Option<&'static WireEventType> {
+    CATALOG
+        .binary_search_by_key(&wire_id, |e| e.wire_id)
+        .ok()
+        .map(|i| &CATALOG[i])
+}
+
+/// `Name_major_minor` for catalogued ids, `wire <id>` otherwise.
+pub fn describe(wire_id: u16) -> String {
+    match lookup(wire_id) {
+        Some(e) => format!("{}_{}", e.name, e.version),
+        None => format!("wire {wire_id}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_sorted_and_unique() {
+        assert!(CATALOG.windows(2).all(|w| w[0].wire_id < w[1].wire_id));
+    }
+
+    #[test]
+    fn test_lookup_known() {
+        let e = lookup(265).unwrap();
+        assert_eq!(e.name, "DaemonState");
+        assert_eq!(e.version, "1_1");
+        assert_eq!(describe(2074), "TaskFinished_1_8");
+    }
+
+    #[test]
+    fn test_lookup_unknown() {
+        assert!(lookup(999).is_none());
+        assert_eq!(describe(999), "wire 999");
+    }
+}
@@ -0,0 +1,134 @@
+//! The config file's schema. Every table rejects keys it doesn't know, which are
+//! misspellings or unsupported options; `Config::resolve` merges it with the flags.
+
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroU64};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{CaptureFormat, Compression, Layout, MatchStrategy};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct File {
+    pub listen: Option<SocketAddr>,
+    pub payload_dir: Option<PathBuf>,
+    pub upstream_url: Option<String>,
+    pub routes: Vec<Route>,
+    pub mirrors: Vec<Mirror>,
+    pub mirror_retries: Option<u32>,
+    pub timeouts: Timeouts,
+    pub limits: Limits,
+    pub capture: Capture,
+    pub redaction: Redaction,
+    pub storage: Storage,
+    pub queue: Queue,
+    pub replay: Replay,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    pub prefix: String,
+    pub upstream_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mirror {
+    pub url: String,
+    pub retries: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    pub connect_secs: Option<NonZeroU64>,
+    pub request_secs: Option<NonZeroU64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    pub max_request_body: Option<NonZeroU64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Capture {
+    pub include: Vec<CaptureRule>,
+    pub exclude: Vec<CaptureRule>,
+    pub sample_percent: Option<u8>,
+}
+
+/// Each criterion takes a string or an array of strings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureRule {
+    #[serde(deserialize_with = "one_or_many")]
+    pub method: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub path: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub status: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub content_type: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Redaction {
+    /// `false` drops the built-in Develocity credentials.
+    pub defaults: Option<bool>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub headers: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub query_params: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub body_fields: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub body_paths: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Storage {
+    pub format: Option<CaptureFormat>,
+    pub layout: Option<Layout>,
+    pub compression: Option<Compression>,
+    pub max_total_size: Option<NonZeroU64>,
+    pub max_age_days: Option<NonZeroU64>,
+    /// `false` stops keeping `index.tsv`.
+    pub index: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Queue {
+    pub dir: Option<PathBuf>,
+    pub max_attempts: Option<NonZeroU32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Replay {
+    pub dir: Option<PathBuf>,
+    #[serde(rename = "match")]
+    pub strategy: Option<MatchStrategy>,
+    pub strict: Option<bool>,
+}
+
+/// A string or an array of strings, as a list.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged, expecting = "a string or an array of strings")]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
@@ -0,0 +1,335 @@
+//! Which proxied exchanges are saved to the payload directory.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::str::FromStr;
+
+use globset::{GlobBuilder, GlobMatcher};
+
+/// Include/exclude rules plus sampling. An exchange is saved when it matches an include
+/// rule (or there are none), matches no exclude rule, and falls within the sample.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureFilter {
+    pub include: Vec<CaptureRule>,
+    pub exclude: Vec<CaptureRule>,
+    /// Percentage of the exchanges passing the rules that are saved, 1 to 100.
+    pub sample_percent: u8,
+}
+
+impl Default for CaptureFilter {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            sample_percent: 100,
+        }
+    }
+}
+
+/// Matches an exchange when every criterion that is set matches; a criterion listing
+/// several values matches any of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CaptureRule {
+    /// HTTP methods, compared case-insensitively.
+    pub methods: Vec<String>,
+    pub paths: Globs,
+    pub statuses: Vec<StatusPattern>,
+    /// Media types such as `application/json` or `application/*`, matched against the
+    /// request and the response `Content-Type`.
+    pub content_types: Globs,
+}
+
+/// Globs compiled when the config is loaded: `*` and `?` stay within a `/`-separated
+/// segment, `**` crosses segments. Equal when their patterns are.
+#[derive(Debug, Clone, Default)]
+pub struct Globs(Vec<GlobMatcher>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusPattern {
+    /// A single status code, e.g. `404`.
+    Exact(u16),
+    /// A status class, e.g. `2xx`.
+    Class(u16),
+}
+
+/// The parts of a finished exchange the rules look at.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Exchange<'a> {
+    pub method: &'a str,
+    /// Request path without the query string.
+    pub path: &'a str,
+    /// `None` when the upstream couldn't be reached.
+    pub status: Option<u16>,
+    pub request_content_type: Option<&'a str>,
+    pub response_content_type: Option<&'a str>,
+}
+
+impl CaptureFilter {
+    /// Whether `exchange` passes the include and exclude rules.
+    pub fn matches(&self, exchange: &Exchange) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|rule| rule.matches(exchange)))
+            && !self.exclude.iter().any(|rule| rule.matches(exchange))
+    }
+
+    /// Whether the exchange identified by `key` (its request id) is in the sample. The
+    /// same key always gives the same answer.
+    pub fn sampled(&self, key: &str) -> bool {
+        if self.sample_percent >= 100 {
+            return true;
+        }
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish() % 100 < u64::from(self.sample_percent)
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl CaptureRule {
+    pub fn matches(&self, exchange: &Exchange) -> bool {
+        let any = |values: &[String], matches: &dyn Fn(&str) -> bool| {
+            values.is_empty() || values.iter().any(|v| matches(v))
+        };
+        any(&self.methods, &|m| m.eq_ignore_ascii_case(exchange.method))
+            && (self.paths.is_empty() || self.paths.is_match(exchange.path))
+            && (self.statuses.is_empty()
+                || exchange
+                    .status
+                    .is_some_and(|status| self.statuses.iter().any(|p| p.matches(status))))
+            && (self.content_types.is_empty()
+                || [
+                    exchange.request_content_type,
+                    exchange.response_content_type,
+                ]
+                .into_iter()
+                .flatten()
+                .any(|content_type| self.content_types.is_match(&media_type(content_type))))
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if *self == Self::default() {
+            return Err("a capture rule needs at least one criterion".into());
+        }
+        if let Some(path) = self.paths.patterns().find(|p| !p.starts_with('/')) {
+            return Err(format!("path {path:?} must start with `/`"));
+        }
+        Ok(())
+    }
+}
+
+/// Parses the flag form of a rule: space-separated `key=value` criteria with
+/// comma-separated alternatives, e.g. `method=POST path=/scans/** status=2xx,3xx`. Keys
+/// are `method`, `path`, `status` and `content_type`.
+impl FromStr for CaptureRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rule = Self::default();
+        let (mut paths, mut content_types) = (Vec::new(), Vec::new());
+        for criterion in s.split_whitespace() {
+            let (key, values) = criterion
+                .split_once('=')
+                .ok_or_else(|| format!("{criterion:?} is not of the form key=value"))?;
+            let values = values
+                .split(',')
+                .filter(|v| !v.is_empty())
+                .map(String::from);
+            match key {
+                "method" => rule.methods.extend(values),
+                "path" => paths.extend(values),
+                "status" => {
+                    for value in values {
+                        rule.statuses.push(value.parse()?);
+                    }
+                }
+                "content_type" => content_types.extend(values),
+                other => {
+                    return Err(format!(
+                        "unknown criterion {other:?}, expected method, path, status or content_type"
+                    ));
+                }
+            }
+        }
+        rule.paths = Globs::new(paths)?;
+        rule.content_types = Globs::new(content_types)?;
+        rule.validate()?;
+        Ok(rule)
+    }
+}
+
+impl Globs {
+    pub fn new<S: AsRef<str>>(patterns: impl IntoIterator<Item = S>) -> Result<Self, String> {
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                GlobBuilder::new(pattern.as_ref())
+                    .literal_separator(true)
+                    .build()
+                    .map(|glob| glob.compile_matcher())
+                    .map_err(|e| e.to_string())
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.iter().any(|matcher| matcher.is_match(text))
+    }
+
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|matcher| matcher.glob().glob())
+    }
+
+    /// Adds the globs of `other` this doesn't have yet.
+    pub(crate) fn extend(&mut self, other: Globs) {
+        for matcher in other.0 {
+            if !self
+                .patterns()
+                .any(|pattern| pattern == matcher.glob().glob())
+            {
+                self.0.push(matcher);
+            }
+        }
+    }
+}
+
+impl PartialEq for Globs {
+    fn eq(&self, other: &Self) -> bool {
+        self.patterns().eq(other.patterns())
+    }
+}
+
+impl Eq for Globs {}
+
+impl StatusPattern {
+    pub fn matches(self, status: u16) -> bool {
+        match self {
+            Self::Exact(code) => status == code,
+            Self::Class(class) => status / 100 == class,
+        }
+    }
+}
+
+impl FromStr for StatusPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("status {s:?} must be a code like 404 or a class like 2xx");
+        let pattern = match s.to_ascii_lowercase().strip_suffix("xx") {
+            Some(class) => Self::Class(class.parse().map_err(|_| invalid())?),
+            None => Self::Exact(s.parse().map_err(|_| invalid())?),
+        };
+        match pattern {
+            Self::Exact(100..=599) | Self::Class(1..=5) => Ok(pattern),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// `text/plain; charset=utf-8` -> `text/plain`.
+fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange<'a>(method: &'a str, path: &'a str, status: Option<u16>) -> Exchange<'a> {
+        Exchange {
+            method,
+            path,
+            status,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_globs() {
+        let globs = |patterns: &[&str]| Globs::new(patterns).unwrap();
+        assert!(
+            globs(&["/scans/publish/gradle/*/upload"])
+                .is_match("/scans/publish/gradle/4.3.2/upload")
+        );
+        assert!(!globs(&["/scans/*"]).is_match("/scans/publish/token"));
+        assert!(globs(&["/scans/**"]).is_match("/scans/publish/token"));
+        assert!(globs(&["/**/token"]).is_match("/scans/publish/token"));
+        assert!(globs(&["/usage/users/che?k"]).is_match("/usage/users/check"));
+        assert!(!globs(&["/usage/?"]).is_match("/usage/"));
+        assert!(globs(&["text/*", "application/*"]).is_match("application/json"));
+        assert!(!Globs::default().is_match("/"));
+        assert_eq!(globs(&["/a/*"]), globs(&["/a/*"]));
+        assert!(
+            Globs::new(["/scans/[a"])
+                .unwrap_err()
+                .contains("unclosed character class")
+        );
+    }
+
+    #[test]
+    fn test_rules_and_sampling() {
+        let filter = CaptureFilter {
+            include: vec!["path=/scans/**".parse().unwrap()],
+            exclude: vec!["method=get".parse().unwrap(), "status=4xx".parse().unwrap()],
+            sample_percent: 100,
+        };
+        let upload = exchange("POST", "/scans/publish/gradle/4.3.2/upload", Some(200));
+        assert!(filter.matches(&upload));
+        assert!(!filter.matches(&exchange(
+            "GET",
+            "/scans/publish/gradle/4.3.2/upload",
+            Some(200)
+        )));
+        assert!(!filter.matches(&exchange(
+            "POST",
+            "/scans/publish/gradle/4.3.2/upload",
+            Some(401)
+        )));
+        assert!(!filter.matches(&exchange("POST", "/usage/users/check", Some(200))));
+
+        let json: CaptureRule = "content_type=application/json status=200".parse().unwrap();
+        let mut response = exchange("POST", "/x", Some(200));
+        response.response_content_type = Some("Application/JSON; charset=utf-8");
+        assert!(json.matches(&response));
+        assert!(!json.matches(&exchange("POST", "/x", Some(200))));
+        response.status = None;
+        assert!(!json.matches(&response));
+
+        let sampled = CaptureFilter {
+            sample_percent: 25,
+            ..Default::default()
+        };
+        let kept = (0..1000)
+            .filter(|i| sampled.sampled(&format!("request-{i}")))
+            .count();
+        assert!((150..350).contains(&kept), "kept {kept} of 1000");
+        assert_eq!(sampled.sampled("request-1"), sampled.sampled("request-1"));
+        assert!(CaptureFilter::default().sampled("anything"));
+    }
+
+    #[test]
+    fn test_rule_parse_errors() {
+        let error = |s: &str| s.parse::<CaptureRule>().unwrap_err();
+        assert!(error("").contains("at least one criterion"));
+        assert!(error("method").contains("key=value"));
+        assert!(error("host=example.com").contains("unknown criterion"));
+        assert!(error("status=6xx").contains("must be a code"));
+        assert!(error("status=20").contains("must be a code"));
+        assert!(error("path=scans/**").contains("must start with `/`"));
+        assert!(error("path=/scans/{a").contains("unclosed alternate group"));
+        assert_eq!(
+            "status=2XX,404".parse::<CaptureRule>().unwrap().statuses,
+            vec![StatusPattern::Class(2), StatusPattern::Exact(404)]
+        );
+    }
+}
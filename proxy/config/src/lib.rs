@@ -1,29 +1,1056 @@
-use std::path::PathBuf;
+//! Proxy configuration, merged from command-line flags, their environment variable
+//! fallbacks, an optional TOML file, and defaults, in that order of precedence.
+
+mod file;
+mod filter;
+mod redaction;
+
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroU64};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::Parser;
+use serde::Deserialize;
+
+use crate::file::File;
+
+pub use filter::{CaptureFilter, CaptureRule, Exchange, Globs, StatusPattern};
+pub use redaction::{REDACTED, Redaction};
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub port: u16,
+    pub listen: SocketAddr,
     pub payload_dir: PathBuf,
+    /// Where requests go that no route matches. Optional when replaying or when routes
+    /// cover everything.
+    pub upstream_url: Option<String>,
+    pub routes: Vec<Route>,
+    /// Servers that also receive every build scan upload, after the upstream.
+    pub mirrors: Vec<Mirror>,
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    /// Requests with larger bodies are rejected with 413: up front when their
+    /// `Content-Length` says so, else once the body streams past the limit.
+    pub max_request_body: Option<u64>,
+    /// Which exchanges are saved to `payload_dir`.
+    pub capture: CaptureFilter,
+    /// Secrets removed from captures before they are written.
+    pub redaction: Redaction,
+    pub storage: Storage,
+    pub queue: Option<QueueConfig>,
+    pub replay: Option<ReplayConfig>,
+}
+
+/// Requests whose path starts with `prefix` are forwarded to `upstream_url`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub prefix: String,
     pub upstream_url: String,
 }
 
+/// A server build scan uploads are copied to, at the same path as upstream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mirror {
+    pub url: String,
+    /// Further attempts after a failed delivery.
+    pub retries: u32,
+}
+
+/// How captures are laid out in the payload directory and how long they are kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Storage {
+    pub format: CaptureFormat,
+    pub layout: Layout,
+    pub compression: Compression,
+    /// Oldest captures are deleted once the payload directory grows past this many bytes.
+    pub max_total_size: Option<u64>,
+    /// Captures older than this are deleted.
+    pub max_age: Option<Duration>,
+    /// Don't keep `index.tsv`, the list of captures with method, URI, status and size.
+    pub no_index: bool,
+}
+
+impl Storage {
+    pub fn retains(&self) -> bool {
+        self.max_total_size.is_some() || self.max_age.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureFormat {
+    /// `format::Payload` JSON, which replay and the CLI read.
+    #[default]
+    Payload,
+    /// HTTP Archive 1.2, one entry per file, for browser tooling.
+    Har,
+}
+
+impl FromStr for CaptureFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "payload" => Ok(Self::Payload),
+            "har" => Ok(Self::Har),
+            other => Err(format!(
+                "unknown capture format {other:?}, expected payload or har"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Every capture directly in the payload directory.
+    #[default]
+    Flat,
+    /// A `YYYY-MM-DD` subdirectory per day.
+    Daily,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flat" => Ok(Self::Flat),
+            "daily" => Ok(Self::Daily),
+            other => Err(format!("unknown layout {other:?}, expected flat or daily")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    /// Capture files are written as `.json.gz`.
+    Gzip,
+    /// Capture files are written as `.json.zst`.
+    Zstd,
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            other => Err(format!(
+                "unknown compression {other:?}, expected none, gzip or zstd"
+            )),
+        }
+    }
+}
+
+/// Store-and-forward for build scan uploads: each is persisted before it is forwarded,
+/// and when the upstream can't take it the client is told it succeeded while delivery is
+/// retried in the background. Token requests the upstream can't answer get a stand-in
+/// token, traded for a real one when the upload is delivered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueConfig {
+    pub dir: PathBuf,
+    /// Delivery attempts before an upload is given up on; `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+/// Serve recorded responses from captured payloads instead of (or before) forwarding.
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    pub dir: PathBuf,
+    pub strategy: MatchStrategy,
+    /// Fail unmatched requests instead of forwarding them upstream.
+    pub strict: bool,
+}
+
+/// Which parts of a request must equal the recorded one for its response to be replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchStrategy {
+    /// Method and path, ignoring the query string.
+    Path,
+    /// Method, path and query string.
+    #[default]
+    #[serde(rename = "path-query")]
+    PathAndQuery,
+    /// Method, path, query string and a hash of the body.
+    Body,
+}
+
+impl FromStr for MatchStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(Self::Path),
+            "path-query" => Ok(Self::PathAndQuery),
+            "body" => Ok(Self::Body),
+            other => Err(format!(
+                "unknown match strategy {other:?}, expected path, path-query or body"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid config file {path:?}: {source}")]
+    Syntax {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Invalid config: {0}")]
+    Invalid(String),
+}
+
+/// Recording proxy for Gradle build scan traffic.
+#[derive(Debug, Default, Parser)]
+#[command(version)]
+pub struct Args {
+    /// TOML config file; flags and environment variables override its settings
+    #[arg(long, env = "PROXY_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on [default: 0.0.0.0:8080]
+    #[arg(long, env = "LISTEN")]
+    pub listen: Option<SocketAddr>,
+
+    /// Port to listen on, replacing the port of the listen address
+    #[arg(long, env = "PORT")]
+    pub port: Option<u16>,
+
+    /// Directory captures are written to [default: /tmp/gradle-payloads]
+    #[arg(long, env = "PAYLOAD_DIR")]
+    pub payload_dir: Option<PathBuf>,
+
+    /// Upstream for requests no route matches
+    #[arg(long, env = "UPSTREAM_URL")]
+    pub upstream_url: Option<String>,
+
+    /// Route requests by path prefix, as PREFIX=URL; repeatable
+    #[arg(long = "route", env = "UPSTREAM_ROUTES", value_delimiter = ',')]
+    pub routes: Vec<String>,
+
+    /// Also send build scan uploads to this server; repeatable
+    #[arg(long = "mirror", env = "MIRROR_URLS", value_delimiter = ',')]
+    pub mirrors: Vec<String>,
+
+    /// Times to retry a failed mirror delivery, for mirrors that don't set their own
+    /// [default: 3]
+    #[arg(long, env = "MIRROR_RETRIES")]
+    pub mirror_retries: Option<u32>,
+
+    /// Seconds to wait for an upstream connection [default: 30]
+    #[arg(long, env = "CONNECT_TIMEOUT")]
+    pub connect_timeout: Option<u64>,
+
+    /// Seconds an upstream exchange may take in total [default: 120]
+    #[arg(long, env = "REQUEST_TIMEOUT")]
+    pub request_timeout: Option<u64>,
+
+    /// Reject requests with a larger body, in bytes
+    #[arg(long, env = "MAX_REQUEST_BODY")]
+    pub max_request_body: Option<u64>,
+
+    /// Only save exchanges matching this rule, e.g. "method=POST path=/scans/**"; repeatable
+    #[arg(long, env = "CAPTURE_INCLUDE", value_delimiter = ';')]
+    pub capture_include: Vec<CaptureRule>,
+
+    /// Don't save exchanges matching this rule, e.g. "status=4xx,5xx"; repeatable
+    #[arg(long, env = "CAPTURE_EXCLUDE", value_delimiter = ';')]
+    pub capture_exclude: Vec<CaptureRule>,
+
+    /// Percentage of the exchanges passing the rules to save [default: 100]
+    #[arg(long, env = "CAPTURE_SAMPLE", value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub capture_sample: Option<u8>,
+
+    /// Also redact this header in captures; repeatable
+    #[arg(long = "redact-header", env = "REDACT_HEADERS", value_delimiter = ',')]
+    pub redact_headers: Vec<String>,
+
+    /// Also redact this query parameter in captures; repeatable
+    #[arg(
+        long = "redact-query-param",
+        env = "REDACT_QUERY_PARAMS",
+        value_delimiter = ','
+    )]
+    pub redact_query_params: Vec<String>,
+
+    /// Also redact this JSON body field in captures; repeatable
+    #[arg(
+        long = "redact-body-field",
+        env = "REDACT_BODY_FIELDS",
+        value_delimiter = ','
+    )]
+    pub redact_body_fields: Vec<String>,
+
+    /// Drop captured bodies of requests to paths matching this glob; repeatable
+    #[arg(long = "redact-body", env = "REDACT_BODY_PATHS", value_delimiter = ',')]
+    pub redact_body_paths: Vec<String>,
+
+    /// Only redact what is configured, without the built-in Develocity credentials
+    #[arg(long, env = "NO_REDACTION_DEFAULTS")]
+    pub no_redaction_defaults: bool,
+
+    /// Capture file format: payload, or har (which can't be replayed) [default: payload]
+    #[arg(long, env = "CAPTURE_FORMAT")]
+    pub capture_format: Option<CaptureFormat>,
+
+    /// Capture file layout: flat or daily [default: flat]
+    #[arg(long, env = "CAPTURE_LAYOUT")]
+    pub layout: Option<Layout>,
+
+    /// Capture file compression: none, gzip or zstd [default: none]
+    #[arg(long, env = "CAPTURE_COMPRESSION")]
+    pub compression: Option<Compression>,
+
+    /// Delete the oldest captures once the payload directory exceeds this many bytes
+    #[arg(long, env = "MAX_TOTAL_SIZE")]
+    pub max_total_size: Option<u64>,
+
+    /// Delete captures older than this many days
+    #[arg(long, env = "MAX_AGE_DAYS")]
+    pub max_age_days: Option<u64>,
+
+    /// Don't keep an index.tsv of captures in the payload directory
+    #[arg(long, env = "NO_CAPTURE_INDEX")]
+    pub no_index: bool,
+
+    /// Persist build scan uploads in this directory and accept them while the upstream is
+    /// down, delivering them once it's back
+    #[arg(long, env = "QUEUE_DIR")]
+    pub queue_dir: Option<PathBuf>,
+
+    /// Give up on a queued upload after this many delivery attempts [default: never]
+    #[arg(long, env = "QUEUE_MAX_ATTEMPTS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub queue_max_attempts: Option<u32>,
+
+    /// Replay recorded responses from this capture directory
+    #[arg(long, env = "REPLAY_DIR")]
+    pub replay_dir: Option<PathBuf>,
+
+    /// What must match for a replay: path, path-query or body [default: path-query]
+    #[arg(long, env = "REPLAY_MATCH")]
+    pub replay_match: Option<MatchStrategy>,
+
+    /// Fail requests without a recorded response instead of forwarding them
+    #[arg(long, env = "REPLAY_STRICT")]
+    pub replay_strict: bool,
+}
+
 impl Config {
-    pub fn from_env() -> Self {
-        let upstream_url =
-            std::env::var("UPSTREAM_URL").expect("UPSTREAM_URL environment variable is required");
-
-        let upstream_url = upstream_url.trim_end_matches('/').to_string();
-
-        Self {
-            port: std::env::var("PORT")
-                .ok()
-                .and_then(|p| p.parse().ok())
-                .unwrap_or(8080),
-            payload_dir: std::env::var("PAYLOAD_DIR")
-                .ok()
-                .map(PathBuf::from)
-                .unwrap_or_else(|| PathBuf::from("/tmp/gradle-payloads")),
+    /// Parses the command line (exiting on usage errors) and loads the config it points
+    /// to.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_args(Args::parse())
+    }
+
+    pub fn from_args(args: Args) -> Result<Self, ConfigError> {
+        let file = match &args.config {
+            Some(path) => read_file(path)?,
+            None => File::default(),
+        };
+        Self::resolve(args, file)
+    }
+
+    /// The upstream for `path`: the longest matching route prefix, else the default.
+    pub fn upstream_for(&self, path: &str) -> Option<&str> {
+        self.routes
+            .iter()
+            .filter(|route| path.starts_with(&route.prefix))
+            .max_by_key(|route| route.prefix.len())
+            .map(|route| route.upstream_url.as_str())
+            .or(self.upstream_url.as_deref())
+    }
+
+    fn resolve(args: Args, file: File) -> Result<Self, ConfigError> {
+        let file_routes = file
+            .routes
+            .into_iter()
+            .map(|route| Route::new(route.prefix, route.upstream_url))
+            .collect::<Result<Vec<_>, _>>()?;
+        let flag_routes = args
+            .routes
+            .iter()
+            .map(|route| {
+                let (prefix, url) = route.split_once('=').ok_or_else(|| {
+                    invalid(format!("route {route:?} is not of the form PREFIX=URL"))
+                })?;
+                Route::new(prefix.to_string(), url.to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let routes = or_file(flag_routes, file_routes);
+
+        let mirror_retries = args.mirror_retries.or(file.mirror_retries).unwrap_or(3);
+        let file_mirrors = file
+            .mirrors
+            .into_iter()
+            .map(|mirror| Mirror::new(mirror.url, mirror.retries.unwrap_or(mirror_retries)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let flag_mirrors = args
+            .mirrors
+            .into_iter()
+            .map(|url| Mirror::new(url, mirror_retries))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mirrors = or_file(flag_mirrors, file_mirrors);
+
+        let mut listen = args
+            .listen
+            .or(file.listen)
+            .unwrap_or(SocketAddr::from(([0, 0, 0, 0], 8080)));
+        if let Some(port) = args.port {
+            listen.set_port(port);
+        }
+
+        let payload_dir = args
+            .payload_dir
+            .or(file.payload_dir)
+            .unwrap_or_else(|| PathBuf::from("/tmp/gradle-payloads"));
+
+        let upstream_url = args
+            .upstream_url
+            .or(file.upstream_url)
+            .map(|url| check_url("upstream_url", url))
+            .transpose()?;
+
+        let connect_timeout = args
+            .connect_timeout
+            .or(file.timeouts.connect_secs.map(NonZeroU64::get))
+            .unwrap_or(30);
+        let request_timeout = args
+            .request_timeout
+            .or(file.timeouts.request_secs.map(NonZeroU64::get))
+            .unwrap_or(120);
+        if connect_timeout == 0 || request_timeout == 0 {
+            return Err(invalid("timeouts must be at least one second".into()));
+        }
+
+        let max_request_body = args
+            .max_request_body
+            .or(file.limits.max_request_body.map(NonZeroU64::get));
+
+        let file_include = capture_rules("include", file.capture.include)?;
+        let file_exclude = capture_rules("exclude", file.capture.exclude)?;
+        if file
+            .capture
+            .sample_percent
+            .is_some_and(|percent| !(1..=100).contains(&percent))
+        {
+            return Err(invalid(
+                "capture.sample_percent must be between 1 and 100".into(),
+            ));
+        }
+        let capture_filter = CaptureFilter {
+            include: or_file(args.capture_include, file_include),
+            exclude: or_file(args.capture_exclude, file_exclude),
+            sample_percent: args
+                .capture_sample
+                .or(file.capture.sample_percent)
+                .unwrap_or(100),
+        };
+
+        let mut redaction = if args.no_redaction_defaults || file.redaction.defaults == Some(false)
+        {
+            Redaction::none()
+        } else {
+            Redaction::default()
+        };
+        redaction.extend(Redaction {
+            headers: file.redaction.headers,
+            query_params: file.redaction.query_params,
+            body_fields: file.redaction.body_fields,
+            body_paths: Globs::new(file.redaction.body_paths)
+                .map_err(|e| invalid(format!("redaction.body_paths: {e}")))?,
+        });
+        redaction.extend(Redaction {
+            headers: args.redact_headers,
+            query_params: args.redact_query_params,
+            body_fields: args.redact_body_fields,
+            body_paths: Globs::new(args.redact_body_paths)
+                .map_err(|e| invalid(format!("redacted body path: {e}")))?,
+        });
+        if let Some(path) = redaction
+            .body_paths
+            .patterns()
+            .find(|p| !p.starts_with('/'))
+        {
+            return Err(invalid(format!(
+                "redacted body path {path:?} must start with `/`"
+            )));
+        }
+
+        if args.max_total_size == Some(0) || args.max_age_days == Some(0) {
+            return Err(invalid("retention limits must be positive".into()));
+        }
+        let storage = file.storage;
+        let storage_config = Storage {
+            format: args.capture_format.or(storage.format).unwrap_or_default(),
+            layout: args.layout.or(storage.layout).unwrap_or_default(),
+            compression: args.compression.or(storage.compression).unwrap_or_default(),
+            max_total_size: args
+                .max_total_size
+                .or(storage.max_total_size.map(NonZeroU64::get)),
+            max_age: args
+                .max_age_days
+                .or(storage.max_age_days.map(NonZeroU64::get))
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            no_index: args.no_index || storage.index == Some(false),
+        };
+
+        let queue_config = args.queue_dir.or(file.queue.dir).map(|dir| QueueConfig {
+            dir,
+            max_attempts: args
+                .queue_max_attempts
+                .or(file.queue.max_attempts.map(NonZeroU32::get)),
+        });
+        // Retention would take queued uploads for stray captures.
+        if let Some(queue) = &queue_config
+            && queue.dir.starts_with(&payload_dir)
+        {
+            return Err(invalid(format!(
+                "queue directory {:?} must not be inside the payload directory {:?}",
+                queue.dir, payload_dir
+            )));
+        }
+
+        let replay_config = args.replay_dir.or(file.replay.dir).map(|dir| ReplayConfig {
+            dir,
+            strategy: args
+                .replay_match
+                .or(file.replay.strategy)
+                .unwrap_or_default(),
+            strict: args.replay_strict || file.replay.strict.unwrap_or(false),
+        });
+
+        if upstream_url.is_none() && routes.is_empty() && replay_config.is_none() {
+            return Err(invalid(
+                "an upstream is required: set UPSTREAM_URL, --upstream-url, routes, or replay"
+                    .into(),
+            ));
+        }
+
+        Ok(Self {
+            listen,
+            payload_dir,
             upstream_url,
+            routes,
+            mirrors,
+            connect_timeout: Duration::from_secs(connect_timeout),
+            request_timeout: Duration::from_secs(request_timeout),
+            max_request_body,
+            capture: capture_filter,
+            redaction,
+            storage: storage_config,
+            queue: queue_config,
+            replay: replay_config,
+        })
+    }
+}
+
+impl Route {
+    fn new(prefix: String, upstream_url: String) -> Result<Self, ConfigError> {
+        if !prefix.starts_with('/') {
+            return Err(invalid(format!(
+                "route prefix {prefix:?} must start with `/`"
+            )));
         }
+        Ok(Self {
+            upstream_url: check_url(&format!("route {prefix}"), upstream_url)?,
+            prefix,
+        })
+    }
+}
+
+impl Mirror {
+    fn new(url: String, retries: u32) -> Result<Self, ConfigError> {
+        Ok(Self {
+            url: check_url("mirror", url)?,
+            retries,
+        })
+    }
+}
+
+/// The `[capture]` table's `include` or `exclude` rules.
+fn capture_rules(
+    key: &str,
+    rules: Vec<file::CaptureRule>,
+) -> Result<Vec<CaptureRule>, ConfigError> {
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            let name = format!("capture.{key}[{i}]");
+            let statuses = rule
+                .status
+                .iter()
+                .map(|status| status.parse())
+                .collect::<Result<_, String>>()
+                .map_err(|e| invalid(format!("{name}.status: {e}")))?;
+            let globs = |key: &str, patterns: Vec<String>| {
+                Globs::new(patterns).map_err(|e| invalid(format!("{name}.{key}: {e}")))
+            };
+            let rule = CaptureRule {
+                methods: rule.method,
+                paths: globs("path", rule.path)?,
+                statuses,
+                content_types: globs("content_type", rule.content_type)?,
+            };
+            rule.validate()
+                .map_err(|e| invalid(format!("{name}: {e}")))?;
+            Ok(rule)
+        })
+        .collect()
+}
+
+/// Flag values replace the file's list rather than adding to it.
+fn or_file<T>(args: Vec<T>, file: Vec<T>) -> Vec<T> {
+    if args.is_empty() { file } else { args }
+}
+
+fn read_file(path: &Path) -> Result<File, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse_file(path, &text)
+}
+
+fn parse_file(path: &Path, text: &str) -> Result<File, ConfigError> {
+    toml::from_str(text).map_err(|source| ConfigError::Syntax {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn invalid(message: String) -> ConfigError {
+    ConfigError::Invalid(message)
+}
+
+/// Accepts `http(s)://host...` and drops trailing slashes, since request paths are
+/// appended as they are.
+fn check_url(what: &str, url: String) -> Result<String, ConfigError> {
+    let host = url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"));
+    match host {
+        Some(host) if !host.is_empty() && !host.starts_with('/') => {
+            Ok(url.trim_end_matches('/').to_string())
+        }
+        _ => Err(invalid(format!(
+            "{what}: {url:?} is not an http:// or https:// URL"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(args: Args, file: &str) -> Result<Config, ConfigError> {
+        Config::resolve(args, parse_file(Path::new("proxy.toml"), file)?)
+    }
+
+    #[test]
+    fn test_file_settings_with_flag_overrides() {
+        let file = r#"
+listen = "127.0.0.1:9000"
+upstream_url = "https://ge.example.com/"
+
+[timeouts]
+connect_secs = 5
+
+[[routes]]
+prefix = "/scans/publish"
+upstream_url = "http://localhost:5000"
+
+[replay]
+dir = "captures"
+match = "body"
+"#;
+        let config = resolve(
+            Args {
+                port: Some(9100),
+                request_timeout: Some(300),
+                ..Default::default()
+            },
+            file,
+        )
+        .unwrap();
+
+        assert_eq!(config.listen, "127.0.0.1:9100".parse().unwrap());
+        assert_eq!(
+            config.upstream_url.as_deref(),
+            Some("https://ge.example.com")
+        );
+        assert_eq!(config.connect_timeout, Duration::from_secs(5));
+        assert_eq!(config.request_timeout, Duration::from_secs(300));
+        assert_eq!(config.payload_dir, PathBuf::from("/tmp/gradle-payloads"));
+        let replay = config.replay.as_ref().unwrap();
+        assert_eq!(replay.strategy, MatchStrategy::Body);
+        assert!(!replay.strict);
+
+        assert_eq!(
+            config.upstream_for("/scans/publish/gradle/4.3.2/upload"),
+            Some("http://localhost:5000")
+        );
+        assert_eq!(
+            config.upstream_for("/usage/users/check"),
+            Some("https://ge.example.com")
+        );
+    }
+
+    #[test]
+    fn test_flag_routes_replace_file_routes() {
+        let config = resolve(
+            Args {
+                routes: vec!["/a=http://a".into(), "/a/b=http://b".into()],
+                ..Default::default()
+            },
+            "[[routes]]\nprefix = \"/c\"\nupstream_url = \"http://c\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.routes.len(), 2);
+        assert_eq!(config.upstream_for("/a/b/c"), Some("http://b"));
+        assert_eq!(config.upstream_for("/a/x"), Some("http://a"));
+        assert_eq!(config.upstream_for("/c"), None);
+    }
+
+    #[test]
+    fn test_mirror_settings() {
+        let file = r#"
+upstream_url = "http://up"
+mirror_retries = 1
+
+[[mirrors]]
+url = "http://ingest.internal:8080/"
+
+[[mirrors]]
+url = "https://backup.example.com"
+retries = 0
+"#;
+        let config = resolve(Args::default(), file).unwrap();
+        assert_eq!(
+            config.mirrors,
+            vec![
+                Mirror {
+                    url: "http://ingest.internal:8080".into(),
+                    retries: 1,
+                },
+                Mirror {
+                    url: "https://backup.example.com".into(),
+                    retries: 0,
+                },
+            ]
+        );
+
+        let config = resolve(
+            Args {
+                mirrors: vec!["http://flag".into()],
+                ..Default::default()
+            },
+            file,
+        )
+        .unwrap();
+        assert_eq!(config.mirrors.len(), 1);
+        assert_eq!(config.mirrors[0].retries, 1);
+        assert!(
+            resolve(Args::default(), "upstream_url = \"http://up\"")
+                .unwrap()
+                .mirrors
+                .is_empty()
+        );
+
+        let error = resolve(Args::default(), "[[mirrors]]\nurl = \"ftp://x\"\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("mirror"), "{error}");
+        let error = resolve(Args::default(), "mirror_retries = -1")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("invalid value: integer `-1`"), "{error}");
+    }
+
+    #[test]
+    fn test_capture_filter_settings() {
+        let file = r#"
+upstream_url = "http://upstream"
+
+[capture]
+sample_percent = 50
+include = [
+    { method = "POST", path = "/scans/publish/gradle/*/upload" },
+    { path = ["/scans/**"], status = ["5xx", "429"] },
+]
+exclude = [{ content_type = "text/*" }]
+"#;
+        let config = resolve(Args::default(), file).unwrap();
+        let capture = &config.capture;
+        assert_eq!(capture.sample_percent, 50);
+        assert_eq!(capture.include.len(), 2);
+        assert_eq!(
+            capture.include[1].statuses,
+            vec![StatusPattern::Class(5), StatusPattern::Exact(429)]
+        );
+        assert!(capture.exclude[0].content_types.patterns().eq(["text/*"]));
+
+        let config = resolve(
+            Args {
+                capture_include: vec!["method=PUT".parse().unwrap()],
+                capture_sample: Some(100),
+                ..Default::default()
+            },
+            file,
+        )
+        .unwrap();
+        assert_eq!(config.capture.include[0].methods, vec!["PUT"]);
+        assert_eq!(config.capture.exclude.len(), 1);
+        assert_eq!(config.capture.sample_percent, 100);
+        assert!(
+            resolve(Args::default(), "upstream_url = \"http://up\"")
+                .unwrap()
+                .capture
+                .is_default()
+        );
+    }
+
+    #[test]
+    fn test_redaction_settings_extend_defaults() {
+        let file = r#"
+upstream_url = "http://upstream"
+
+[redaction]
+headers = "X-Build-Token"
+body_fields = ["apiKey"]
+"#;
+        let config = resolve(
+            Args {
+                redact_query_params: vec!["sig".into()],
+                ..Default::default()
+            },
+            file,
+        )
+        .unwrap();
+        let redaction = &config.redaction;
+        assert!(redaction.header("authorization"));
+        assert!(redaction.header("x-build-token"));
+        assert!(redaction.body_field("apikey"));
+        assert!(redaction.body_field("accessKey"));
+        assert!(redaction.query_param("sig"));
+
+        let config = resolve(
+            Args::default(),
+            "upstream_url = \"http://up\"\n[redaction]\ndefaults = false\nheaders = [\"x-key\"]",
+        )
+        .unwrap();
+        assert_eq!(config.redaction.headers, vec!["x-key"]);
+        assert!(config.redaction.body_fields.is_empty());
+
+        let config = resolve(
+            Args {
+                upstream_url: Some("http://up".into()),
+                no_redaction_defaults: true,
+                ..Default::default()
+            },
+            "",
+        )
+        .unwrap();
+        assert!(config.redaction.is_none());
+    }
+
+    #[test]
+    fn test_storage_settings() {
+        let file = r#"
+upstream_url = "http://upstream"
+
+[storage]
+format = "har"
+layout = "daily"
+compression = "gzip"
+max_total_size = 1_000_000_000
+max_age_days = 14
+index = false
+"#;
+        let config = resolve(
+            Args {
+                max_age_days: Some(2),
+                ..Default::default()
+            },
+            file,
+        )
+        .unwrap();
+        assert_eq!(
+            config.storage,
+            Storage {
+                format: CaptureFormat::Har,
+                layout: Layout::Daily,
+                compression: Compression::Gzip,
+                max_total_size: Some(1_000_000_000),
+                max_age: Some(Duration::from_secs(2 * 24 * 60 * 60)),
+                no_index: true,
+            }
+        );
+        assert!(config.storage.retains());
+
+        let config = resolve(Args::default(), "upstream_url = \"http://up\"").unwrap();
+        assert_eq!(config.storage, Storage::default());
+        assert!(!config.storage.retains());
+    }
+
+    #[test]
+    fn test_queue_settings() {
+        let file = "upstream_url = \"http://up\"\n[queue]\ndir = \"/var/spool/uploads\"\nmax_attempts = 50\n";
+        let config = resolve(Args::default(), file).unwrap();
+        assert_eq!(
+            config.queue,
+            Some(QueueConfig {
+                dir: PathBuf::from("/var/spool/uploads"),
+                max_attempts: Some(50),
+            })
+        );
+        let config = resolve(
+            Args {
+                queue_dir: Some("/q".into()),
+                ..Default::default()
+            },
+            "upstream_url = \"http://up\"",
+        )
+        .unwrap();
+        assert_eq!(config.queue.unwrap().max_attempts, None);
+
+        let error = resolve(
+            Args {
+                queue_dir: Some("/tmp/gradle-payloads/queue".into()),
+                ..Default::default()
+            },
+            "upstream_url = \"http://up\"",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("must not be inside"), "{error}");
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let error = |args: Args, file: &str| resolve(args, file).unwrap_err().to_string();
+        let upstream = || Args {
+            upstream_url: Some("http://upstream".into()),
+            ..Default::default()
+        };
+
+        assert!(error(Args::default(), "").contains("an upstream is required"));
+        let syntax = |file: &str, expected: &str| {
+            let error = error(upstream(), file);
+            assert!(
+                error.starts_with("Invalid config file \"proxy.toml\"") && error.contains(expected),
+                "{error}"
+            );
+        };
+        syntax(
+            "[timeouts]\nconect_secs = 5",
+            "unknown field `conect_secs`, expected `connect_secs` or `request_secs`",
+        );
+        syntax(
+            "[limits]\nmax_request_body = 0",
+            "invalid value: integer `0`, expected a nonzero u64",
+        );
+        syntax("payload_dir = 1", "invalid type: integer `1`");
+        syntax(
+            "[[routes]]\nprefix = \"/x\"",
+            "missing field `upstream_url`",
+        );
+        syntax(
+            "[replay]\ndir = \"d\"\nmatch = \"exact\"",
+            "unknown variant `exact`, expected one of `path`, `path-query`, `body`",
+        );
+        syntax(
+            "[capture]\ninclude = [{ status = \"2xx\", host = \"x\" }]",
+            "unknown field `host`",
+        );
+        syntax(
+            "[redaction]\nheaders = [1]",
+            "a string or an array of strings",
+        );
+        assert_eq!(
+            error(upstream(), "[capture]\nexclude = [{ status = \"20\" }]"),
+            "Invalid config: capture.exclude[0].status: \
+             status \"20\" must be a code like 404 or a class like 2xx"
+        );
+        assert!(error(upstream(), "[capture]\ninclude = [{}]").contains("at least one criterion"));
+        assert!(error(upstream(), "[capture]\nsample_percent = 101").contains("between 1 and 100"));
+        syntax(
+            "[storage]\ncompression = \"brotli\"",
+            "unknown variant `brotli`, expected one of `none`, `gzip`, `zstd`",
+        );
+        assert!(
+            error(
+                Args {
+                    max_total_size: Some(0),
+                    ..upstream()
+                },
+                ""
+            )
+            .contains("must be positive")
+        );
+        assert!(
+            error(upstream(), "[redaction]\nbody_paths = [\"token\"]")
+                .contains("must start with `/`")
+        );
+        assert!(
+            error(upstream(), "[redaction]\nbody_paths = \"/api/[\"")
+                .starts_with("Invalid config: redaction.body_paths: error parsing glob")
+        );
+        assert!(
+            error(
+                Args {
+                    upstream_url: Some("ftp://host".into()),
+                    ..Default::default()
+                },
+                ""
+            )
+            .contains("is not an http:// or https:// URL")
+        );
+        assert!(
+            error(
+                Args {
+                    routes: vec!["scans=http://x".into()],
+                    ..Default::default()
+                },
+                ""
+            )
+            .contains("must start with `/`")
+        );
+    }
+
+    #[test]
+    fn test_flags_parse_with_env_fallbacks() {
+        let args = Args::try_parse_from([
+            "proxy",
+            "--upstream-url",
+            "http://up",
+            "--route",
+            "/a=http://a",
+            "--route",
+            "/b=http://b",
+            "--replay-match",
+            "path",
+        ])
+        .unwrap();
+        assert_eq!(args.routes.len(), 2);
+        assert_eq!(args.replay_match, Some(MatchStrategy::Path));
+        assert!(Args::try_parse_from(["proxy", "--replay-match", "nope"]).is_err());
+        assert!(Args::try_parse_from(["proxy", "--capture-sample", "0"]).is_err());
+        assert!(Args::try_parse_from(["proxy", "--capture-include", "path=x"]).is_err());
     }
 }
@@ -0,0 +1,161 @@
+//! Secrets removed from captures before they are written.
+
+use crate::filter::Globs;
+
+/// What replaces a redacted value.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Names are compared case-insensitively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    /// Request and response headers whose values are replaced.
+    pub headers: Vec<String>,
+    /// Query parameters whose values are replaced.
+    pub query_params: Vec<String>,
+    /// JSON object fields, at any depth, whose values are replaced.
+    pub body_fields: Vec<String>,
+    /// Paths of exchanges whose bodies are replaced entirely, for endpoints that answer
+    /// with a bare secret.
+    pub body_paths: Globs,
+}
+
+impl Default for Redaction {
+    /// Develocity credentials: access keys travel in `Authorization`, short-lived tokens
+    /// come back as the plain text body of `/api/auth/token`, scan publishing hands out
+    /// an upload token the upload presents in `X-Upload-Token`, and publish responses
+    /// hand out presigned upload URLs.
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        Self {
+            headers: names(&[
+                "authorization",
+                "proxy-authorization",
+                "cookie",
+                "set-cookie",
+                "x-upload-token",
+            ]),
+            query_params: names(&[
+                "access_token",
+                "token",
+                "x-amz-credential",
+                "x-amz-security-token",
+                "x-amz-signature",
+            ]),
+            body_fields: names(&[
+                "accessKey",
+                "access_key",
+                "accessToken",
+                "access_token",
+                "refreshToken",
+                "scanUploadToken",
+                "token",
+                "password",
+                "secret",
+            ]),
+            body_paths: Globs::new(["/api/auth/token"]).expect("built-in glob is valid"),
+        }
+    }
+}
+
+impl Redaction {
+    /// No redaction at all.
+    pub fn none() -> Self {
+        Self {
+            headers: Vec::new(),
+            query_params: Vec::new(),
+            body_fields: Vec::new(),
+            body_paths: Globs::default(),
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::none()
+    }
+
+    pub fn header(&self, name: &str) -> bool {
+        contains(&self.headers, name)
+    }
+
+    pub fn query_param(&self, name: &str) -> bool {
+        contains(&self.query_params, name)
+    }
+
+    pub fn body_field(&self, name: &str) -> bool {
+        contains(&self.body_fields, name)
+    }
+
+    /// Whether the bodies of requests to `path` (without query) are dropped entirely.
+    pub fn body(&self, path: &str) -> bool {
+        self.body_paths.is_match(path)
+    }
+
+    /// `uri` with the values of redacted query parameters replaced.
+    pub fn uri(&self, uri: &str) -> String {
+        let Some((path, query)) = uri.split_once('?') else {
+            return uri.to_string();
+        };
+        let query: Vec<String> = query
+            .split('&')
+            .map(|param| match param.split_once('=') {
+                Some((name, _)) if self.query_param(name) => format!("{name}={REDACTED}"),
+                _ => param.to_string(),
+            })
+            .collect();
+        format!("{path}?{}", query.join("&"))
+    }
+
+    /// Adds names from the config file or flags, skipping ones already listed.
+    pub(crate) fn extend(&mut self, other: Redaction) {
+        let merge = |into: &mut Vec<String>, from: Vec<String>| {
+            for name in from {
+                if !contains(into, &name) {
+                    into.push(name);
+                }
+            }
+        };
+        merge(&mut self.headers, other.headers);
+        merge(&mut self.query_params, other.query_params);
+        merge(&mut self.body_fields, other.body_fields);
+        self.body_paths.extend(other.body_paths);
+    }
+}
+
+fn contains(names: &[String], name: &str) -> bool {
+    names.iter().any(|n| n.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let redaction = Redaction::default();
+        assert!(redaction.header("Authorization"));
+        assert!(redaction.header("X-Upload-Token"));
+        assert!(redaction.body_field("scanUploadToken"));
+        assert!(!redaction.header("Content-Type"));
+        assert!(redaction.body_field("accesskey"));
+        assert!(redaction.body("/api/auth/token"));
+        // The token exchange's other fields are kept; `scanUploadToken` goes field-wise.
+        assert!(!redaction.body("/scans/publish/gradle/4.3.2/token"));
+        assert_eq!(
+            redaction.uri("/upload?X-Amz-Signature=abc&part=1&token"),
+            "/upload?X-Amz-Signature=[REDACTED]&part=1&token"
+        );
+        assert_eq!(redaction.uri("/upload"), "/upload");
+        assert_eq!(Redaction::none().uri("/x?token=a"), "/x?token=a");
+    }
+
+    #[test]
+    fn test_extend_skips_duplicates() {
+        let mut redaction = Redaction::default();
+        let headers = redaction.headers.len();
+        redaction.extend(Redaction {
+            headers: vec!["AUTHORIZATION".into(), "X-Custom-Key".into()],
+            ..Redaction::none()
+        });
+        assert_eq!(redaction.headers.len(), headers + 1);
+        assert!(redaction.header("x-custom-key"));
+    }
+}
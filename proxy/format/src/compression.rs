@@ -0,0 +1,79 @@
+//! Compressed capture files, told apart by their extension.
+
+use std::io::{self, Read};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    pub const ALL: [Codec; 2] = [Codec::Gzip, Codec::Zstd];
+
+    /// Appended to the name of a file compressed with this codec.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Codec::Gzip => ".gz",
+            Codec::Zstd => ".zst",
+        }
+    }
+
+    pub fn decode(self, compressed: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        match self {
+            Codec::Gzip => {
+                flate2::read::GzDecoder::new(compressed).read_to_end(&mut decoded)?;
+            }
+            Codec::Zstd => {
+                zstd::Decoder::new(compressed)?.read_to_end(&mut decoded)?;
+            }
+        }
+        Ok(decoded)
+    }
+}
+
+/// `name` without its compression extension, and the codec that extension stands for.
+pub fn split(name: &str) -> (&str, Option<Codec>) {
+    Codec::ALL
+        .into_iter()
+        .find_map(|codec| {
+            name.strip_suffix(codec.extension())
+                .map(|name| (name, Some(codec)))
+        })
+        .unwrap_or((name, None))
+}
+
+/// Reads a capture file, decompressed if its extension says it's compressed.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match split(name).1 {
+        Some(codec) => codec.decode(&bytes),
+        None => Ok(bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_split_and_decode() {
+        assert_eq!(split("a.json.gz"), ("a.json", Some(Codec::Gzip)));
+        assert_eq!(split("a.har.zst"), ("a.har", Some(Codec::Zstd)));
+        assert_eq!(split("a.json"), ("a.json", None));
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(b"{}").unwrap();
+        assert_eq!(Codec::Gzip.decode(&gzip.finish().unwrap()).unwrap(), b"{}");
+        let zstd = zstd::encode_all(&b"{}"[..], 0).unwrap();
+        assert_eq!(Codec::Zstd.decode(&zstd).unwrap(), b"{}");
+        assert!(Codec::Zstd.decode(b"{}").is_err());
+    }
+}
@@ -0,0 +1,416 @@
+//! HTTP Archive (HAR 1.2) rendering of captures, for browser and other HAR tooling.
+//!
+//! Binary bodies stay base64: response content uses the standard `encoding` field,
+//! request post data the custom `_encoding` field, since HAR has none there.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{MirrorDelivery, Payload};
+
+pub const VERSION: &str = "1.2";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Har {
+    pub log: Log,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Log {
+    pub version: String,
+    pub creator: Creator,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub started_date_time: String,
+    /// Sum of the known timings, in milliseconds.
+    pub time: f64,
+    pub request: Request,
+    pub response: Response,
+    pub cache: Cache,
+    pub timings: Timings,
+    /// Why the upstream couldn't be reached, for failed exchanges.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(rename = "_mirrors", default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<MirrorDelivery>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub cookies: Vec<NameValue>,
+    pub headers: Vec<NameValue>,
+    pub query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<PostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostData {
+    pub mime_type: String,
+    pub text: String,
+    #[serde(rename = "_encoding", skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    /// 0 when no response was received.
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub cookies: Vec<NameValue>,
+    pub headers: Vec<NameValue>,
+    pub content: Content,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Content {
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameValue {
+    pub name: String,
+    pub value: String,
+}
+
+/// Milliseconds per phase; -1 where unknown, as HAR allows for all but `send`, `wait`
+/// and `receive`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Timings {
+    pub blocked: f64,
+    pub dns: f64,
+    pub connect: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+    pub ssl: f64,
+}
+
+impl Har {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        Self {
+            log: Log {
+                version: VERSION.to_string(),
+                creator: Creator {
+                    name: "gradle-build-scan-proxy".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                entries,
+            },
+        }
+    }
+
+    /// One entry per capture, in the order given.
+    pub fn from_payloads<'a>(payloads: impl IntoIterator<Item = &'a Payload>) -> Self {
+        Self::new(payloads.into_iter().map(Entry::from_payload).collect())
+    }
+}
+
+impl Entry {
+    pub fn from_payload(payload: &Payload) -> Self {
+        let request = &payload.request;
+        let host = header(&request.headers, "host").unwrap_or("localhost");
+        let (request_text, request_encoding) = body_text(&request.body);
+        let post_data = request_text.map(|text| PostData {
+            mime_type: header(&request.headers, "content-type")
+                .unwrap_or_default()
+                .to_string(),
+            text,
+            encoding: request_encoding,
+        });
+
+        let response = &payload.response;
+        let response_headers = response.headers.as_deref().unwrap_or_default();
+        let (response_text, response_encoding) =
+            response.body.as_ref().map_or((None, None), body_text);
+        let response_size = response.body.as_ref().map_or(0, body_size);
+
+        let timings = match &payload.timings {
+            Some(t) => Timings {
+                blocked: -1.0,
+                dns: -1.0,
+                connect: t.connect.unwrap_or(-1.0),
+                send: t.send.unwrap_or(0.0),
+                wait: t.wait,
+                receive: t.receive.unwrap_or(0.0),
+                ssl: -1.0,
+            },
+            None => Timings {
+                blocked: -1.0,
+                dns: -1.0,
+                connect: -1.0,
+                send: 0.0,
+                wait: 0.0,
+                receive: 0.0,
+                ssl: -1.0,
+            },
+        };
+        let time: f64 = [timings.connect, timings.send, timings.wait, timings.receive]
+            .into_iter()
+            .filter(|t| *t > 0.0)
+            .sum();
+        // Keep the microsecond precision of the phases rather than float noise.
+        let time = (time * 1e3).round() / 1e3;
+
+        Self {
+            started_date_time: payload
+                .timings
+                .as_ref()
+                .map(|t| t.started.clone())
+                .unwrap_or_else(|| started_from_timestamp(&payload.timestamp)),
+            time,
+            request: Request {
+                method: request.method.clone(),
+                url: format!("http://{host}{}", request.uri),
+                http_version: "HTTP/1.1".to_string(),
+                cookies: Vec::new(),
+                headers: name_values(&request.headers),
+                query_string: query_string(&request.uri),
+                post_data,
+                headers_size: -1,
+                body_size: body_size(&request.body),
+            },
+            response: Response {
+                status: response.status.unwrap_or(0),
+                status_text: String::new(),
+                http_version: "HTTP/1.1".to_string(),
+                cookies: Vec::new(),
+                headers: name_values(response_headers),
+                content: Content {
+                    size: response_size,
+                    mime_type: header(response_headers, "content-type")
+                        .unwrap_or_default()
+                        .to_string(),
+                    text: response_text,
+                    encoding: response_encoding,
+                },
+                redirect_url: header(response_headers, "location")
+                    .unwrap_or_default()
+                    .to_string(),
+                headers_size: -1,
+                body_size: response_size,
+            },
+            cache: Cache::default(),
+            timings,
+            comment: response.error.clone(),
+            mirrors: payload.mirrors.clone(),
+        }
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+fn name_values(headers: &[(String, String)]) -> Vec<NameValue> {
+    headers
+        .iter()
+        .map(|(name, value)| NameValue {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect()
+}
+
+/// Query parameters as they appear in the URI, still percent-encoded.
+fn query_string(uri: &str) -> Vec<NameValue> {
+    let Some((_, query)) = uri.split_once('?') else {
+        return Vec::new();
+    };
+    query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            NameValue {
+                name: name.to_string(),
+                value: value.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// A captured body as HAR text and encoding: a string as is, `{"base64": ...}` as
+/// base64 text. Empty bodies have no text.
+fn body_text(body: &serde_json::Value) -> (Option<String>, Option<String>) {
+    match body {
+        serde_json::Value::String(s) if !s.is_empty() => (Some(s.clone()), None),
+        serde_json::Value::Object(map) => match map.get("base64").and_then(|v| v.as_str()) {
+            Some(b64) if !b64.is_empty() => (Some(b64.to_string()), Some("base64".to_string())),
+            _ => (None, None),
+        },
+        _ => (None, None),
+    }
+}
+
+/// Size of the captured body in bytes, without decoding base64.
+fn body_size(body: &serde_json::Value) -> i64 {
+    match body {
+        serde_json::Value::String(s) => s.len() as i64,
+        serde_json::Value::Object(map) => {
+            let b64 = map
+                .get("base64")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let padding = b64.bytes().rev().take_while(|b| *b == b'=').count();
+            (b64.len() / 4 * 3).saturating_sub(padding) as i64
+        }
+        _ => 0,
+    }
+}
+
+/// `20261018_204514.959` (UTC, as the proxy names captures) to
+/// `2026-10-18T20:45:14.959Z`; other strings are passed through.
+fn started_from_timestamp(timestamp: &str) -> String {
+    let b = timestamp.as_bytes();
+    let digits = |range: std::ops::Range<usize>| {
+        b.get(range)
+            .is_some_and(|d| d.iter().all(u8::is_ascii_digit))
+    };
+    if !(digits(0..8) && b.get(8) == Some(&b'_') && digits(9..15)) {
+        return timestamp.to_string();
+    }
+    format!(
+        "{}-{}-{}T{}:{}:{}{}Z",
+        &timestamp[..4],
+        &timestamp[4..6],
+        &timestamp[6..8],
+        &timestamp[9..11],
+        &timestamp[11..13],
+        &timestamp[13..15],
+        &timestamp[15..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RequestData, ResponseData};
+    use serde_json::json;
+
+    fn upload() -> Payload {
+        Payload {
+            request_id: "id".into(),
+            timestamp: "20261018_204514.959".into(),
+            request: RequestData {
+                method: "POST".into(),
+                uri: "/scans/publish/gradle/4.3.2/upload?retry=1&x".into(),
+                headers: vec![
+                    ("host".into(), "ge.example.com".into()),
+                    (
+                        "content-type".into(),
+                        "application/vnd.gradle.scan-upload".into(),
+                    ),
+                ],
+                body: json!({"base64": "KMUBAg=="}),
+            },
+            response: ResponseData {
+                status: Some(200),
+                headers: Some(vec![("Content-Type".into(), "text/plain".into())]),
+                body: Some(json!("ok")),
+                error: None,
+            },
+            timings: None,
+            mirrors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_entry_from_payload() {
+        let mut payload = upload();
+        payload.timings = Some(crate::Timings {
+            started: "2026-10-18T20:45:14.959Z".into(),
+            connect: Some(12.5),
+            send: Some(3.0),
+            wait: 40.0,
+            receive: None,
+        });
+        let har = serde_json::to_value(Har::from_payloads([&payload])).unwrap();
+        let entry = &har["log"]["entries"][0];
+
+        assert_eq!(har["log"]["version"], "1.2");
+        assert_eq!(entry["time"], 55.5);
+        assert_eq!(
+            entry["request"]["url"],
+            "http://ge.example.com/scans/publish/gradle/4.3.2/upload?retry=1&x"
+        );
+        assert_eq!(
+            entry["request"]["queryString"],
+            json!([{"name": "retry", "value": "1"}, {"name": "x", "value": ""}])
+        );
+        assert_eq!(
+            entry["request"]["postData"],
+            json!({
+                "mimeType": "application/vnd.gradle.scan-upload",
+                "text": "KMUBAg==",
+                "_encoding": "base64",
+            })
+        );
+        assert_eq!(entry["request"]["bodySize"], 4);
+        assert_eq!(
+            entry["response"]["content"],
+            json!({"size": 2, "mimeType": "text/plain", "text": "ok"})
+        );
+        assert_eq!(entry["response"]["redirectURL"], "");
+        assert_eq!(
+            entry["timings"],
+            json!({
+                "blocked": -1.0, "dns": -1.0, "connect": 12.5, "send": 3.0,
+                "wait": 40.0, "receive": 0.0, "ssl": -1.0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_failed_exchange_without_timings() {
+        let mut payload = upload();
+        payload.request.body = json!("");
+        payload.response = ResponseData {
+            status: None,
+            headers: None,
+            body: None,
+            error: Some("connection refused".into()),
+        };
+        let entry = Entry::from_payload(&payload);
+        assert_eq!(entry.started_date_time, "2026-10-18T20:45:14.959Z");
+        assert!(entry.request.post_data.is_none());
+        assert_eq!(entry.response.status, 0);
+        assert_eq!(entry.comment.as_deref(), Some("connection refused"));
+        assert_eq!(entry.time, 0.0);
+        assert_eq!(started_from_timestamp("yesterday"), "yesterday");
+    }
+}
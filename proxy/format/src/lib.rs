@@ -1,5 +1,27 @@
+pub mod compression;
+pub mod har;
+
 use serde::{Deserialize, Serialize};
 
+/// Suffix of a capture saved as `Payload` JSON.
+pub const PAYLOAD_SUFFIX: &str = ".json";
+/// Suffix of a capture saved as a HAR log.
+pub const HAR_SUFFIX: &str = ".har";
+/// Suffix of the parsed `BuildScanPayload` saved beside an upload.
+pub const BUILD_SCAN_SUFFIX: &str = ".build-scan.json";
+/// Suffix of the `ParseReport` saved beside an upload that failed to parse.
+pub const PARSE_REPORT_SUFFIX: &str = ".parse-report.json";
+/// Suffixes of the files making up a capture, before any compression extension. The
+/// sidecars come before `PAYLOAD_SUFFIX`, which they end with too.
+pub const CAPTURE_SUFFIXES: &[&str] = &[
+    BUILD_SCAN_SUFFIX,
+    PARSE_REPORT_SUFFIX,
+    PAYLOAD_SUFFIX,
+    HAR_SUFFIX,
+];
+/// The proxy's list of captures, at the top of the payload directory.
+pub const INDEX_FILE: &str = "index.tsv";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RequestData {
     pub method: String,
@@ -26,4 +48,59 @@ pub struct Payload {
     pub timestamp: String,
     pub request: RequestData,
     pub response: ResponseData,
+    /// Absent in captures from before the proxy measured them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+    /// Deliveries of the request to the configured mirrors, for build scan uploads.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<MirrorDelivery>,
+}
+
+/// How long each phase of the upstream exchange took, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    /// When the request arrived, as RFC 3339.
+    pub started: String,
+    /// Until the upstream connection took the request body. `None` for requests without
+    /// a body, whose connection time is part of `wait`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect: Option<f64>,
+    /// Streaming the request body upstream.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub send: Option<f64>,
+    /// Until the response headers arrived, or the exchange failed.
+    pub wait: f64,
+    /// Streaming the response body back.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receive: Option<f64>,
+}
+
+/// How sending a copy of the request to one mirror went.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorDelivery {
+    pub url: String,
+    pub attempts: u32,
+    /// Status of the last response, if any came.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Why the last attempt failed; `None` once delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl MirrorDelivery {
+    pub fn delivered(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Written next to a captured build scan upload that failed to parse.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParseReport {
+    pub request_id: String,
+    pub timestamp: String,
+    pub uri: String,
+    /// `ParseError` variant name.
+    pub kind: String,
+    pub error: String,
 }
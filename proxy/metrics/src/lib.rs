@@ -0,0 +1,278 @@
+//! Prometheus metrics for the proxy: request counters plus build health figures derived
+//! from the build scans passing through it, served in the OpenMetrics text format.
+
+use std::time::Duration;
+
+use error::ParseError;
+use models::{BuildOutcome, BuildScanPayload, TaskOutcome};
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::histogram::Histogram;
+
+/// `Content-Type` of `Registry::render`'s output.
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+const BUILD_DURATION_BUCKETS: &[f64] = &[
+    1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0,
+];
+const TASK_DURATION_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0];
+const REQUEST_DURATION_BUCKETS: &[f64] =
+    &[0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 120.0];
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct OutcomeLabels {
+    outcome: &'static str,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct TaskLabels {
+    task_path: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct CacheLabels {
+    task_type: String,
+    result: &'static str,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ErrorLabels {
+    error: &'static str,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RequestLabels {
+    method: String,
+    status: u16,
+}
+
+type HistogramFamily<L> = Family<L, Histogram, fn() -> Histogram>;
+
+#[derive(Debug)]
+pub struct Registry {
+    registry: prometheus_client::registry::Registry,
+    builds: Family<OutcomeLabels, Counter>,
+    build_duration: Histogram,
+    task_duration: HistogramFamily<TaskLabels>,
+    task_cache: Family<CacheLabels, Counter>,
+    parse_failures: Family<ErrorLabels, Counter>,
+    requests: Family<RequestLabels, Counter>,
+    request_duration: Histogram,
+    upstream_errors: Counter,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        let builds = Family::default();
+        let build_duration = Histogram::new(BUILD_DURATION_BUCKETS.iter().copied());
+        let task_duration: HistogramFamily<TaskLabels> =
+            Family::new_with_constructor(|| Histogram::new(TASK_DURATION_BUCKETS.iter().copied()));
+        let task_cache = Family::default();
+        let parse_failures = Family::default();
+        let requests = Family::default();
+        let request_duration = Histogram::new(REQUEST_DURATION_BUCKETS.iter().copied());
+        let upstream_errors = Counter::default();
+
+        // Counters are registered without `_total`; the encoder appends it.
+        let mut registry = prometheus_client::registry::Registry::default();
+        registry.register(
+            "gradle_builds",
+            "Parsed build scans by build outcome",
+            builds.clone(),
+        );
+        registry.register(
+            "gradle_build_duration_seconds",
+            "Wall-clock build duration from parsed build scans",
+            build_duration.clone(),
+        );
+        registry.register(
+            "gradle_task_duration_seconds",
+            "Task execution duration by task path",
+            task_duration.clone(),
+        );
+        registry.register(
+            "gradle_task_cache_results",
+            "Build cache hits and misses of cacheable tasks by task type",
+            task_cache.clone(),
+        );
+        registry.register(
+            "gradle_scan_parse_failures",
+            "Uploaded build scans that failed to parse, by error kind",
+            parse_failures.clone(),
+        );
+        registry.register(
+            "proxy_requests",
+            "Proxied requests by method and response status",
+            requests.clone(),
+        );
+        registry.register(
+            "proxy_request_duration_seconds",
+            "Time until the upstream response head is returned; bodies stream afterwards",
+            request_duration.clone(),
+        );
+        registry.register(
+            "proxy_upstream_errors",
+            "Requests that failed to reach the upstream server",
+            upstream_errors.clone(),
+        );
+
+        Self {
+            registry,
+            builds,
+            build_duration,
+            task_duration,
+            task_cache,
+            parse_failures,
+            requests,
+            request_duration,
+            upstream_errors,
+        }
+    }
+
+    pub fn record_scan(&self, payload: &BuildScanPayload) {
+        let outcome = match payload.build.as_ref().and_then(|b| b.outcome) {
+            Some(BuildOutcome::Success) => "success",
+            Some(BuildOutcome::Failed) => "failed",
+            None => "unknown",
+        };
+        self.builds.get_or_create(&OutcomeLabels { outcome }).inc();
+        if let Some(ms) = payload.build.as_ref().and_then(|b| b.duration_ms) {
+            self.build_duration.observe(ms as f64 / 1000.0);
+        }
+
+        for task in &payload.tasks {
+            if let Some(ms) = task.duration_ms {
+                self.task_duration
+                    .get_or_create(&TaskLabels {
+                        task_path: task.task_path.to_string(),
+                    })
+                    .observe(ms as f64 / 1000.0);
+            }
+            let result = match (task.outcome, task.cacheable) {
+                (Some(TaskOutcome::FromCache), _) => "hit",
+                (Some(TaskOutcome::Success | TaskOutcome::Failed), Some(true)) => "miss",
+                _ => continue,
+            };
+            let task_type = task.class_name.as_deref().unwrap_or("unknown");
+            self.task_cache
+                .get_or_create(&CacheLabels {
+                    task_type: task_type.to_string(),
+                    result,
+                })
+                .inc();
+        }
+    }
+
+    pub fn record_parse_failure(&self, error: &ParseError) {
+        self.parse_failures
+            .get_or_create(&ErrorLabels {
+                error: error.kind(),
+            })
+            .inc();
+    }
+
+    pub fn record_request(&self, method: &str, status: u16, elapsed: Duration) {
+        self.requests
+            .get_or_create(&RequestLabels {
+                method: method.to_string(),
+                status,
+            })
+            .inc();
+        self.request_duration.observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_upstream_error(&self) {
+        self.upstream_errors.inc();
+    }
+
+    /// The exposition served on `/metrics`, as `CONTENT_TYPE`.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        // Writing to a String can't fail.
+        let _ = prometheus_client::encoding::text::encode(&mut out, &self.registry);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::{BuildData, Task};
+
+    fn task(path: &str, outcome: TaskOutcome, cacheable: bool, duration_ms: i64) -> Task {
+        Task {
+            id: 1,
+            build_path: ":".into(),
+            task_path: path.into(),
+            class_name: Some("org.gradle.api.tasks.compile.JavaCompile".into()),
+            outcome: Some(outcome),
+            cacheable: Some(cacheable),
+            caching_disabled_reason: None,
+            caching_disabled_explanation: None,
+            origin_build_cache_key: None,
+            actionable: None,
+            started_at: Some(0),
+            finished_at: Some(duration_ms),
+            duration_ms: Some(duration_ms),
+            inputs: None,
+        }
+    }
+
+    #[test]
+    fn test_render_scan_metrics() {
+        let registry = Registry::new();
+        registry.record_scan(&BuildScanPayload {
+            build: Some(BuildData {
+                outcome: Some(BuildOutcome::Success),
+                duration_ms: Some(42_000),
+                ..Default::default()
+            }),
+            tasks: vec![
+                task(":app:compileJava", TaskOutcome::FromCache, true, 40),
+                task(":lib:compileJava", TaskOutcome::Success, true, 2_000),
+            ],
+            ..Default::default()
+        });
+        let text = registry.render();
+
+        assert!(text.contains("# TYPE gradle_builds counter\n"));
+        assert!(text.contains("gradle_builds_total{outcome=\"success\"} 1\n"));
+        assert!(text.contains("gradle_build_duration_seconds_bucket{le=\"30.0\"} 0\n"));
+        assert!(text.contains("gradle_build_duration_seconds_bucket{le=\"60.0\"} 1\n"));
+        assert!(text.contains("gradle_build_duration_seconds_bucket{le=\"+Inf\"} 1\n"));
+        assert!(text.contains("gradle_build_duration_seconds_sum 42.0\n"));
+        assert!(
+            text.contains("gradle_task_duration_seconds_count{task_path=\":lib:compileJava\"} 1\n")
+        );
+        assert!(text.contains(
+            "gradle_task_cache_results_total{task_type=\"org.gradle.api.tasks.compile.JavaCompile\",result=\"hit\"} 1\n"
+        ));
+        assert!(text.contains(
+            "gradle_task_cache_results_total{task_type=\"org.gradle.api.tasks.compile.JavaCompile\",result=\"miss\"} 1\n"
+        ));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn test_render_request_and_failure_metrics() {
+        let registry = Registry::new();
+        registry.record_request("POST", 200, Duration::from_millis(20));
+        registry.record_request("POST", 200, Duration::from_millis(700));
+        registry.record_upstream_error();
+        registry.record_parse_failure(&ParseError::InvalidHeader { reason: "bad" });
+        let text = registry.render();
+
+        assert!(text.contains("proxy_requests_total{method=\"POST\",status=\"200\"} 2\n"));
+        assert!(text.contains("proxy_request_duration_seconds_bucket{le=\"0.05\"} 1\n"));
+        assert!(text.contains("proxy_request_duration_seconds_count 2\n"));
+        assert!(text.contains("proxy_upstream_errors_total 1\n"));
+        assert!(text.contains("gradle_scan_parse_failures_total{error=\"InvalidHeader\"} 1\n"));
+    }
+}
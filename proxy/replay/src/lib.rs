@@ -0,0 +1,323 @@
+//! Offline replay of captured payloads: incoming requests are matched against recorded
+//! requests and answered with the stored response.
+//!
+//! A request recorded several times (e.g. polling, or a `GET` before and after a `PUT`)
+//! is answered with its responses in capture order; once they run out the last one is
+//! repeated.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use base64::Engine as _;
+use config::MatchStrategy;
+use format::compression;
+use format::{PAYLOAD_SUFFIX, Payload};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    method: String,
+    uri: String,
+    body_hash: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Responses {
+    queue: Vec<Arc<RecordedResponse>>,
+    next: usize,
+}
+
+#[derive(Debug)]
+pub struct Replay {
+    strategy: MatchStrategy,
+    entries: Mutex<HashMap<Key, Responses>>,
+    /// Captures that can't be replayed: not a payload, or no upstream response.
+    pub skipped: usize,
+}
+
+impl Replay {
+    /// Loads every `*.json` capture, compressed or not, in `dir` and its (daily)
+    /// subdirectories, in path (capture time) order. Files that aren't payloads, or whose
+    /// request never got a response, are skipped.
+    pub fn load(dir: &Path, strategy: MatchStrategy) -> std::io::Result<Self> {
+        let mut paths = Vec::new();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    dirs.push(entry.path());
+                } else {
+                    paths.push(entry.path());
+                }
+            }
+        }
+        paths.retain(|p| {
+            p.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| compression::split(name).0.ends_with(PAYLOAD_SUFFIX))
+        });
+        paths.sort();
+
+        let mut payloads = Vec::with_capacity(paths.len());
+        let mut skipped = 0;
+        for path in paths {
+            let mut json = std::fs::read(&path)?;
+            let name = path.file_name().and_then(|name| name.to_str());
+            if let Some(codec) = name.and_then(|name| compression::split(name).1) {
+                match codec.decode(&json) {
+                    Ok(decoded) => json = decoded,
+                    Err(_) => {
+                        skipped += 1;
+                        continue;
+                    }
+                }
+            }
+            match serde_json::from_slice::<Payload>(&json) {
+                Ok(payload) => payloads.push(payload),
+                Err(_) => skipped += 1,
+            }
+        }
+        let mut replay = Self::from_payloads(payloads, strategy);
+        replay.skipped += skipped;
+        Ok(replay)
+    }
+
+    pub fn from_payloads(
+        payloads: impl IntoIterator<Item = Payload>,
+        strategy: MatchStrategy,
+    ) -> Self {
+        let mut entries: HashMap<Key, Responses> = HashMap::new();
+        let mut skipped = 0;
+        for payload in payloads {
+            let Some(status) = payload.response.status else {
+                skipped += 1;
+                continue;
+            };
+            let request_body = body_bytes(&payload.request.body);
+            let key = key(
+                strategy,
+                &payload.request.method,
+                &payload.request.uri,
+                &request_body,
+            );
+            let response = RecordedResponse {
+                status,
+                headers: payload.response.headers.unwrap_or_default(),
+                body: payload
+                    .response
+                    .body
+                    .as_ref()
+                    .map(body_bytes)
+                    .unwrap_or_default(),
+            };
+            entries
+                .entry(key)
+                .or_default()
+                .queue
+                .push(Arc::new(response));
+        }
+        Self {
+            strategy,
+            entries: Mutex::new(entries),
+            skipped,
+        }
+    }
+
+    pub fn strategy(&self) -> MatchStrategy {
+        self.strategy
+    }
+
+    /// Number of distinct requests that can be replayed.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The next recorded response for the request, if one was captured. `uri` is the path
+    /// and query as the client sent it.
+    pub fn lookup(&self, method: &str, uri: &str, body: &[u8]) -> Option<Arc<RecordedResponse>> {
+        let key = key(self.strategy, method, uri, body);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let responses = entries.get_mut(&key)?;
+        let response = responses
+            .queue
+            .get(responses.next)
+            .or(responses.queue.last())?;
+        responses.next = (responses.next + 1).min(responses.queue.len());
+        Some(response.clone())
+    }
+}
+
+fn key(strategy: MatchStrategy, method: &str, uri: &str, body: &[u8]) -> Key {
+    let uri = match strategy {
+        MatchStrategy::Path => uri.split_once('?').map_or(uri, |(path, _)| path),
+        MatchStrategy::PathAndQuery | MatchStrategy::Body => uri,
+    };
+    let body_hash = (strategy == MatchStrategy::Body).then(|| {
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        hasher.finish()
+    });
+    Key {
+        method: method.to_ascii_uppercase(),
+        uri: uri.to_string(),
+        body_hash,
+    }
+}
+
+/// Decodes a recorded body: a string for UTF-8 bodies, `{"base64": ...}` otherwise.
+fn body_bytes(body: &serde_json::Value) -> Vec<u8> {
+    match body {
+        serde_json::Value::String(s) => s.clone().into_bytes(),
+        serde_json::Value::Object(map) => map
+            .get("base64")
+            .and_then(|v| v.as_str())
+            .and_then(|b64| base64::engine::general_purpose::STANDARD.decode(b64).ok())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::{RequestData, ResponseData};
+    use serde_json::json;
+
+    fn payload(method: &str, uri: &str, body: serde_json::Value, status: u16) -> Payload {
+        Payload {
+            request_id: "id".into(),
+            timestamp: "ts".into(),
+            request: RequestData {
+                method: method.into(),
+                uri: uri.into(),
+                headers: Vec::new(),
+                body,
+            },
+            response: ResponseData {
+                status: Some(status),
+                headers: Some(vec![("content-type".into(), "text/plain".into())]),
+                body: Some(json!(format!("response {status}"))),
+                error: None,
+            },
+            timings: None,
+            mirrors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_replays_in_capture_order_then_repeats_last() {
+        let replay = Replay::from_payloads(
+            [
+                payload("GET", "/maven-metadata.xml", json!(""), 404),
+                payload("PUT", "/maven-metadata.xml", json!("<metadata/>"), 201),
+                payload("GET", "/maven-metadata.xml", json!(""), 200),
+            ],
+            MatchStrategy::PathAndQuery,
+        );
+        assert_eq!(replay.len(), 2);
+
+        let status = |method| {
+            replay
+                .lookup(method, "/maven-metadata.xml", b"")
+                .map(|r| r.status)
+        };
+        assert_eq!(status("GET"), Some(404));
+        assert_eq!(status("GET"), Some(200));
+        assert_eq!(status("GET"), Some(200));
+        assert_eq!(status("put"), Some(201));
+        assert_eq!(replay.lookup("GET", "/other", b""), None);
+
+        let response = replay.lookup("PUT", "/maven-metadata.xml", b"").unwrap();
+        assert_eq!(response.body, b"response 201");
+        assert_eq!(response.headers[0].0, "content-type");
+    }
+
+    #[test]
+    fn test_match_strategies() {
+        let recorded = || {
+            [payload(
+                "POST",
+                "/scans/publish?v=1",
+                json!({"base64": "KMUB"}),
+                200,
+            )]
+        };
+
+        let path = Replay::from_payloads(recorded(), MatchStrategy::Path);
+        assert!(
+            path.lookup("POST", "/scans/publish?v=2", b"other")
+                .is_some()
+        );
+
+        let query = Replay::from_payloads(recorded(), MatchStrategy::PathAndQuery);
+        assert!(query.lookup("POST", "/scans/publish?v=2", b"").is_none());
+        assert!(
+            query
+                .lookup("POST", "/scans/publish?v=1", b"other")
+                .is_some()
+        );
+
+        let body = Replay::from_payloads(recorded(), MatchStrategy::Body);
+        assert!(
+            body.lookup("POST", "/scans/publish?v=1", b"other")
+                .is_none()
+        );
+        assert!(
+            body.lookup("POST", "/scans/publish?v=1", &[0x28, 0xC5, 0x01])
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_loads_compressed_captures_from_subdirectories() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("replay-load-{}", std::process::id()));
+        let day = dir.join("2026-10-18");
+        std::fs::create_dir_all(&day).unwrap();
+        let first = serde_json::to_vec(&payload("GET", "/a", json!(""), 404)).unwrap();
+        std::fs::write(dir.join("1.json"), first).unwrap();
+        let second = serde_json::to_vec(&payload("GET", "/a", json!(""), 200)).unwrap();
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&second).unwrap();
+        std::fs::write(day.join("2.json.gz"), gzip.finish().unwrap()).unwrap();
+        let third = serde_json::to_vec(&payload("GET", "/a", json!(""), 201)).unwrap();
+        let zstd = zstd::encode_all(third.as_slice(), 0).unwrap();
+        std::fs::write(day.join("3.json.zst"), zstd).unwrap();
+        std::fs::write(dir.join("index.tsv"), "timestamp\n").unwrap();
+
+        let replay = Replay::load(&dir, MatchStrategy::Path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(replay.skipped, 0);
+        assert_eq!(replay.lookup("GET", "/a", b"").map(|r| r.status), Some(404));
+        assert_eq!(replay.lookup("GET", "/a", b"").map(|r| r.status), Some(200));
+        assert_eq!(replay.lookup("GET", "/a", b"").map(|r| r.status), Some(201));
+    }
+
+    #[test]
+    fn test_skips_requests_without_response() {
+        let mut failed = payload("GET", "/", json!(""), 200);
+        failed.response = ResponseData {
+            status: None,
+            headers: None,
+            body: None,
+            error: Some("connection refused".into()),
+        };
+        let replay = Replay::from_payloads([failed], MatchStrategy::Path);
+        assert!(replay.is_empty());
+        assert_eq!(replay.skipped, 1);
+    }
+}
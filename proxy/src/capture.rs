@@ -0,0 +1,1128 @@
+//! Captures of proxied exchanges.
+//!
+//! Bodies stream through the proxy untouched and are spooled to temporary files as they
+//! pass. When the last handle to a capture is dropped (both bodies finished or were
+//! abandoned) the spools are assembled into the `format::Payload` JSON on a blocking
+//! thread and removed, so memory use doesn't grow with body size. Exchanges the
+//! configured `CaptureFilter` rejects are dropped at that point; the decision needs the
+//! response, so they are spooled like any other. Secrets are redacted from what is kept
+//! before it is written. Build scan uploads are first sent to the configured mirrors,
+//! so their captures record how that went.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use axum::body::{Bytes, HttpBody};
+use base64::Engine as _;
+use config::{CaptureFilter, CaptureFormat, Config, Exchange, REDACTED, Redaction};
+use format::har::{Entry, Har};
+use format::{BUILD_SCAN_SUFFIX, PARSE_REPORT_SUFFIX, ParseReport, Payload, Timings};
+use http_body::{Frame, SizeHint};
+use tracing::{debug, error, info, warn};
+
+use crate::mirror::{Mirrors, Upload};
+use crate::storage::{IndexEntry, Store};
+use crate::{redact, uri_path};
+
+/// Leading bytes of a build scan upload body.
+const SCAN_MAGIC: [u8; 2] = [0x28, 0xC5];
+
+/// Stand-ins serialized in place of the bodies, then replaced by the spooled contents.
+const REQUEST_BODY_MARK: &str = "\u{0}request body\u{0}";
+const RESPONSE_BODY_MARK: &str = "\u{0}response body\u{0}";
+
+/// JSON bodies larger than this are dropped rather than parsed to redact their fields.
+const MAX_REDACTED_JSON: u64 = 16 * 1024 * 1024;
+
+/// Bytes read per step when copying a spool into the capture; a multiple of 3 so base64
+/// chunks concatenate without padding.
+const COPY_CHUNK: usize = 3 * 16 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Side {
+    Request,
+    Response,
+}
+
+/// A body written to a temporary file as it streams past.
+#[derive(Debug)]
+struct Spool {
+    path: PathBuf,
+    file: BufWriter<File>,
+    len: u64,
+    head: Vec<u8>,
+    utf8: bool,
+    /// An incomplete UTF-8 sequence at the end of the last chunk.
+    pending: Vec<u8>,
+}
+
+impl Spool {
+    fn new(path: PathBuf, file: File) -> Self {
+        Self {
+            path,
+            file: BufWriter::new(file),
+            len: 0,
+            head: Vec::with_capacity(SCAN_MAGIC.len()),
+            utf8: true,
+            pending: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let missing = SCAN_MAGIC.len() - self.head.len();
+        self.head
+            .extend_from_slice(&chunk[..missing.min(chunk.len())]);
+        if self.utf8 {
+            self.utf8 = continue_utf8(&mut self.pending, chunk);
+        }
+        self.file.write_all(chunk)?;
+        self.len += chunk.len() as u64;
+        Ok(())
+    }
+
+    fn is_utf8(&self) -> bool {
+        self.utf8 && self.pending.is_empty()
+    }
+
+    fn is_json(&self, content_type: Option<&str>) -> bool {
+        self.is_utf8() && looks_like_json(content_type, &self.head)
+    }
+
+    /// Replaces everything spooled so far with `contents`.
+    fn replace(&mut self, contents: &[u8]) -> io::Result<()> {
+        self.file.flush()?;
+        let file = self.file.get_mut();
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        self.len = 0;
+        self.head.clear();
+        self.utf8 = true;
+        self.pending.clear();
+        self.write(contents)
+    }
+}
+
+/// Whether a UTF-8 body starting with `head` is JSON, going by its type or first byte.
+fn looks_like_json(content_type: Option<&str>, head: &[u8]) -> bool {
+    content_type.is_some_and(|t| t.to_ascii_lowercase().contains("json"))
+        || matches!(head.first(), Some(b'{' | b'['))
+}
+
+/// Validates `chunk` as the continuation of a UTF-8 stream whose last chunk ended in the
+/// incomplete sequence `pending`, leaving any new incomplete tail there.
+fn continue_utf8(pending: &mut Vec<u8>, chunk: &[u8]) -> bool {
+    pending.extend_from_slice(chunk);
+    match std::str::from_utf8(pending) {
+        Ok(_) => {
+            pending.clear();
+            true
+        }
+        Err(e) if e.error_len().is_none() => {
+            pending.drain(..e.valid_up_to());
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// When each phase of the upstream exchange ended.
+#[derive(Debug)]
+struct Timeline {
+    started: Instant,
+    /// RFC 3339 time of `started`.
+    started_at: String,
+    /// The upstream connection first asked for request body data.
+    request_polled: Option<Instant>,
+    request_sent: Option<Instant>,
+    /// Response headers arrived, or the exchange failed.
+    responded: Option<Instant>,
+    response_received: Option<Instant>,
+}
+
+impl Timeline {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            request_polled: None,
+            request_sent: None,
+            responded: None,
+            response_received: None,
+        }
+    }
+
+    /// Phase durations as of `finished`, which stands in for ends never observed.
+    fn timings(&self, finished: Instant) -> Timings {
+        let ms = |from: Instant, to: Instant| {
+            (to.saturating_duration_since(from).as_secs_f64() * 1e6).round() / 1e3
+        };
+        let responded = self.responded.unwrap_or(finished);
+        Timings {
+            started: self.started_at.clone(),
+            connect: self.request_polled.map(|polled| ms(self.started, polled)),
+            send: self
+                .request_polled
+                .map(|polled| ms(polled, self.request_sent.unwrap_or(responded))),
+            wait: ms(
+                self.request_sent
+                    .or(self.request_polled)
+                    .unwrap_or(self.started),
+                responded,
+            ),
+            receive: self
+                .responded
+                .map(|responded| ms(responded, self.response_received.unwrap_or(finished))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Capture {
+    /// Directory and stem of the capture's files.
+    stem: PathBuf,
+    payload: Mutex<Payload>,
+    timeline: Mutex<Timeline>,
+    request: Mutex<Option<Spool>>,
+    response: Mutex<Option<Spool>>,
+    filter: CaptureFilter,
+    redaction: Redaction,
+    store: Arc<Store>,
+    mirrors: Arc<Mirrors>,
+    metrics: Arc<metrics::Registry>,
+}
+
+impl Capture {
+    /// Starts capturing the exchange described by `payload` (bodies and response are
+    /// filled in later) into the payload directory. If the spools can't be created the
+    /// exchange is still proxied, just not saved.
+    pub async fn start(
+        config: &Config,
+        store: &Arc<Store>,
+        mirrors: &Arc<Mirrors>,
+        payload: Payload,
+        metrics: Arc<metrics::Registry>,
+    ) -> Arc<Self> {
+        let dir = store.dir(&payload.timestamp);
+        let stem = format!("{}-{}", payload.timestamp, payload.request_id);
+        let (request, response) = match open_spools(&dir, &stem).await {
+            Ok((request, response)) => (Some(request), Some(response)),
+            Err(e) => {
+                error!("Failed to create capture files in {:?}: {}", dir, e);
+                (None, None)
+            }
+        };
+        Arc::new(Self {
+            stem: dir.join(stem),
+            payload: Mutex::new(payload),
+            timeline: Mutex::new(Timeline::new()),
+            request: Mutex::new(request),
+            response: Mutex::new(response),
+            filter: config.capture.clone(),
+            redaction: config.redaction.clone(),
+            store: store.clone(),
+            mirrors: mirrors.clone(),
+            metrics,
+        })
+    }
+
+    /// Records the upstream response head; its body is captured through [`Capture::tee`].
+    pub fn respond(&self, status: u16, headers: Vec<(String, String)>) {
+        let mut payload = self.payload.lock().unwrap_or_else(|e| e.into_inner());
+        payload.response.status = Some(status);
+        payload.response.headers = Some(headers);
+        self.mark(|timeline| &mut timeline.responded);
+    }
+
+    /// Records that the upstream couldn't be reached.
+    pub fn fail(&self, error: String) {
+        let mut payload = self.payload.lock().unwrap_or_else(|e| e.into_inner());
+        payload.response.error = Some(error);
+        self.mark(|timeline| &mut timeline.responded);
+    }
+
+    /// Wraps `body` so every data frame passing through is also spooled to `side`.
+    pub fn tee<B>(self: &Arc<Self>, side: Side, body: B) -> TeeBody<B> {
+        TeeBody {
+            inner: Mutex::new(body),
+            capture: self.clone(),
+            side,
+            polled: false,
+            ended: false,
+        }
+    }
+
+    /// Sets the time a phase ended, unless it already has one.
+    fn mark(&self, phase: impl FnOnce(&mut Timeline) -> &mut Option<Instant>) {
+        let mut timeline = self.timeline.lock().unwrap_or_else(|e| e.into_inner());
+        phase(&mut timeline).get_or_insert_with(Instant::now);
+    }
+
+    fn write(&self, side: Side, data: &[u8]) {
+        let spool = match side {
+            Side::Request => &self.request,
+            Side::Response => &self.response,
+        };
+        let mut spool = spool.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(s) = spool.as_mut()
+            && let Err(e) = s.write(data)
+        {
+            error!("Failed to spool {:?} body to {:?}: {}", side, s.path, e);
+            let _ = std::fs::remove_file(&s.path);
+            *spool = None;
+        }
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        let mut payload = std::mem::replace(
+            self.payload.get_mut().unwrap_or_else(|e| e.into_inner()),
+            empty_payload(),
+        );
+        let timeline = self.timeline.get_mut().unwrap_or_else(|e| e.into_inner());
+        payload.timings = Some(timeline.timings(Instant::now()));
+        // Only a body the upstream took in full can be passed on.
+        let request_complete = timeline.request_sent.is_some();
+        let mut finished = Finished {
+            stem: std::mem::take(&mut self.stem),
+            payload,
+            request: self
+                .request
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(),
+            response: self
+                .response
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(),
+            filter: std::mem::take(&mut self.filter),
+            redaction: std::mem::replace(&mut self.redaction, Redaction::none()),
+            store: self.store.clone(),
+            metrics: self.metrics.clone(),
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return finished.save();
+        };
+        match finished.mirror_upload(&self.mirrors, request_complete) {
+            Some(body_path) => {
+                let mirrors = self.mirrors.clone();
+                handle.spawn(async move {
+                    finished.mirror(&mirrors, body_path).await;
+                    tokio::task::spawn_blocking(move || finished.save());
+                });
+            }
+            None => {
+                handle.spawn_blocking(move || finished.save());
+            }
+        }
+    }
+}
+
+async fn open_spools(dir: &Path, stem: &str) -> io::Result<(Spool, Spool)> {
+    tokio::fs::create_dir_all(dir).await?;
+    let request = open_spool(dir.join(format!("{stem}.request.part"))).await?;
+    let response = open_spool(dir.join(format!("{stem}.response.part"))).await?;
+    Ok((request, response))
+}
+
+async fn open_spool(path: PathBuf) -> io::Result<Spool> {
+    let file = tokio::fs::File::create(&path).await?.into_std().await;
+    Ok(Spool::new(path, file))
+}
+
+fn empty_payload() -> Payload {
+    Payload {
+        request_id: String::new(),
+        timestamp: String::new(),
+        request: format::RequestData {
+            method: String::new(),
+            uri: String::new(),
+            headers: Vec::new(),
+            body: serde_json::Value::Null,
+        },
+        response: format::ResponseData {
+            status: None,
+            headers: None,
+            body: None,
+            error: None,
+        },
+        timings: None,
+        mirrors: Vec::new(),
+    }
+}
+
+/// Everything needed to write a capture once no body is streaming anymore.
+struct Finished {
+    stem: PathBuf,
+    payload: Payload,
+    request: Option<Spool>,
+    response: Option<Spool>,
+    filter: CaptureFilter,
+    redaction: Redaction,
+    store: Arc<Store>,
+    metrics: Arc<metrics::Registry>,
+}
+
+impl Finished {
+    /// Whether this is a build scan upload to mirror, and if so the spooled body to send.
+    fn mirror_upload(&mut self, mirrors: &Mirrors, request_complete: bool) -> Option<PathBuf> {
+        if mirrors.is_empty() || !self.is_upload() {
+            return None;
+        }
+        let request = self.request.as_mut()?;
+        if !request_complete {
+            warn!(
+                "Not mirroring {}: the upload didn't reach the upstream in full",
+                self.payload.request.uri
+            );
+            return None;
+        }
+        if let Err(e) = request.file.flush() {
+            error!("Not mirroring {}: {}", self.payload.request.uri, e);
+            return None;
+        }
+        Some(request.path.clone())
+    }
+
+    async fn mirror(&mut self, mirrors: &Mirrors, body_path: PathBuf) {
+        let body = match tokio::fs::read(&body_path).await {
+            Ok(body) => body,
+            Err(e) => {
+                error!(
+                    "Not mirroring {}: failed to read {:?}: {}",
+                    self.payload.request.uri, body_path, e
+                );
+                return;
+            }
+        };
+        let request = &self.payload.request;
+        self.payload.mirrors = mirrors
+            .deliver(Upload {
+                method: request.method.clone(),
+                path_and_query: request.uri.clone(),
+                headers: request.headers.clone(),
+                body: body.into(),
+            })
+            .await;
+    }
+
+    fn is_upload(&self) -> bool {
+        is_scan_upload(&self.payload.request.method, &self.payload.request.uri)
+            || self.request.as_ref().is_some_and(|r| r.head == SCAN_MAGIC)
+    }
+
+    fn save(mut self) {
+        let Some(mut request) = self.request.take() else {
+            return;
+        };
+        let mut response = self.response.take();
+        // A failed exchange has no response body to speak of.
+        if self.payload.response.status.is_none()
+            && let Some(spool) = response.take()
+        {
+            let _ = std::fs::remove_file(&spool.path);
+        }
+
+        let upload = is_scan_upload(&self.payload.request.method, &self.payload.request.uri);
+        let parse = upload || request.head == SCAN_MAGIC;
+        if !self.keep() {
+            debug!(
+                "Not saving {} {} (capture filter)",
+                self.payload.request.method, self.payload.request.uri
+            );
+            // Uploads still count towards the build metrics.
+            if parse {
+                self.parse_upload(&request.path, false);
+            }
+            remove_spools(request, response);
+            return;
+        }
+
+        let path = uri_path(&self.payload.request.uri).to_string();
+        let body_redacted = self.redaction.body(&path);
+        redact::payload(&self.redaction, &mut self.payload);
+        // Parsed from the original body, but not saved if that is to be dropped.
+        if parse {
+            self.parse_upload(&request.path, upload && !body_redacted);
+        }
+        let redacted = self
+            .redact_body(&mut request, Side::Request, &path)
+            .and_then(|()| match response.as_mut() {
+                Some(spool) => self.redact_body(spool, Side::Response, &path),
+                None => Ok(()),
+            });
+        let path = self.file(self.store.capture_suffix());
+        if let Err(e) = redacted {
+            error!("Not saving {:?}, failed to redact its bodies: {}", path, e);
+            remove_spools(request, response);
+            return;
+        }
+
+        self.payload.request.body = body_mark(REQUEST_BODY_MARK, &request);
+        if let Some(response) = response.as_ref() {
+            self.payload.response.body = Some(body_mark(RESPONSE_BODY_MARK, response));
+        }
+        let mut bodies = vec![(REQUEST_BODY_MARK, &mut request)];
+        if let Some(response) = response.as_mut() {
+            bodies.push((RESPONSE_BODY_MARK, response));
+        }
+        match self.write_capture(&path, &mut bodies) {
+            Ok(()) => info!("Saved payload to: {:?}", path),
+            Err(e) => error!("Failed to write payload {:?}: {}", path, e),
+        }
+        remove_spools(request, response);
+    }
+
+    /// `<stem><suffix>` in the capture's directory, compressed as configured.
+    fn file(&self, suffix: &str) -> PathBuf {
+        let stem = self
+            .stem
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        self.stem.with_file_name(self.store.file_name(stem, suffix))
+    }
+
+    fn write_capture(&self, path: &Path, bodies: &mut [(&str, &mut Spool)]) -> io::Result<()> {
+        let mut out = self.store.create(path)?;
+        match self.store.format() {
+            CaptureFormat::Payload => write_spliced(&mut out, &self.payload, bodies)?,
+            CaptureFormat::Har => {
+                let mut entry = Entry::from_payload(&self.payload);
+                // Sizes of the spooled bodies, not of the marks standing in for them.
+                for (mark, spool) in bodies.iter() {
+                    let len = spool.len as i64;
+                    if *mark == REQUEST_BODY_MARK {
+                        entry.request.body_size = len;
+                    } else {
+                        entry.response.content.size = len;
+                        entry.response.body_size = len;
+                    }
+                }
+                write_spliced(&mut out, &Har::new(vec![entry]), bodies)?
+            }
+        }
+        out.finish()?;
+
+        let payload = &self.payload;
+        self.store.record(&IndexEntry {
+            timestamp: &payload.timestamp,
+            request_id: &payload.request_id,
+            method: &payload.request.method,
+            status: payload.response.status,
+            size: std::fs::metadata(path)?.len(),
+            file: path,
+            uri: &payload.request.uri,
+        })
+    }
+
+    fn write_json(&self, path: &Path, value: &impl serde::Serialize) -> io::Result<()> {
+        let mut out = self.store.create(path)?;
+        serde_json::to_writer_pretty(&mut out, value)?;
+        out.finish()
+    }
+
+    /// Drops the body if its path is redacted entirely, else blanks redacted JSON fields,
+    /// looking through a gzip or deflate `Content-Encoding`. JSON that is too large or
+    /// fails to parse is dropped too, since it can't be checked, and so is a body in an
+    /// encoding that can't be undone.
+    fn redact_body(&self, spool: &mut Spool, side: Side, path: &str) -> io::Result<()> {
+        if self.redaction.body(path) {
+            return spool.replace(REDACTED.as_bytes());
+        }
+        let headers = match side {
+            Side::Request => Some(self.payload.request.headers.as_slice()),
+            Side::Response => self.payload.response.headers.as_deref(),
+        };
+        let content_type = headers.and_then(content_type);
+        let encoding = headers.and_then(content_encoding);
+        if self.redaction.body_fields.is_empty()
+            || (encoding.is_none() && !spool.is_json(content_type))
+        {
+            return Ok(());
+        }
+        if spool.len > MAX_REDACTED_JSON {
+            warn!(
+                "Dropping {:?} body of {} bytes in {:?}: too large to redact",
+                side,
+                spool.len,
+                self.file(self.store.capture_suffix())
+            );
+            return spool.replace(REDACTED.as_bytes());
+        }
+
+        spool.file.flush()?;
+        let mut body = std::fs::read(&spool.path)?;
+        if let Some(encoding) = &encoding {
+            body = match decode(encoding, &body) {
+                Ok(decoded) if decoded.len() as u64 > MAX_REDACTED_JSON => {
+                    warn!(
+                        "Dropping {:?} body in {:?}: too large to redact once decoded",
+                        side,
+                        self.file(self.store.capture_suffix())
+                    );
+                    return spool.replace(REDACTED.as_bytes());
+                }
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    warn!(
+                        "Dropping {:?} body in {:?}: its {} encoding can't be undone, so it can't be redacted: {}",
+                        side,
+                        self.file(self.store.capture_suffix()),
+                        encoding,
+                        e
+                    );
+                    return spool.replace(REDACTED.as_bytes());
+                }
+            };
+            if std::str::from_utf8(&body).is_err() || !looks_like_json(content_type, &body) {
+                return Ok(());
+            }
+        }
+        let mut body = match serde_json::from_slice(&body) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(
+                    "Dropping {:?} body in {:?}: not valid JSON, so it can't be redacted: {}",
+                    side,
+                    self.file(self.store.capture_suffix()),
+                    e
+                );
+                return spool.replace(REDACTED.as_bytes());
+            }
+        };
+        if redact::json(&self.redaction, &mut body) {
+            let json = serde_json::to_vec(&body)?;
+            match &encoding {
+                Some(encoding) => spool.replace(&encode(encoding, &json)?)?,
+                None => spool.replace(&json)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the capture filter wants this exchange saved.
+    fn keep(&self) -> bool {
+        let payload = &self.payload;
+        let exchange = Exchange {
+            method: &payload.request.method,
+            path: uri_path(&payload.request.uri),
+            status: payload.response.status,
+            request_content_type: content_type(&payload.request.headers),
+            response_content_type: payload.response.headers.as_deref().and_then(content_type),
+        };
+        self.filter.matches(&exchange) && self.filter.sampled(&payload.request_id)
+    }
+
+    /// Parses a spooled build scan upload and records what it says. Uploads to the publish
+    /// endpoint also get the parsed `BuildScanPayload`, or a `ParseReport` on failure,
+    /// saved next to the capture.
+    fn parse_upload(&self, spool: &Path, save: bool) {
+        let body = match std::fs::read(spool) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to read spooled upload {:?}: {}", spool, e);
+                return;
+            }
+        };
+        let parsed = lib::parse(&body);
+        match &parsed {
+            Ok(scan) => self.metrics.record_scan(scan),
+            Err(e) => {
+                warn!("Failed to parse build scan upload: {}", e);
+                self.metrics.record_parse_failure(e);
+            }
+        }
+        if !save {
+            return;
+        }
+
+        let (path, written) = match parsed {
+            Ok(scan) => {
+                let path = self.file(BUILD_SCAN_SUFFIX);
+                (path.clone(), self.write_json(&path, &scan))
+            }
+            Err(e) => {
+                let path = self.file(PARSE_REPORT_SUFFIX);
+                let report = ParseReport {
+                    request_id: self.payload.request_id.clone(),
+                    timestamp: self.payload.timestamp.clone(),
+                    uri: self.payload.request.uri.clone(),
+                    kind: e.kind().to_string(),
+                    error: e.to_string(),
+                };
+                (path.clone(), self.write_json(&path, &report))
+            }
+        };
+        match written {
+            Ok(()) => info!("Saved parsed upload to: {:?}", path),
+            Err(e) => error!("Failed to write {:?}: {}", path, e),
+        }
+    }
+}
+
+fn remove_spools(request: Spool, response: Option<Spool>) {
+    for spool in std::iter::once(request).chain(response) {
+        let _ = std::fs::remove_file(&spool.path);
+    }
+}
+
+/// `POST /scans/publish/gradle/<version>/upload`, where the Gradle plugin sends scans.
+pub fn is_scan_upload(method: &str, uri: &str) -> bool {
+    publish_endpoint(method, uri) == Some("upload")
+}
+
+/// `POST /scans/publish/gradle/<version>/token`, where the Gradle plugin asks for the
+/// token it uploads with.
+pub fn is_token_request(method: &str, uri: &str) -> bool {
+    publish_endpoint(method, uri) == Some("token")
+}
+
+fn publish_endpoint<'a>(method: &str, uri: &'a str) -> Option<&'a str> {
+    let segments: Vec<&str> = uri_path(uri).trim_start_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["scans", "publish", "gradle", version, endpoint]
+            if method.eq_ignore_ascii_case("POST") && !version.is_empty() =>
+        {
+            Some(endpoint)
+        }
+        _ => None,
+    }
+}
+
+fn content_type(headers: &[(String, String)]) -> Option<&str> {
+    headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str())
+}
+
+/// The `Content-Encoding` of a body, lowercased, unless it's `identity`.
+fn content_encoding(headers: &[(String, String)]) -> Option<String> {
+    headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-encoding"))
+        .map(|(_, value)| value.trim().to_ascii_lowercase())
+        .filter(|encoding| !encoding.is_empty() && encoding != "identity")
+}
+
+/// Undoes a gzip or deflate `Content-Encoding`, reading at most one byte past
+/// `MAX_REDACTED_JSON` so a compression bomb stays cheap.
+fn decode(encoding: &str, body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let limit = MAX_REDACTED_JSON + 1;
+    match encoding {
+        "gzip" | "x-gzip" => flate2::read::MultiGzDecoder::new(body)
+            .take(limit)
+            .read_to_end(&mut decoded)?,
+        "deflate" => flate2::read::ZlibDecoder::new(body)
+            .take(limit)
+            .read_to_end(&mut decoded)?,
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported content encoding {other:?}"),
+            ));
+        }
+    };
+    Ok(decoded)
+}
+
+/// Encodes a redacted body the way [`decode`] found it.
+fn encode(encoding: &str, body: &[u8]) -> io::Result<Vec<u8>> {
+    let level = flate2::Compression::default();
+    match encoding {
+        "deflate" => {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), level);
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+        _ => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// Where a spooled body goes in the payload: the mark itself for UTF-8 bodies,
+/// `{"base64": mark}` otherwise, as `proxy` always saved them. Empty bodies are left
+/// empty.
+fn body_mark(mark: &str, spool: &Spool) -> serde_json::Value {
+    if spool.len == 0 {
+        serde_json::json!("")
+    } else if spool.is_utf8() {
+        serde_json::json!(mark)
+    } else {
+        serde_json::json!({ "base64": mark })
+    }
+}
+
+/// Writes `value` as pretty JSON with each body mark replaced by its spool's contents, as
+/// a string of text or base64 (see [`body_mark`]).
+fn write_spliced(
+    out: &mut impl Write,
+    value: &impl serde::Serialize,
+    bodies: &mut [(&str, &mut Spool)],
+) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    let mut rest = json.as_str();
+    for (mark, spool) in bodies.iter_mut() {
+        let needle = serde_json::to_string(mark)?;
+        let Some(at) = rest.find(&needle) else {
+            continue;
+        };
+        out.write_all(&rest.as_bytes()[..at])?;
+        write_body(out, spool)?;
+        rest = &rest[at + needle.len()..];
+    }
+    out.write_all(rest.as_bytes())
+}
+
+fn write_body(out: &mut impl Write, spool: &mut Spool) -> io::Result<()> {
+    spool.file.flush()?;
+    let mut file = File::open(&spool.path)?;
+    let mut buf = vec![0; COPY_CHUNK];
+
+    if spool.is_utf8() {
+        out.write_all(b"\"")?;
+        let mut carried = 0;
+        loop {
+            let read = file.read(&mut buf[carried..])?;
+            let filled = carried + read;
+            if read == 0 {
+                break;
+            }
+            // Chunk boundaries may split a character; carry its start to the next read.
+            let valid = match std::str::from_utf8(&buf[..filled]) {
+                Ok(s) => s,
+                Err(e) => std::str::from_utf8(&buf[..e.valid_up_to()]).expect("valid prefix"),
+            };
+            let quoted = serde_json::to_string(valid)?;
+            out.write_all(&quoted.as_bytes()[1..quoted.len() - 1])?;
+            let used = valid.len();
+            buf.copy_within(used..filled, 0);
+            carried = filled - used;
+        }
+        out.write_all(b"\"")
+    } else {
+        out.write_all(b"\"")?;
+        loop {
+            let filled = fill(&mut file, &mut buf)?;
+            if filled == 0 {
+                break;
+            }
+            let encoded = base64::engine::general_purpose::STANDARD.encode(&buf[..filled]);
+            out.write_all(encoded.as_bytes())?;
+            if filled < buf.len() {
+                break;
+            }
+        }
+        out.write_all(b"\"")
+    }
+}
+
+/// Reads until `buf` is full or the input ends.
+fn fill(input: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// A body that spools its data frames into a [`Capture`] as they are polled.
+#[derive(Debug)]
+pub struct TeeBody<B> {
+    /// Never contended; the mutex only makes the body `Sync`, which `reqwest` requires.
+    inner: Mutex<B>,
+    capture: Arc<Capture>,
+    side: Side,
+    polled: bool,
+    ended: bool,
+}
+
+impl<B> HttpBody for TeeBody<B>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, B::Error>>> {
+        let this = self.get_mut();
+        if !this.polled {
+            this.polled = true;
+            if let Side::Request = this.side {
+                this.capture.mark(|timeline| &mut timeline.request_polled);
+            }
+        }
+        let inner = this.inner.get_mut().unwrap_or_else(|e| e.into_inner());
+        let mut inner = Pin::new(inner);
+        let frame = inner.as_mut().poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &frame
+            && let Some(data) = frame.data_ref()
+        {
+            this.capture.write(this.side, data);
+        }
+        // Callers stop polling once the body says it's done, so that counts as the end.
+        let ended = matches!(frame, Poll::Ready(None))
+            || (matches!(frame, Poll::Ready(Some(Ok(_)))) && inner.is_end_stream());
+        if ended && !this.ended {
+            this.ended = true;
+            this.capture.mark(|timeline| match this.side {
+                Side::Request => &mut timeline.request_sent,
+                Side::Response => &mut timeline.response_received,
+            });
+        }
+        frame
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner
+            .lock()
+            .map(|inner| inner.is_end_stream())
+            .unwrap_or(false)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner
+            .lock()
+            .map(|inner| inner.size_hint())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use format::{RequestData, ResponseData};
+
+    fn spool(dir: &Path, name: &str, chunks: &[&[u8]]) -> Spool {
+        let path = dir.join(name);
+        let mut spool = Spool::new(path.clone(), File::create(&path).unwrap());
+        for chunk in chunks {
+            spool.write(chunk).unwrap();
+        }
+        spool
+    }
+
+    #[test]
+    fn test_is_scan_upload() {
+        assert!(is_scan_upload("POST", "/scans/publish/gradle/4.3.2/upload"));
+        assert!(is_scan_upload(
+            "post",
+            "/scans/publish/gradle/4.3.2/upload?x=1"
+        ));
+        assert!(!is_scan_upload("POST", "/scans/publish/gradle/4.3.2/token"));
+        assert!(!is_scan_upload("GET", "/scans/publish/gradle/4.3.2/upload"));
+        assert!(!is_scan_upload("POST", "/scans/publish/gradle//upload"));
+        assert!(is_token_request(
+            "POST",
+            "/scans/publish/gradle/4.3.2/token"
+        ));
+        assert!(!is_token_request(
+            "GET",
+            "/scans/publish/gradle/4.3.2/token"
+        ));
+    }
+
+    #[test]
+    fn test_redact_body() {
+        let dir = test_dir("redact");
+        let finished = |headers: Vec<(String, String)>| Finished {
+            stem: dir.join("capture"),
+            payload: Payload {
+                request: RequestData {
+                    headers: headers.clone(),
+                    ..empty_payload().request
+                },
+                response: ResponseData {
+                    headers: Some(headers),
+                    ..empty_payload().response
+                },
+                ..empty_payload()
+            },
+            request: None,
+            response: None,
+            filter: CaptureFilter::default(),
+            redaction: Redaction::default(),
+            store: Arc::new(Store::new(dir.clone(), Default::default())),
+            metrics: Arc::new(metrics::Registry::new()),
+        };
+        let json = || vec![("Content-Type".into(), "application/json".into())];
+        let read = |spool: &mut Spool| {
+            spool.file.flush().unwrap();
+            String::from_utf8(std::fs::read(&spool.path).unwrap()).unwrap()
+        };
+
+        let mut body = spool(dir.as_path(), "json", &[br#" {"token": "t", "n": 1}"#]);
+        finished(json())
+            .redact_body(&mut body, Side::Request, "/scans")
+            .unwrap();
+        let redacted = r#"{"n":1,"token":"[REDACTED]"}"#;
+        assert_eq!(read(&mut body), redacted);
+        assert_eq!(body.len, redacted.len() as u64);
+
+        // JSON that can't be checked for secrets isn't kept.
+        let mut body = spool(dir.as_path(), "malformed", &[br#"{"token": "t", "#]);
+        finished(json())
+            .redact_body(&mut body, Side::Response, "/scans")
+            .unwrap();
+        assert_eq!(read(&mut body), REDACTED);
+
+        let filler = vec![b' '; MAX_REDACTED_JSON as usize];
+        let mut body = spool(
+            dir.as_path(),
+            "oversized",
+            &[br#"{"token": "t"}"#, filler.as_slice()],
+        );
+        finished(json())
+            .redact_body(&mut body, Side::Response, "/scans")
+            .unwrap();
+        assert_eq!(read(&mut body), REDACTED);
+        assert_eq!(body.len, REDACTED.len() as u64);
+
+        let text = r#"token: {"token": "t"}"#;
+        let mut body = spool(dir.as_path(), "text", &[text.as_bytes()]);
+        finished(Vec::new())
+            .redact_body(&mut body, Side::Request, "/scans")
+            .unwrap();
+        assert_eq!(read(&mut body), text);
+
+        // The token exchange as the upstream answers it: gzip encoded.
+        let gzip = |body: &[u8]| encode("gzip", body).unwrap();
+        let mut gzip_json = json();
+        gzip_json.push(("Content-Encoding".into(), "gzip".into()));
+        let grant = br#"{"id":"s","scanUploadToken":"t"}"#;
+        let mut body = spool(dir.as_path(), "gzip", &[gzip(grant).as_slice()]);
+        finished(gzip_json.clone())
+            .redact_body(
+                &mut body,
+                Side::Response,
+                "/scans/publish/gradle/4.3.2/token",
+            )
+            .unwrap();
+        body.file.flush().unwrap();
+        let redacted = decode("gzip", &std::fs::read(&body.path).unwrap()).unwrap();
+        assert_eq!(redacted, br#"{"id":"s","scanUploadToken":"[REDACTED]"}"#);
+
+        let mut body = spool(dir.as_path(), "corrupt", &[b"\x1f\x8bnot gzip"]);
+        finished(gzip_json)
+            .redact_body(&mut body, Side::Response, "/scans")
+            .unwrap();
+        assert_eq!(read(&mut body), REDACTED);
+
+        let mut brotli = json();
+        brotli.push(("Content-Encoding".into(), "br".into()));
+        let mut body = spool(dir.as_path(), "brotli", &[b"\x0b\x02\x80"]);
+        finished(brotli)
+            .redact_body(&mut body, Side::Response, "/scans")
+            .unwrap();
+        assert_eq!(read(&mut body), REDACTED);
+
+        let mut body = spool(dir.as_path(), "token", &[b"eyJhbGciOi"]);
+        finished(Vec::new())
+            .redact_body(&mut body, Side::Response, "/api/auth/token")
+            .unwrap();
+        assert_eq!(read(&mut body), REDACTED);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_utf8_across_chunks() {
+        let mut pending = Vec::new();
+        let euro = "€".as_bytes();
+        assert!(continue_utf8(&mut pending, &[b'a', euro[0]]));
+        assert_eq!(pending, &euro[..1]);
+        assert!(continue_utf8(&mut pending, &euro[1..]));
+        assert!(pending.is_empty());
+        assert!(!continue_utf8(&mut pending, &[0x28, 0xC5, 0xFF]));
+    }
+
+    #[test]
+    fn test_timeline_timings() {
+        let mut timeline = Timeline::new();
+        let at = |ms: u64| timeline.started + std::time::Duration::from_millis(ms);
+        let (polled, sent, responded, finished) = (at(20), at(35), at(135), at(160));
+        timeline.request_polled = Some(polled);
+        timeline.request_sent = Some(sent);
+        timeline.responded = Some(responded);
+        let timings = timeline.timings(finished);
+        assert_eq!(timings.connect, Some(20.0));
+        assert_eq!(timings.send, Some(15.0));
+        assert_eq!(timings.wait, 100.0);
+        assert_eq!(timings.receive, Some(25.0));
+
+        // The upstream was never reached: everything is waiting.
+        let failed = Timeline::new();
+        let timings = failed.timings(failed.started + std::time::Duration::from_millis(5));
+        assert_eq!(
+            (timings.connect, timings.send, timings.receive),
+            (None, None, None)
+        );
+        assert_eq!(timings.wait, 5.0);
+    }
+
+    #[test]
+    fn test_write_payload_streams_bodies() {
+        let dir = test_dir("write");
+        let text = "é".repeat(COPY_CHUNK);
+        let mut request = spool(dir.as_path(), "request", &[text.as_bytes()]);
+        let binary: Vec<u8> = (0..COPY_CHUNK * 2 + 7).map(|i| (i % 251) as u8).collect();
+        let mut response = spool(dir.as_path(), "response", &[&binary[..5], &binary[5..]]);
+        assert_eq!(request.head, "é".as_bytes());
+        assert!(!response.is_utf8());
+
+        let payload = Payload {
+            request_id: "id".into(),
+            timestamp: "ts".into(),
+            request: RequestData {
+                method: "POST".into(),
+                uri: "/upload".into(),
+                headers: vec![("content-type".into(), "text/plain".into())],
+                body: body_mark(REQUEST_BODY_MARK, &request),
+            },
+            response: ResponseData {
+                status: Some(200),
+                headers: Some(Vec::new()),
+                body: Some(body_mark(RESPONSE_BODY_MARK, &response)),
+                error: None,
+            },
+            timings: None,
+            mirrors: Vec::new(),
+        };
+        let mut out = Vec::new();
+        write_spliced(
+            &mut out,
+            &payload,
+            &mut [
+                (REQUEST_BODY_MARK, &mut request),
+                (RESPONSE_BODY_MARK, &mut response),
+            ],
+        )
+        .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let saved: Payload = serde_json::from_slice(&out).unwrap();
+        assert_eq!(saved.request.body, serde_json::json!(text));
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&binary);
+        assert_eq!(
+            saved.response.body,
+            Some(serde_json::json!({ "base64": encoded }))
+        );
+    }
+}
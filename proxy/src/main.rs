@@ -1,21 +1,40 @@
-use axum::{Router, body::Body, extract::Request, extract::State, response::Response};
-use base64::Engine as _;
-use chrono::Utc;
-use std::net::SocketAddr;
+mod capture;
+mod mirror;
+mod queue;
+mod redact;
+mod retry;
+mod storage;
+#[cfg(test)]
+mod test_support;
+
+use axum::body::Bytes;
+use axum::{
+    Router, body::Body, extract::Query, extract::Request, extract::State, response::Response,
+    routing::get,
+};
+use chrono::{SecondsFormat, Utc};
+use http_body_util::BodyExt;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::signal;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
 
-use config::Config;
+use capture::{Capture, Side};
+use config::{Config, MatchStrategy};
 use format::{Payload, RequestData, ResponseData};
-
-const MAX_BODY_SIZE: usize = 10 * 1024 * 1024; // 10 MB
+use queue::{QueuedUpload, TokenRequest};
 
 #[derive(Debug, Clone)]
 struct AppState {
     config: Config,
     client: reqwest::Client,
+    metrics: Arc<metrics::Registry>,
+    store: Arc<storage::Store>,
+    mirrors: Arc<mirror::Mirrors>,
+    queue: Option<Arc<queue::Queue>>,
+    replay: Option<Arc<replay::Replay>>,
 }
 
 #[tokio::main]
@@ -25,36 +44,142 @@ async fn main() {
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
         )
         .init();
-    let config = Config::from_env();
-    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let addr = config.listen;
 
     let client = reqwest::Client::builder()
         .no_gzip()
         .no_brotli()
         .no_deflate()
-        .connect_timeout(std::time::Duration::from_secs(30))
-        .timeout(std::time::Duration::from_secs(120))
+        .connect_timeout(config.connect_timeout)
+        .timeout(config.request_timeout)
         .build()
         .expect("Failed to create HTTP client");
 
+    let replay = config.replay.as_ref().map(|replay_config| {
+        match replay::Replay::load(&replay_config.dir, replay_config.strategy) {
+            Ok(replay) => {
+                info!(
+                    "Replaying {} recorded requests from {:?} ({:?} matching{}, {} files skipped)",
+                    replay.len(),
+                    replay_config.dir,
+                    replay_config.strategy,
+                    if replay_config.strict { ", strict" } else { "" },
+                    replay.skipped
+                );
+                Arc::new(replay)
+            }
+            Err(e) => {
+                error!(
+                    "Failed to load recorded payloads from {:?}: {}",
+                    replay_config.dir, e
+                );
+                std::process::exit(1);
+            }
+        }
+    });
+
+    let store = Arc::new(storage::Store::new(
+        config.payload_dir.clone(),
+        config.storage.clone(),
+    ));
+    store.spawn_retention();
+    let mirrors = Arc::new(mirror::Mirrors::new(
+        client.clone(),
+        config.mirrors.clone(),
+        config.redaction.clone(),
+    ));
+
+    let queue = config.queue.as_ref().map(|queue_config| {
+        match queue::Queue::open(queue_config, client.clone()) {
+            Ok(queue) => {
+                let queue = Arc::new(queue);
+                queue.spawn_delivery();
+                queue
+            }
+            Err(e) => {
+                error!(
+                    "Failed to open the upload queue in {:?}: {}",
+                    queue_config.dir, e
+                );
+                std::process::exit(1);
+            }
+        }
+    });
+
     let state = AppState {
         config: config.clone(),
         client,
+        metrics: Arc::new(metrics::Registry::new()),
+        store,
+        mirrors,
+        queue: queue.clone(),
+        replay,
     };
 
-    let app = Router::new().fallback(proxy_handler).with_state(state);
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .route("/admin/queue", get(queue_handler))
+        .route("/admin/trends", get(trends_handler))
+        .fallback(proxy_handler)
+        .with_state(state);
 
     let listener = match tokio::net::TcpListener::bind(addr).await {
         Ok(l) => l,
         Err(e) => {
-            error!("Failed to bind to port {}: {}", config.port, e);
+            error!("Failed to bind to {}: {}", addr, e);
             std::process::exit(1);
         }
     };
-    info!(
-        "Proxy server listening on http://{}, forwarding to {}",
-        addr, config.upstream_url
-    );
+    match &config.upstream_url {
+        Some(upstream_url) => info!(
+            "Proxy server listening on http://{}, forwarding to {}",
+            addr, upstream_url
+        ),
+        None if config.routes.is_empty() => {
+            info!("Proxy server listening on http://{}, replay only", addr)
+        }
+        None => info!("Proxy server listening on http://{}", addr),
+    }
+    for route in &config.routes {
+        info!("Forwarding {}* to {}", route.prefix, route.upstream_url);
+    }
+    for mirror in &config.mirrors {
+        info!(
+            "Mirroring build scan uploads to {} ({} retries)",
+            mirror.url, mirror.retries
+        );
+    }
+    if config.storage != config::Storage::default() {
+        info!(
+            "Capture storage: {:?} layout, {:?} compression, max size {:?} bytes, max age {:?}",
+            config.storage.layout,
+            config.storage.compression,
+            config.storage.max_total_size,
+            config.storage.max_age
+        );
+    }
+    if let Some(queue) = &queue {
+        info!(
+            "Queueing build scan uploads in {:?} ({} waiting), state at /admin/queue",
+            queue.dir(),
+            queue.len()
+        );
+    }
+    if !config.capture.is_default() {
+        info!(
+            "Capture filter: {} include rules, {} exclude rules, saving {}%",
+            config.capture.include.len(),
+            config.capture.exclude.len(),
+            config.capture.sample_percent
+        );
+    }
 
     axum::serve(listener, app)
         .with_graceful_shutdown(shutdown_signal())
@@ -106,7 +231,358 @@ fn is_hop_by_hop(name: &str) -> bool {
         .any(|h| h.eq_ignore_ascii_case(name))
 }
 
+async fn metrics_handler(State(state): State<AppState>) -> Response<Body> {
+    Response::builder()
+        .header("Content-Type", metrics::CONTENT_TYPE)
+        .body(Body::from(state.metrics.render()))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+async fn queue_handler(State(state): State<AppState>) -> Response<Body> {
+    let Some(queue) = &state.queue else {
+        return Response::builder()
+            .status(404)
+            .body(Body::from("Upload queueing is not enabled"))
+            .unwrap_or_else(|_| Response::new(Body::empty()));
+    };
+    let json = serde_json::to_string_pretty(&queue.state()).unwrap_or_default();
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+/// Query of `/admin/trends`, with the options and defaults of `build-scan-cli trends`.
+#[derive(Debug, serde::Deserialize)]
+#[serde(default)]
+struct TrendsQuery {
+    /// Percent slowdown that counts as a regression.
+    threshold: f64,
+    recent: usize,
+    min_runs: usize,
+    regressions_only: bool,
+}
+
+impl Default for TrendsQuery {
+    fn default() -> Self {
+        let options = trends::TrendOptions::default();
+        Self {
+            threshold: options.regression_threshold * 100.0,
+            recent: options.recent_runs,
+            min_runs: options.min_runs,
+            regressions_only: false,
+        }
+    }
+}
+
+/// Task duration trends across the parsed scans in the payload directory.
+async fn trends_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TrendsQuery>,
+) -> Response<Body> {
+    let store = state.store.clone();
+    let analyzed = tokio::task::spawn_blocking(move || {
+        let scans = store.build_scans()?;
+        let named: Vec<(String, &models::BuildScanPayload)> = scans
+            .iter()
+            .map(|(stem, payload)| (stem.clone(), payload))
+            .collect();
+        let options = trends::TrendOptions {
+            regression_threshold: query.threshold / 100.0,
+            recent_runs: query.recent,
+            min_runs: query.min_runs,
+        };
+        let mut trends = trends::analyze(&named, &options);
+        if query.regressions_only {
+            trends.retain(|t| t.regression.is_some());
+        }
+        std::io::Result::Ok(serde_json::json!({ "scans": scans.len(), "trends": trends }))
+    })
+    .await;
+    let error = match analyzed {
+        Ok(Ok(json)) => {
+            return Response::builder()
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::to_string_pretty(&json).unwrap_or_default(),
+                ))
+                .unwrap_or_else(|_| Response::new(Body::empty()));
+        }
+        Ok(Err(e)) => format!("Failed to read parsed scans: {e}"),
+        Err(e) => format!("Trend analysis failed: {e}"),
+    };
+    error!("{}", error);
+    Response::builder()
+        .status(500)
+        .body(Body::from(error))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+fn replayed_response(recorded: &replay::RecordedResponse) -> Response<Body> {
+    let mut builder = Response::builder().status(recorded.status);
+    for (name, value) in &recorded.headers {
+        if !is_hop_by_hop(name) {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+    builder
+        .body(Body::from(recorded.body.clone()))
+        .unwrap_or_else(|_| {
+            Response::builder()
+                .status(500)
+                .body(Body::from("Failed to build response"))
+                .unwrap()
+        })
+}
+
+/// Answers the request from recorded payloads, or returns the body back when it should
+/// go upstream instead.
+async fn replay_request(
+    state: &AppState,
+    replay: &replay::Replay,
+    method: &str,
+    path_and_query: &str,
+    body: Body,
+) -> Result<Response<Body>, Body> {
+    // Matching on the body needs all of it; replay serves tests, so buffering is fine.
+    let (body, body_bytes) = if replay.strategy() == MatchStrategy::Body {
+        match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(bytes) => (Body::from(bytes.clone()), bytes),
+            Err(e) if is_body_too_large(&e) => {
+                warn!("Rejected {} {}: {}", method, path_and_query, e);
+                return Ok(payload_too_large());
+            }
+            Err(e) => {
+                error!("Failed to read request body: {}", e);
+                return Ok(Response::builder()
+                    .status(400)
+                    .body(Body::from("Failed to read request body"))
+                    .unwrap_or_else(|_| Response::new(Body::from("Failed to read request body"))));
+            }
+        }
+    } else {
+        (body, Default::default())
+    };
+
+    let strict = state.config.replay.as_ref().is_some_and(|r| r.strict);
+    match replay.lookup(method, path_and_query, &body_bytes) {
+        Some(recorded) => {
+            info!(
+                "Replayed {} {} -> {}",
+                method, path_and_query, recorded.status
+            );
+            Ok(replayed_response(&recorded))
+        }
+        None if strict
+            || state
+                .config
+                .upstream_for(uri_path(path_and_query))
+                .is_none() =>
+        {
+            warn!("No recorded response for {} {}", method, path_and_query);
+            let status = if strict { 500 } else { 404 };
+            Ok(Response::builder()
+                .status(status)
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "error": "No recorded response",
+                        "method": method,
+                        "uri": path_and_query,
+                    })
+                    .to_string(),
+                ))
+                .unwrap_or_else(|_| Response::new(Body::from("No recorded response"))))
+        }
+        None => Err(body),
+    }
+}
+
+/// Set on the synthesized success answering an upload that was queued, to its queue id.
+const QUEUED_HEADER: &str = "x-proxy-queued";
+/// How the upstream acknowledges an upload it took, which a queued upload gets too.
+const UPLOAD_ACK_CONTENT_TYPE: &str = "application/vnd.gradle.scan-upload-ack+json";
+const UPLOAD_ACK: &str = "{}";
+/// The type of the upstream's answer to a token request.
+const TOKEN_ACK_CONTENT_TYPE: &str = "application/vnd.gradle.scan-ack+json";
+
+/// Token requests are a few hundred bytes of JSON, read in full so they can be held.
+const MAX_TOKEN_REQUEST_BODY: usize = 64 * 1024;
+
+/// Reads a request body of up to `limit` bytes in full through the capture.
+async fn read_in_full(
+    capture: &Arc<Capture>,
+    id: &str,
+    body: Body,
+    limit: usize,
+) -> Result<Bytes, Response<Body>> {
+    axum::body::to_bytes(Body::new(capture.tee(Side::Request, body)), limit)
+        .await
+        .map_err(|e| unreadable_body(capture, id, e))
+}
+
+/// Answers a request whose body couldn't be read.
+fn unreadable_body(capture: &Capture, id: &str, error: axum::Error) -> Response<Body> {
+    if is_body_too_large(&error) {
+        warn!("Rejected request {}: {}", id, error);
+        capture.fail(format!("Request body too large: {error}"));
+        return payload_too_large();
+    }
+    warn!("Failed to read request {}: {}", id, error);
+    capture.fail(format!("Failed to read request body: {error}"));
+    Response::builder()
+        .status(400)
+        .body(Body::from("Failed to read request body"))
+        .unwrap_or_else(|_| Response::new(Body::from("Failed to read request body")))
+}
+
+/// Streams an upload through the capture into the queue. Returns the body to forward
+/// and, if queueing worked, the handler's attempt at it; or the response to answer with
+/// right away, as for an upload presenting a `held_token`, which only the delivery task
+/// can send. An upload that can't be queued at all is forwarded as it streams.
+async fn queue_upload(
+    queue: &Arc<queue::Queue>,
+    capture: &Arc<Capture>,
+    upload: QueuedUpload,
+    held_token: Option<String>,
+    body: Body,
+) -> Result<(reqwest::Body, Option<queue::Attempt>), Response<Body>> {
+    let id = upload.id.clone();
+    let mut body = capture.tee(Side::Request, body);
+    let mut spool = match queue.spool(&id) {
+        Ok(spool) => spool,
+        Err(e) => {
+            error!("Failed to queue upload {}, forwarding it as is: {}", id, e);
+            return Ok((reqwest::Body::wrap(body), None));
+        }
+    };
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|e| unreadable_body(capture, &id, e))?;
+        if let Ok(data) = frame.into_data()
+            && let Err(e) = spool.write(&data)
+        {
+            return Err(unqueued(capture, &id, e));
+        }
+    }
+    let attempt = match queue.enqueue(upload, spool).await {
+        Ok(Some(attempt)) => attempt,
+        Ok(None) => {
+            // A stand-in token means nothing upstream; delivery gets a real one first
+            if let Some(token) = held_token {
+                queue.release_token(&token).await;
+            }
+            info!("Queued upload {} for its held token request", id);
+            return Err(queued_ack(capture, id, "Token request held".into()));
+        }
+        Err(e) => return Err(unqueued(capture, &id, e)),
+    };
+    match attempt.body().await {
+        Ok(queued) => Ok((reqwest::Body::wrap(queued), Some(attempt))),
+        Err(e) => {
+            let error = format!("failed to read the queued body: {e}");
+            attempt.failed(error.clone(), true).await;
+            Err(queued_ack(capture, id, error))
+        }
+    }
+}
+
+/// Answers an upload whose body was read but couldn't be queued, and so is lost.
+fn unqueued(capture: &Capture, id: &str, error: std::io::Error) -> Response<Body> {
+    error!("Failed to queue upload {}: {}", id, error);
+    capture.fail(format!("Failed to queue upload: {error}"));
+    Response::builder()
+        .status(500)
+        .body(Body::from("Failed to queue upload"))
+        .unwrap_or_else(|_| Response::new(Body::from("Failed to queue upload")))
+}
+
+/// Answers a queued upload the way the upstream acknowledges one it took.
+fn queued_ack(capture: &Arc<Capture>, id: String, reason: String) -> Response<Body> {
+    capture.respond(
+        200,
+        vec![
+            (
+                "content-type".to_string(),
+                UPLOAD_ACK_CONTENT_TYPE.to_string(),
+            ),
+            (QUEUED_HEADER.to_string(), id.clone()),
+        ],
+    );
+    capture.fail(format!("{reason}; queued for delivery"));
+    Response::builder()
+        .status(200)
+        .header("Content-Type", UPLOAD_ACK_CONTENT_TYPE)
+        .header(QUEUED_HEADER, id)
+        .body(Body::new(
+            capture.tee(Side::Response, Body::from(UPLOAD_ACK)),
+        ))
+        .unwrap_or_else(|_| Response::new(Body::from(UPLOAD_ACK)))
+}
+
+/// Answers a held token request with a stand-in `token`, in the upstream's format. The
+/// scan URL points at the queue, as the scan has no upstream URL yet.
+fn stand_in_token(
+    capture: &Arc<Capture>,
+    host: &str,
+    path: &str,
+    token: String,
+    reason: String,
+) -> Response<Body> {
+    let upload_path = format!("{}upload", path.strip_suffix("token").unwrap_or(path));
+    let grant = serde_json::json!({
+        "id": token,
+        "scanUrl": format!("http://{host}/admin/queue"),
+        "scanUploadUrl": upload_path,
+        "scanUploadToken": token,
+    })
+    .to_string();
+    capture.respond(
+        200,
+        vec![(
+            "content-type".to_string(),
+            TOKEN_ACK_CONTENT_TYPE.to_string(),
+        )],
+    );
+    capture.fail(format!("{reason}; token request held for the upload"));
+    Response::builder()
+        .status(200)
+        .header("Content-Type", TOKEN_ACK_CONTENT_TYPE)
+        .body(Body::new(capture.tee(Side::Response, Body::from(grant))))
+        .unwrap_or_else(|_| Response::new(Body::from("Failed to build response")))
+}
+
+/// Why the upstream couldn't take a request the queue can hold, if it couldn't.
+fn upstream_failure(result: &reqwest::Result<reqwest::Response>) -> Option<String> {
+    match result {
+        Ok(response) if retry::is_retryable(response.status()) => {
+            Some(format!("HTTP {}", response.status()))
+        }
+        Ok(_) => None,
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+fn payload_too_large() -> Response<Body> {
+    Response::builder()
+        .status(413)
+        .body(Body::from("Payload too large"))
+        .unwrap_or_else(|_| Response::new(Body::from("Payload too large")))
+}
+
+/// Whether `error`, or an error it wraps, is a request body hitting `max_request_body`.
+fn is_body_too_large(error: &(dyn std::error::Error + 'static)) -> bool {
+    std::iter::successors(Some(error), |e| e.source())
+        .any(|e| e.is::<http_body_util::LengthLimitError>())
+}
+
+fn uri_path(path_and_query: &str) -> &str {
+    path_and_query
+        .split_once('?')
+        .map_or(path_and_query, |(path, _)| path)
+}
+
 async fn proxy_handler(State(state): State<AppState>, request: Request<Body>) -> Response<Body> {
+    let started = Instant::now();
     let method = request.method().clone();
     let uri = request.uri().clone();
     let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
@@ -116,30 +592,93 @@ async fn proxy_handler(State(state): State<AppState>, request: Request<Body>) ->
         .iter()
         .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
         .collect();
+    let declared_length = request
+        .headers()
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if let (Some(limit), Some(length)) = (state.config.max_request_body, declared_length)
+        && length > limit
+    {
+        warn!(
+            "Rejected {} {}: body of {} bytes exceeds the {} byte limit",
+            method, path_and_query, length, limit
+        );
+        state
+            .metrics
+            .record_request(method.as_str(), 413, started.elapsed());
+        return payload_too_large();
+    }
+    // Bodies without a Content-Length, or lying about it, are cut off at the limit.
+    let mut body = match state.config.max_request_body {
+        Some(limit) => Body::new(http_body_util::Limited::new(
+            request.into_body(),
+            usize::try_from(limit).unwrap_or(usize::MAX),
+        )),
+        None => request.into_body(),
+    };
 
-    // Read request body
-    let body_bytes = match axum::body::to_bytes(request.into_body(), MAX_BODY_SIZE).await {
-        Ok(b) => b,
-        Err(e) => {
-            error!("Failed to read request body: {}", e);
-            return Response::builder()
-                .status(413)
-                .body(Body::from("Payload too large"))
-                .unwrap_or_else(|_| Response::new(Body::from("Payload too large")));
+    if let Some(replay) = &state.replay {
+        match replay_request(&state, replay, method.as_str(), path_and_query, body).await {
+            Ok(http_response) => {
+                state.metrics.record_request(
+                    method.as_str(),
+                    http_response.status().as_u16(),
+                    started.elapsed(),
+                );
+                return http_response;
+            }
+            Err(unmatched) => body = unmatched,
         }
-    };
-    let request_body = match String::from_utf8(body_bytes.to_vec()) {
-        Ok(s) => serde_json::json!(s),
-        Err(_) => serde_json::json!({
-            "base64": base64::engine::general_purpose::STANDARD.encode(&body_bytes)
-        }),
-    };
+    }
 
     let request_id = Uuid::new_v4().to_string();
     let timestamp = Utc::now().format("%Y%m%d_%H%M%S%.3f").to_string();
 
-    // Build upstream URL
-    let upstream_url = format!("{}{}", state.config.upstream_url, path_and_query);
+    // Bodies are filled in by the capture as they stream through
+    let capture = Capture::start(
+        &state.config,
+        &state.store,
+        &state.mirrors,
+        Payload {
+            request_id: request_id.clone(),
+            timestamp,
+            request: RequestData {
+                method: method.to_string(),
+                uri: path_and_query.to_string(),
+                headers: request_headers.clone(),
+                body: serde_json::Value::Null,
+            },
+            response: ResponseData {
+                status: None,
+                headers: None,
+                body: None,
+                error: None,
+            },
+            timings: None,
+            mirrors: Vec::new(),
+        },
+        state.metrics.clone(),
+    )
+    .await;
+
+    // Build upstream URL from the matching route
+    let Some(upstream) = state.config.upstream_for(uri.path()) else {
+        warn!("No upstream configured for {} {}", method, path_and_query);
+        capture.fail("No upstream configured".into());
+        state
+            .metrics
+            .record_request(method.as_str(), 502, started.elapsed());
+        return Response::builder()
+            .status(502)
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::json!({"error": "Bad Gateway", "detail": "No upstream configured"})
+                    .to_string(),
+            ))
+            .unwrap_or_else(|_| Response::new(Body::from("Bad Gateway")));
+    };
+    let upstream_url = format!("{}{}", upstream, path_and_query);
 
     // Build upstream request, forwarding non-hop-by-hop headers
     let mut upstream_headers = reqwest::header::HeaderMap::new();
@@ -167,7 +706,76 @@ async fn proxy_handler(State(state): State<AppState>, request: Request<Body>) ->
         }
     }
 
-    // Forward request upstream
+    // Store-and-forward: uploads are streamed to the queue before the upstream sees them
+    // and sent from there, token requests are read in full so they can be held if the
+    // upstream doesn't answer
+    let forwarded_headers: Vec<_> = upstream_headers
+        .iter()
+        .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+        .collect();
+    let mut attempt = None;
+    let mut token_request = None;
+    let upstream_body = match &state.queue {
+        Some(queue) if capture::is_scan_upload(method.as_str(), path_and_query) => {
+            let held = queue.held_token(&request_headers);
+            let (token, token_request) = held.unzip();
+            let upload = QueuedUpload {
+                id: request_id.clone(),
+                method: method.to_string(),
+                url: upstream_url.clone(),
+                headers: forwarded_headers,
+                queued_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                size: 0,
+                attempts: 0,
+                last_error: None,
+                failed: false,
+                token_request,
+            };
+            match queue_upload(queue, &capture, upload, token, body).await {
+                Ok((body, queued)) => {
+                    attempt = queued;
+                    body
+                }
+                Err(http_response) => {
+                    state.metrics.record_request(
+                        method.as_str(),
+                        http_response.status().as_u16(),
+                        started.elapsed(),
+                    );
+                    return http_response;
+                }
+            }
+        }
+        Some(queue) if capture::is_token_request(method.as_str(), path_and_query) => {
+            let body = match read_in_full(&capture, &request_id, body, MAX_TOKEN_REQUEST_BODY).await
+            {
+                Ok(body) => body,
+                Err(http_response) => {
+                    state.metrics.record_request(
+                        method.as_str(),
+                        http_response.status().as_u16(),
+                        started.elapsed(),
+                    );
+                    return http_response;
+                }
+            };
+            // Compressed answers couldn't be read when the request is sent again
+            let headers = forwarded_headers
+                .into_iter()
+                .filter(|(name, _)| !name.eq_ignore_ascii_case("accept-encoding"))
+                .collect();
+            let request = TokenRequest {
+                url: upstream_url.clone(),
+                headers,
+                body: String::from_utf8_lossy(&body).into_owned(),
+            };
+            token_request = Some((queue.clone(), request));
+            reqwest::Body::from(body)
+        }
+        _ => reqwest::Body::wrap(capture.tee(Side::Request, body)),
+    };
+
+    // Forward request upstream, streaming the body through the capture
     let upstream_result = state
         .client
         .request(
@@ -176,110 +784,271 @@ async fn proxy_handler(State(state): State<AppState>, request: Request<Body>) ->
             &upstream_url,
         )
         .headers(upstream_headers)
-        .body(body_bytes.to_vec())
+        .body(upstream_body)
         .send()
         .await;
 
-    // Build payload and response
-    let (response_data, http_response) = match upstream_result {
+    // A queued upload the upstream couldn't take is acknowledged and delivered later
+    if let Some(attempt) = attempt {
+        match upstream_failure(&upstream_result) {
+            Some(error) => {
+                let id = attempt.id().to_string();
+                warn!(
+                    "Upstream couldn't take upload {}, queued for retry: {}",
+                    id, error
+                );
+                if upstream_result.is_err() {
+                    state.metrics.record_upstream_error();
+                }
+                attempt.failed(error.clone(), true).await;
+                state
+                    .metrics
+                    .record_request(method.as_str(), 200, started.elapsed());
+                return queued_ack(&capture, id, error);
+            }
+            None => attempt.delivered().await,
+        }
+    }
+
+    // A token request the upstream couldn't answer gets a stand-in token, so the upload
+    // that follows can be queued
+    if let Some((queue, request)) = token_request
+        && let Some(error) = upstream_failure(&upstream_result)
+    {
+        match queue.hold_token(request).await {
+            Ok(token) => {
+                warn!(
+                    "Upstream couldn't answer token request {}, handing out a stand-in token: {}",
+                    request_id, error
+                );
+                if upstream_result.is_err() {
+                    state.metrics.record_upstream_error();
+                }
+                state
+                    .metrics
+                    .record_request(method.as_str(), 200, started.elapsed());
+                let host = request_headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+                    .map_or_else(|| state.config.listen.to_string(), |(_, host)| host.clone());
+                return stand_in_token(&capture, &host, uri.path(), token, error);
+            }
+            Err(e) => error!("Failed to hold token request {}: {}", request_id, e),
+        }
+    }
+
+    let http_response = match upstream_result {
         Ok(upstream_response) => {
             let status = upstream_response.status().as_u16();
             let response_headers: Vec<_> = upstream_response
                 .headers()
                 .iter()
+                .filter(|(k, _)| !is_hop_by_hop(k.as_str()))
                 .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
                 .collect();
+            capture.respond(status, response_headers.clone());
 
-            let response_body_bytes = upstream_response.bytes().await.unwrap_or_default();
-            let response_body = match String::from_utf8(response_body_bytes.to_vec()) {
-                Ok(s) => serde_json::json!(s),
-                Err(_) => serde_json::json!({
-                    "base64": base64::engine::general_purpose::STANDARD.encode(&response_body_bytes)
-                }),
-            };
-
-            let response_data = ResponseData {
-                status: Some(status),
-                headers: Some(
-                    response_headers
-                        .iter()
-                        .filter(|(k, _)| !is_hop_by_hop(k))
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect::<Vec<_>>(),
-                ),
-                body: Some(response_body),
-                error: None,
-            };
-
-            // Build HTTP response to return to client
+            // Build HTTP response to return to client, streaming the upstream body
             let mut builder = Response::builder().status(status);
             for (name, value) in &response_headers {
-                if !is_hop_by_hop(name) {
-                    builder = builder.header(name.as_str(), value.as_str());
-                }
+                builder = builder.header(name.as_str(), value.as_str());
             }
-            let http_response = builder
-                .body(Body::from(response_body_bytes))
+            let upstream_body = axum::http::Response::from(upstream_response).into_body();
+            builder
+                .body(Body::new(capture.tee(Side::Response, upstream_body)))
                 .unwrap_or_else(|_| {
                     Response::builder()
                         .status(500)
                         .body(Body::from("Failed to build response"))
                         .unwrap()
-                });
-
-            (response_data, http_response)
+                })
+        }
+        Err(e) if is_body_too_large(&e) => {
+            warn!("Rejected {} {}: {}", method, path_and_query, e);
+            capture.fail(format!("Request body too large: {e}"));
+            payload_too_large()
         }
         Err(e) => {
             error!("Upstream request failed: {}", e);
-            let response_data = ResponseData {
-                error: Some(e.to_string()),
-                status: None,
-                headers: None,
-                body: None,
-            };
-            let http_response = Response::builder()
+            state.metrics.record_upstream_error();
+            capture.fail(e.to_string());
+            Response::builder()
                 .status(502)
                 .header("Content-Type", "application/json")
                 .body(Body::from(
                     serde_json::json!({"error": "Bad Gateway", "detail": e.to_string()})
                         .to_string(),
                 ))
-                .unwrap_or_else(|_| Response::new(Body::from("Bad Gateway")));
-
-            (response_data, http_response)
+                .unwrap_or_else(|_| Response::new(Body::from("Bad Gateway")))
         }
     };
 
-    // Save payload (best-effort)
-    let payload = Payload {
-        request_id: request_id.clone(),
-        timestamp: timestamp.clone(),
-        request: RequestData {
-            method: method.to_string(),
-            uri: path_and_query.to_string(),
-            headers: request_headers,
-            body: request_body,
-        },
-        response: response_data,
-    };
+    state.metrics.record_request(
+        method.as_str(),
+        http_response.status().as_u16(),
+        started.elapsed(),
+    );
+    http_response
+}
 
-    let dir = &state.config.payload_dir;
-    if let Err(e) = tokio::fs::create_dir_all(dir).await {
-        error!("Failed to create directory {:?}: {}", dir, e);
-    } else {
-        let filename = format!("{}-{}.json", timestamp, request_id);
-        let path = dir.join(&filename);
-        match serde_json::to_string_pretty(&payload) {
-            Ok(s) => {
-                if let Err(e) = tokio::fs::write(&path, &s).await {
-                    error!("Failed to write payload: {}", e);
-                } else {
-                    info!("Saved payload to: {:?}", path);
-                }
-            }
-            Err(e) => error!("Failed to serialize payload: {}", e),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, test_dir, unreachable_url};
+    use base64::Engine as _;
+    use config::Args;
+
+    fn state(args: Args) -> AppState {
+        let config = Config::from_args(args).unwrap();
+        let client = reqwest::Client::new();
+        AppState {
+            store: Arc::new(storage::Store::new(
+                config.payload_dir.clone(),
+                config.storage.clone(),
+            )),
+            mirrors: Arc::new(mirror::Mirrors::new(
+                client.clone(),
+                Vec::new(),
+                config.redaction.clone(),
+            )),
+            metrics: Arc::new(metrics::Registry::new()),
+            queue: None,
+            replay: None,
+            client,
+            config,
         }
     }
 
-    http_response
+    /// A `POST` whose body has no `Content-Length`, as when it's sent chunked.
+    fn post(uri: &str, len: usize) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .body(Body::from(vec![b'x'; len]))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_max_request_body_without_content_length() {
+        let dir = test_dir("limit");
+        let state = state(Args {
+            // Reads each request body and answers with its length.
+            upstream_url: Some(
+                serve(Router::new().fallback(|body: Bytes| async move { body.len().to_string() }))
+                    .await,
+            ),
+            payload_dir: Some(dir.clone()),
+            max_request_body: Some(1024),
+            ..Default::default()
+        });
+
+        let response = proxy_handler(State(state.clone()), post("/echo", 1024)).await;
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"1024");
+
+        let response = proxy_handler(State(state), post("/echo", 1025)).await;
+        assert_eq!(response.status(), 413);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_holds_token_request_and_queues_upload_while_upstream_is_down() {
+        let dir = test_dir("upstream-down");
+        let mut state = state(Args {
+            upstream_url: Some(unreachable_url().await),
+            payload_dir: Some(dir.join("captures")),
+            ..Default::default()
+        });
+        let queue_config = config::QueueConfig {
+            dir: dir.join("queue"),
+            max_attempts: None,
+        };
+        let queue = Arc::new(queue::Queue::open(&queue_config, state.client.clone()).unwrap());
+        state.queue = Some(queue.clone());
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/scans/publish/gradle/4.3.2/token")
+            .header(
+                "content-type",
+                "application/vnd.gradle.scan-token-request+json",
+            )
+            .body(Body::from(r#"{"buildToolType":"gradle","payloadSize":3}"#))
+            .unwrap();
+        let response = proxy_handler(State(state.clone()), request).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], TOKEN_ACK_CONTENT_TYPE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let grant: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(grant["scanUploadUrl"], "/scans/publish/gradle/4.3.2/upload");
+        let token = grant["scanUploadToken"].as_str().unwrap();
+        assert_eq!(queue.state().held_tokens, 1);
+
+        // The plugin presents the token base64url encoded, and reads the answer like the
+        // upstream's acknowledgement of a stored upload: 200, this type and `{}`.
+        let request = Request::builder()
+            .method("POST")
+            .uri("/scans/publish/gradle/4.3.2/upload")
+            .header(
+                queue::UPLOAD_TOKEN_HEADER,
+                base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(token),
+            )
+            .body(Body::from("abc"))
+            .unwrap();
+        let response = proxy_handler(State(state), request).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], UPLOAD_ACK_CONTENT_TYPE);
+        assert!(response.headers().contains_key(QUEUED_HEADER));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], UPLOAD_ACK.as_bytes());
+
+        let state = queue.state();
+        assert_eq!((state.pending, state.held_tokens), (1, 0));
+        assert_eq!(state.uploads[0].state, "pending");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_streams_queued_upload_to_upstream() {
+        let dir = test_dir("queued-upload");
+        let mut state = state(Args {
+            // Reads each request body and answers with its length.
+            upstream_url: Some(
+                serve(Router::new().fallback(|body: Bytes| async move { body.len().to_string() }))
+                    .await,
+            ),
+            payload_dir: Some(dir.join("captures")),
+            max_request_body: Some(1024),
+            ..Default::default()
+        });
+        let queue_config = config::QueueConfig {
+            dir: dir.join("queue"),
+            max_attempts: None,
+        };
+        let queue = Arc::new(queue::Queue::open(&queue_config, state.client.clone()).unwrap());
+        state.queue = Some(queue.clone());
+
+        let upload = "/scans/publish/gradle/4.3.2/upload";
+        let response = proxy_handler(State(state.clone()), post(upload, 1024)).await;
+        assert_eq!(response.status(), 200);
+        assert!(!response.headers().contains_key(QUEUED_HEADER));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"1024");
+        assert_eq!(queue.len(), 0);
+
+        let response = proxy_handler(State(state), post(upload, 1025)).await;
+        assert_eq!(response.status(), 413);
+        assert_eq!(queue.len(), 0);
+        assert_eq!(std::fs::read_dir(dir.join("queue")).unwrap().count(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,277 @@
+//! Copies of build scan uploads sent to the configured mirrors.
+//!
+//! The upstream's exchange is never held up: an upload is mirrored once its capture
+//! finishes, from the spooled request body, and the capture is saved with the outcome of
+//! every delivery. Each mirror gets its own retries, with exponential backoff between
+//! attempts; responses other than 408, 429 and 5xx are final.
+
+use std::time::Duration;
+
+use axum::body::Bytes;
+use config::{Mirror, Redaction};
+use format::MirrorDelivery;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+use crate::{is_hop_by_hop, retry};
+
+/// Wait before the first retry; doubled for each one after it.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub struct Mirrors {
+    client: reqwest::Client,
+    targets: Vec<Mirror>,
+    /// Headers it redacts from captures aren't sent on: credentials for the upstream are
+    /// no business of a mirror.
+    redaction: Redaction,
+    first_retry_delay: Duration,
+}
+
+/// The request to copy.
+#[derive(Debug, Clone)]
+pub struct Upload {
+    pub method: String,
+    pub path_and_query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+}
+
+impl Mirrors {
+    pub fn new(client: reqwest::Client, targets: Vec<Mirror>, redaction: Redaction) -> Self {
+        Self {
+            client,
+            targets,
+            redaction,
+            first_retry_delay: FIRST_RETRY_DELAY,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Delivers `upload` to every mirror at once, returning how each went in the order
+    /// they're configured.
+    pub async fn deliver(&self, upload: Upload) -> Vec<MirrorDelivery> {
+        let mut tasks = JoinSet::new();
+        for (i, target) in self.targets.iter().enumerate() {
+            let request = self.request(target, &upload);
+            let retries = target.retries;
+            let first_retry_delay = self.first_retry_delay;
+            tasks.spawn(async move { (i, deliver_to(request, retries, first_retry_delay).await) });
+        }
+
+        let mut deliveries: Vec<Option<MirrorDelivery>> = vec![None; self.targets.len()];
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok((i, delivery)) = joined {
+                deliveries[i] = Some(delivery);
+            }
+        }
+        deliveries
+            .into_iter()
+            .zip(&self.targets)
+            .map(|(delivery, target)| {
+                let delivery = delivery.unwrap_or_else(|| MirrorDelivery {
+                    url: target.url.clone(),
+                    attempts: 0,
+                    status: None,
+                    error: Some("delivery task panicked".into()),
+                });
+                match &delivery.error {
+                    None => info!(
+                        "Mirrored {} to {} on attempt {}",
+                        upload.path_and_query, delivery.url, delivery.attempts
+                    ),
+                    Some(error) => warn!(
+                        "Failed to mirror {} to {} after {} attempts: {}",
+                        upload.path_and_query, delivery.url, delivery.attempts, error
+                    ),
+                }
+                delivery
+            })
+            .collect()
+    }
+
+    fn request(&self, target: &Mirror, upload: &Upload) -> MirrorRequest {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &upload.headers {
+            if !is_hop_by_hop(name)
+                && !name.eq_ignore_ascii_case("content-length")
+                && !self.redaction.header(name)
+                && let (Ok(name), Ok(value)) = (
+                    reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+                    reqwest::header::HeaderValue::from_str(value),
+                )
+            {
+                headers.append(name, value);
+            }
+        }
+        MirrorRequest {
+            client: self.client.clone(),
+            method: reqwest::Method::from_bytes(upload.method.as_bytes())
+                .unwrap_or(reqwest::Method::POST),
+            base_url: target.url.clone(),
+            url: format!("{}{}", target.url, upload.path_and_query),
+            headers,
+            body: upload.body.clone(),
+        }
+    }
+}
+
+/// Everything one delivery task needs, owned so it can be spawned.
+struct MirrorRequest {
+    client: reqwest::Client,
+    method: reqwest::Method,
+    base_url: String,
+    url: String,
+    headers: reqwest::header::HeaderMap,
+    body: Bytes,
+}
+
+async fn deliver_to(
+    request: MirrorRequest,
+    retries: u32,
+    first_retry_delay: Duration,
+) -> MirrorDelivery {
+    let mut delivery = MirrorDelivery {
+        url: request.base_url.clone(),
+        attempts: 0,
+        status: None,
+        error: None,
+    };
+    loop {
+        delivery.attempts += 1;
+        let result = request
+            .client
+            .request(request.method.clone(), &request.url)
+            .headers(request.headers.clone())
+            .body(request.body.clone())
+            .send()
+            .await;
+        let retryable = match result {
+            Ok(response) if response.status().is_success() => {
+                delivery.status = Some(response.status().as_u16());
+                delivery.error = None;
+                return delivery;
+            }
+            Ok(response) => {
+                let status = response.status();
+                delivery.status = Some(status.as_u16());
+                delivery.error = Some(format!("HTTP {status}"));
+                retry::is_retryable(status)
+            }
+            Err(e) => {
+                delivery.status = None;
+                delivery.error = Some(e.to_string());
+                true
+            }
+        };
+        if !retryable || delivery.attempts > retries {
+            return delivery;
+        }
+        tokio::time::sleep(retry::backoff(
+            first_retry_delay,
+            MAX_RETRY_DELAY,
+            delivery.attempts,
+        ))
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve_statuses, unreachable_url};
+
+    fn mirrors(targets: Vec<Mirror>) -> Mirrors {
+        Mirrors {
+            first_retry_delay: Duration::from_millis(1),
+            ..Mirrors::new(reqwest::Client::new(), targets, Redaction::default())
+        }
+    }
+
+    fn upload() -> Upload {
+        Upload {
+            method: "POST".into(),
+            path_and_query: "/scans/publish/gradle/4.3.2/upload?x=1".into(),
+            headers: vec![
+                ("x-scan".into(), "abc".into()),
+                ("authorization".into(), "Bearer secret".into()),
+                ("connection".into(), "keep-alive".into()),
+            ],
+            body: Bytes::from_static(&[0x28, 0xC5, 1, 2, 3]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_delivered() {
+        let flaky = serve_statuses(vec![503, 502, 201]).await;
+        let rejecting = serve_statuses(vec![400]).await;
+        let down = serve_statuses(vec![500]).await;
+        let deliveries = mirrors(vec![
+            Mirror {
+                url: flaky.url.clone(),
+                retries: 3,
+            },
+            Mirror {
+                url: rejecting.url.clone(),
+                retries: 3,
+            },
+            Mirror {
+                url: down.url.clone(),
+                retries: 1,
+            },
+        ])
+        .deliver(upload())
+        .await;
+
+        assert_eq!(
+            deliveries,
+            vec![
+                MirrorDelivery {
+                    url: flaky.url.clone(),
+                    attempts: 3,
+                    status: Some(201),
+                    error: None,
+                },
+                MirrorDelivery {
+                    url: rejecting.url.clone(),
+                    attempts: 1,
+                    status: Some(400),
+                    error: Some("HTTP 400 Bad Request".into()),
+                },
+                MirrorDelivery {
+                    url: down.url.clone(),
+                    attempts: 2,
+                    status: Some(500),
+                    error: Some("HTTP 500 Internal Server Error".into()),
+                },
+            ]
+        );
+        assert_eq!(flaky.calls(), 3);
+        assert_eq!(rejecting.calls(), 1);
+        assert_eq!(down.calls(), 2);
+        let (uri, headers) = &flaky.requests.lock().unwrap()[0];
+        assert_eq!(uri, "/scans/publish/gradle/4.3.2/upload?x=1");
+        assert_eq!(headers["x-scan"], "abc");
+        assert!(!headers.contains_key("authorization"));
+        assert_eq!(headers["content-length"], "5");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_mirror() {
+        let url = unreachable_url().await;
+        let deliveries = mirrors(vec![Mirror {
+            url: url.clone(),
+            retries: 2,
+        }])
+        .deliver(upload())
+        .await;
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].attempts, 3);
+        assert_eq!(deliveries[0].status, None);
+        assert!(!deliveries[0].delivered());
+    }
+}
@@ -0,0 +1,850 @@
+//! Store-and-forward for build scan uploads.
+//!
+//! Each upload is written to the queue directory (`<id>.body`, then `<id>.json` with
+//! where it goes) before the upstream sees it, and removed once the upstream has
+//! answered. When the upstream can't be reached or answers 408, 429 or 5xx the upload
+//! stays queued and is delivered again in the background with exponential backoff, also
+//! after a restart. Uploads the upstream rejects outright, or that run out of attempts,
+//! are kept as failed for someone to look at. The files hold the request headers as
+//! sent, credentials included.
+//!
+//! The token request before an upload is held the same way when the upstream can't
+//! answer it: the plugin is given a stand-in token (`<token>.token` holds the request),
+//! and the upload presenting it is queued with the request, which is sent for a real
+//! token right before the upload is delivered.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use config::QueueConfig;
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, ReadBuf};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::retry;
+
+/// Where the plugin sends the token it was given, base64url encoded.
+pub const UPLOAD_TOKEN_HEADER: &str = "x-upload-token";
+
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(15 * 60);
+/// How often the delivery task looks for uploads that are due.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a held token request waits for its upload, across restarts.
+const HELD_TOKEN_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How much of a queued body is read from disk at a time when sending it.
+const BODY_CHUNK: usize = 64 * 1024;
+
+/// A queued upload as persisted in `<id>.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedUpload {
+    pub id: String,
+    pub method: String,
+    /// Full upstream URL.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// RFC 3339.
+    pub queued_at: String,
+    pub size: u64,
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub failed: bool,
+    /// The held token request whose stand-in token the upload presents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_request: Option<TokenRequest>,
+}
+
+/// A token request the upstream couldn't answer, as persisted in `<token>.token`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRequest {
+    /// Full upstream URL.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// The part of the upstream's answer to a token request the upload needs.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenGrant {
+    scan_upload_url: String,
+    scan_upload_token: String,
+}
+
+#[derive(Debug)]
+struct Slot {
+    upload: QueuedUpload,
+    next_attempt: Instant,
+    /// An attempt is under way, by the request handler or the delivery task.
+    in_flight: bool,
+}
+
+/// What the admin endpoint shows. Headers are left out.
+#[derive(Debug, Serialize)]
+pub struct QueueState {
+    pub pending: usize,
+    pub failed: usize,
+    /// Token requests waiting for their upload.
+    pub held_tokens: usize,
+    pub uploads: Vec<UploadState>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadState {
+    pub id: String,
+    pub method: String,
+    pub url: String,
+    pub queued_at: String,
+    pub size: u64,
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// `pending`, `delivering` or `failed`.
+    pub state: &'static str,
+    /// Seconds until the next attempt, for pending uploads.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_attempt_in: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct Queue {
+    dir: PathBuf,
+    max_attempts: Option<u32>,
+    client: reqwest::Client,
+    first_retry_delay: Duration,
+    slots: Mutex<BTreeMap<String, Slot>>,
+    held_tokens: Mutex<HashMap<String, TokenRequest>>,
+}
+
+impl Queue {
+    /// Opens the queue directory, creating it if needed, and picks up the uploads and
+    /// held token requests left in it; pending uploads are due right away.
+    pub fn open(config: &QueueConfig, client: reqwest::Client) -> io::Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+        let mut slots = BTreeMap::new();
+        let mut held_tokens = HashMap::new();
+        for entry in std::fs::read_dir(&config.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "token") {
+                if let Some((token, request)) = load_held_token(&path) {
+                    held_tokens.insert(token, request);
+                }
+                continue;
+            }
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let upload = std::fs::read(&path)
+                .and_then(|json| serde_json::from_slice::<QueuedUpload>(&json).map_err(Into::into));
+            match upload {
+                Ok(upload) => {
+                    slots.insert(
+                        upload.id.clone(),
+                        Slot {
+                            upload,
+                            next_attempt: Instant::now(),
+                            in_flight: false,
+                        },
+                    );
+                }
+                Err(e) => warn!("Skipping unreadable queue entry {:?}: {}", path, e),
+            }
+        }
+        Ok(Self {
+            dir: config.dir.clone(),
+            max_attempts: config.max_attempts,
+            client,
+            first_retry_delay: FIRST_RETRY_DELAY,
+            slots: Mutex::new(slots),
+            held_tokens: Mutex::new(held_tokens),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots().len()
+    }
+
+    /// Starts writing the body of the upload `id` to the queue directory.
+    pub fn spool(&self, id: &str) -> io::Result<BodySpool> {
+        let path = self.body_path(id);
+        let file = File::create(&path)?;
+        Ok(BodySpool {
+            path,
+            file: BufWriter::new(file),
+            len: 0,
+            kept: false,
+        })
+    }
+
+    /// Persists `upload` with the `body` spooled for it. Returns the caller's attempt at
+    /// it, which reports back how it went; an upload with a held token request is left
+    /// to the delivery task instead.
+    pub async fn enqueue(
+        self: &Arc<Self>,
+        mut upload: QueuedUpload,
+        mut body: BodySpool,
+    ) -> io::Result<Option<Attempt>> {
+        body.file.flush()?;
+        upload.size = body.len;
+        self.persist(&upload).await?;
+        body.kept = true;
+        let attempt = upload.token_request.is_none().then(|| Attempt {
+            queue: self.clone(),
+            id: upload.id.clone(),
+            settled: false,
+        });
+        self.slots().insert(
+            upload.id.clone(),
+            Slot {
+                in_flight: attempt.is_some(),
+                upload,
+                next_attempt: Instant::now(),
+            },
+        );
+        Ok(attempt)
+    }
+
+    /// Holds a token request the upstream couldn't answer, returning the stand-in token
+    /// to give the client.
+    pub async fn hold_token(&self, request: TokenRequest) -> io::Result<String> {
+        let token = Uuid::new_v4().simple().to_string();
+        tokio::fs::write(
+            self.token_path(&token),
+            serde_json::to_vec_pretty(&request)?,
+        )
+        .await?;
+        self.held_tokens().insert(token.clone(), request);
+        Ok(token)
+    }
+
+    /// The stand-in token an upload presents in its `headers`, with the request held for
+    /// it.
+    pub fn held_token(&self, headers: &[(String, String)]) -> Option<(String, TokenRequest)> {
+        let (_, value) = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(UPLOAD_TOKEN_HEADER))?;
+        let token = String::from_utf8(URL_SAFE_NO_PAD.decode(value).ok()?).ok()?;
+        let request = self.held_tokens().get(&token)?.clone();
+        Some((token, request))
+    }
+
+    /// Forgets a held token request once the upload presenting its token is queued.
+    pub async fn release_token(&self, token: &str) {
+        if self.held_tokens().remove(token).is_none() {
+            return;
+        }
+        let path = self.token_path(token);
+        if let Err(e) = tokio::fs::remove_file(&path).await
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            error!("Failed to remove {:?} from the upload queue: {}", path, e);
+        }
+    }
+
+    /// Forgets an upload the upstream has answered.
+    async fn remove(&self, id: &str) {
+        self.slots().remove(id);
+        for path in [self.meta_path(id), self.body_path(id)] {
+            if let Err(e) = tokio::fs::remove_file(&path).await
+                && e.kind() != io::ErrorKind::NotFound
+            {
+                error!("Failed to remove {:?} from the upload queue: {}", path, e);
+            }
+        }
+    }
+
+    /// Records a failed attempt: the upload is retried after a backoff, or given up on
+    /// when `retryable` is false or it has run out of attempts.
+    async fn attempted(&self, id: &str, error: String, retryable: bool) {
+        let upload = {
+            let mut slots = self.slots();
+            let Some(slot) = slots.get_mut(id) else {
+                return;
+            };
+            let upload = &mut slot.upload;
+            upload.attempts += 1;
+            upload.last_error = Some(error);
+            upload.failed =
+                !retryable || self.max_attempts.is_some_and(|max| upload.attempts >= max);
+            slot.next_attempt = Instant::now()
+                + retry::backoff(self.first_retry_delay, MAX_RETRY_DELAY, upload.attempts);
+            slot.in_flight = false;
+            upload.clone()
+        };
+        if upload.failed {
+            warn!(
+                "Giving up on queued upload {} to {} after {} attempts: {}",
+                upload.id,
+                upload.url,
+                upload.attempts,
+                upload.last_error.as_deref().unwrap_or_default()
+            );
+        }
+        if let Err(e) = self.persist(&upload).await {
+            error!("Failed to update queued upload {}: {}", upload.id, e);
+        }
+    }
+
+    pub fn state(&self) -> QueueState {
+        let now = Instant::now();
+        let slots = self.slots();
+        let uploads: Vec<_> = slots
+            .values()
+            .map(|slot| {
+                let upload = &slot.upload;
+                let state = if upload.failed {
+                    "failed"
+                } else if slot.in_flight {
+                    "delivering"
+                } else {
+                    "pending"
+                };
+                UploadState {
+                    id: upload.id.clone(),
+                    method: upload.method.clone(),
+                    url: upload.url.clone(),
+                    queued_at: upload.queued_at.clone(),
+                    size: upload.size,
+                    attempts: upload.attempts,
+                    last_error: upload.last_error.clone(),
+                    state,
+                    next_attempt_in: (state == "pending")
+                        .then(|| slot.next_attempt.saturating_duration_since(now).as_secs()),
+                }
+            })
+            .collect();
+        let failed = uploads.iter().filter(|u| u.state == "failed").count();
+        QueueState {
+            pending: uploads.len() - failed,
+            failed,
+            held_tokens: self.held_tokens().len(),
+            uploads,
+        }
+    }
+
+    /// Delivers due uploads in the background, one at a time so a recovering upstream
+    /// isn't flooded.
+    pub fn spawn_delivery(self: &Arc<Self>) {
+        let queue = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                while let Some(upload) = queue.take_due() {
+                    queue.deliver(upload).await;
+                }
+            }
+        });
+    }
+
+    /// The oldest upload that is due, marked as in flight.
+    fn take_due(&self) -> Option<QueuedUpload> {
+        let now = Instant::now();
+        let mut slots = self.slots();
+        let slot = slots
+            .values_mut()
+            .filter(|slot| !slot.upload.failed && !slot.in_flight && slot.next_attempt <= now)
+            .min_by(|a, b| a.upload.queued_at.cmp(&b.upload.queued_at))?;
+        slot.in_flight = true;
+        Some(slot.upload.clone())
+    }
+
+    async fn deliver(&self, mut upload: QueuedUpload) {
+        if let Some(request) = upload.token_request.take() {
+            match self.exchange_token(&request).await {
+                Ok((url, token)) => {
+                    upload.url = url;
+                    upload
+                        .headers
+                        .retain(|(name, _)| !name.eq_ignore_ascii_case(UPLOAD_TOKEN_HEADER));
+                    upload
+                        .headers
+                        .push((UPLOAD_TOKEN_HEADER.to_string(), token));
+                    if let Some(slot) = self.slots().get_mut(&upload.id) {
+                        slot.upload = upload.clone();
+                    }
+                    if let Err(e) = self.persist(&upload).await {
+                        error!("Failed to update queued upload {}: {}", upload.id, e);
+                    }
+                }
+                Err((error, retryable)) => {
+                    return self.attempted(&upload.id, error, retryable).await;
+                }
+            }
+        }
+        let body = match self.body(&upload.id).await {
+            Ok(body) => reqwest::Body::wrap(body),
+            Err(e) => {
+                let error = format!("failed to read the queued body: {e}");
+                return self.attempted(&upload.id, error, false).await;
+            }
+        };
+        let method =
+            reqwest::Method::from_bytes(upload.method.as_bytes()).unwrap_or(reqwest::Method::POST);
+        let result = self
+            .client
+            .request(method, &upload.url)
+            .headers(header_map(&upload.headers))
+            .body(body)
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => {
+                info!(
+                    "Delivered queued upload {} to {} on attempt {}",
+                    upload.id,
+                    upload.url,
+                    upload.attempts + 1
+                );
+                self.remove(&upload.id).await;
+            }
+            Ok(response) => {
+                let status = response.status();
+                self.attempted(
+                    &upload.id,
+                    format!("HTTP {status}"),
+                    retry::is_retryable(status),
+                )
+                .await;
+            }
+            Err(e) => self.attempted(&upload.id, e.to_string(), true).await,
+        }
+    }
+
+    /// Sends a held token request, returning the upload URL and `X-Upload-Token` value
+    /// from the upstream's answer, or the error and whether it's worth retrying.
+    async fn exchange_token(
+        &self,
+        request: &TokenRequest,
+    ) -> Result<(String, String), (String, bool)> {
+        let response = self
+            .client
+            .post(&request.url)
+            .headers(header_map(&request.headers))
+            .body(request.body.clone())
+            .send()
+            .await
+            .map_err(|e| (format!("token request failed: {e}"), true))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err((
+                format!("token request: HTTP {status}"),
+                retry::is_retryable(status),
+            ));
+        }
+        let grant = response
+            .bytes()
+            .await
+            .map_err(|e| (format!("token request failed: {e}"), true))?;
+        let grant: TokenGrant = serde_json::from_slice(&grant)
+            .map_err(|e| (format!("unreadable token response: {e}"), false))?;
+        let url = reqwest::Url::parse(&request.url)
+            .and_then(|url| url.join(&grant.scan_upload_url))
+            .map_err(|e| {
+                (
+                    format!("bad upload URL {:?}: {e}", grant.scan_upload_url),
+                    false,
+                )
+            })?;
+        Ok((
+            url.to_string(),
+            URL_SAFE_NO_PAD.encode(grant.scan_upload_token),
+        ))
+    }
+
+    /// Opens the queued body of the upload `id` for sending.
+    async fn body(&self, id: &str) -> io::Result<QueuedBody> {
+        let file = tokio::fs::File::open(self.body_path(id)).await?;
+        let remaining = file.metadata().await?.len();
+        Ok(QueuedBody {
+            file,
+            remaining,
+            buf: vec![0; BODY_CHUNK],
+        })
+    }
+
+    /// Writes `<id>.json` through a temporary file, so it is never seen half written.
+    async fn persist(&self, upload: &QueuedUpload) -> io::Result<()> {
+        let path = self.meta_path(&upload.id);
+        let partial = path.with_extension("json.part");
+        tokio::fs::write(&partial, serde_json::to_vec_pretty(upload)?).await?;
+        tokio::fs::rename(&partial, &path).await
+    }
+
+    fn meta_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    fn body_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.body"))
+    }
+
+    fn token_path(&self, token: &str) -> PathBuf {
+        self.dir.join(format!("{token}.token"))
+    }
+
+    fn slots(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Slot>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn held_tokens(&self) -> std::sync::MutexGuard<'_, HashMap<String, TokenRequest>> {
+        self.held_tokens.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// An upload body being written to `<id>.body`, removed again unless it gets queued.
+#[derive(Debug)]
+pub struct BodySpool {
+    path: PathBuf,
+    file: BufWriter<File>,
+    len: u64,
+    kept: bool,
+}
+
+impl BodySpool {
+    pub fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.file.write_all(chunk)?;
+        self.len += chunk.len() as u64;
+        Ok(())
+    }
+}
+
+impl Drop for BodySpool {
+    fn drop(&mut self) {
+        if !self.kept {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// The request handler's attempt at an upload it just queued. One dropped before it's
+/// settled, as when the client goes away while the upstream is being sent the upload,
+/// is left to the delivery task.
+#[derive(Debug)]
+pub struct Attempt {
+    queue: Arc<Queue>,
+    id: String,
+    settled: bool,
+}
+
+impl Attempt {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The queued body, to send.
+    pub async fn body(&self) -> io::Result<QueuedBody> {
+        self.queue.body(&self.id).await
+    }
+
+    /// The upstream answered: the upload is done with.
+    pub async fn delivered(mut self) {
+        self.settled = true;
+        self.queue.remove(&self.id).await;
+    }
+
+    /// The upstream couldn't take the upload: it's retried after a backoff, or given up on
+    /// when `retryable` is false or it has run out of attempts.
+    pub async fn failed(mut self, error: String, retryable: bool) {
+        self.settled = true;
+        self.queue.attempted(&self.id, error, retryable).await;
+    }
+}
+
+impl Drop for Attempt {
+    fn drop(&mut self) {
+        if !self.settled
+            && let Some(slot) = self.queue.slots().get_mut(&self.id)
+        {
+            slot.in_flight = false;
+        }
+    }
+}
+
+/// A queued body read from disk as it's sent.
+#[derive(Debug)]
+pub struct QueuedBody {
+    file: tokio::fs::File,
+    remaining: u64,
+    buf: Vec<u8>,
+}
+
+impl HttpBody for QueuedBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        let this = self.get_mut();
+        if this.remaining == 0 {
+            return Poll::Ready(None);
+        }
+        let max = this
+            .buf
+            .len()
+            .min(usize::try_from(this.remaining).unwrap_or(usize::MAX));
+        let mut buf = ReadBuf::new(&mut this.buf[..max]);
+        ready!(Pin::new(&mut this.file).poll_read(cx, &mut buf))?;
+        let chunk = buf.filled();
+        if chunk.is_empty() {
+            return Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into())));
+        }
+        this.remaining -= chunk.len() as u64;
+        Poll::Ready(Some(Ok(Frame::data(Bytes::copy_from_slice(chunk)))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+/// A `<token>.token` file's token and request; one that outlived `HELD_TOKEN_TTL`
+/// without its upload coming is removed.
+fn load_held_token(path: &Path) -> Option<(String, TokenRequest)> {
+    let token = path.file_stem()?.to_str()?.to_string();
+    let expired = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age > HELD_TOKEN_TTL));
+    if expired {
+        let _ = std::fs::remove_file(path);
+        return None;
+    }
+    let request = std::fs::read(path)
+        .and_then(|json| serde_json::from_slice::<TokenRequest>(&json).map_err(Into::into));
+    match request {
+        Ok(request) => Some((token, request)),
+        Err(e) => {
+            warn!("Skipping unreadable held token request {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+fn header_map(headers: &[(String, String)]) -> reqwest::header::HeaderMap {
+    let mut map = reqwest::header::HeaderMap::new();
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+            reqwest::header::HeaderValue::from_str(value),
+        ) {
+            map.append(name, value);
+        }
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, serve_statuses, test_dir};
+
+    fn upload(id: &str, url: &str) -> QueuedUpload {
+        QueuedUpload {
+            id: id.into(),
+            method: "POST".into(),
+            url: format!("{url}/scans/publish/gradle/4.3.2/upload"),
+            headers: vec![("authorization".into(), "Bearer k".into())],
+            queued_at: format!("2026-10-18T12:00:00.00{id}Z"),
+            size: 0,
+            attempts: 0,
+            last_error: None,
+            failed: false,
+            token_request: None,
+        }
+    }
+
+    fn open(dir: &Path, max_attempts: Option<u32>) -> Arc<Queue> {
+        let config = QueueConfig {
+            dir: dir.to_path_buf(),
+            max_attempts,
+        };
+        Arc::new(Queue {
+            first_retry_delay: Duration::ZERO,
+            ..Queue::open(&config, reqwest::Client::new()).unwrap()
+        })
+    }
+
+    async fn enqueue(queue: &Arc<Queue>, upload: QueuedUpload) -> Option<Attempt> {
+        let mut body = queue.spool(&upload.id).unwrap();
+        body.write(b"ab").unwrap();
+        body.write(b"c").unwrap();
+        queue.enqueue(upload, body).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retries_survive_reopening() {
+        let dir = test_dir("reopen");
+        let upstream = serve_statuses(vec![503, 200]).await;
+        let queue = open(&dir, None);
+        let attempt = enqueue(&queue, upload("1", &upstream.url)).await.unwrap();
+        // The request handler's attempt failed.
+        attempt.failed("connection refused".into(), true).await;
+        let state = queue.state();
+        assert_eq!((state.pending, state.failed), (1, 0));
+        assert_eq!(state.uploads[0].attempts, 1);
+        assert_eq!(state.uploads[0].size, 3);
+        drop(queue);
+
+        let queue = open(&dir, None);
+        let due = queue.take_due().unwrap();
+        assert_eq!(due.last_error.as_deref(), Some("connection refused"));
+        assert!(queue.take_due().is_none(), "in flight");
+        queue.deliver(due).await;
+        assert_eq!(queue.state().uploads[0].attempts, 2);
+        assert_eq!(
+            queue.state().uploads[0].last_error.as_deref(),
+            Some("HTTP 503 Service Unavailable")
+        );
+
+        let due = queue.take_due().unwrap();
+        queue.deliver(due).await;
+        assert_eq!(upstream.calls(), 2);
+        assert_eq!(queue.len(), 0);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let dir = test_dir("give-up");
+        let url = serve_statuses(vec![503]).await.url;
+        let queue = open(&dir, Some(2));
+        let first = enqueue(&queue, upload("1", &url)).await.unwrap();
+        let second = enqueue(&queue, upload("2", &url)).await.unwrap();
+        first.failed("HTTP 502 Bad Gateway".into(), true).await;
+        second.failed("HTTP 400 Bad Request".into(), false).await;
+        assert_eq!(queue.state().failed, 1);
+
+        let due = queue.take_due().unwrap();
+        assert_eq!(due.id, "1");
+        queue.deliver(due).await;
+        let state = queue.state();
+        assert_eq!((state.pending, state.failed), (0, 2));
+        assert!(queue.take_due().is_none());
+
+        // Failed uploads stay on disk, and stay failed.
+        let queue = open(&dir, Some(2));
+        assert_eq!(queue.state().failed, 2);
+        assert!(queue.take_due().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trades_held_token_before_delivery() {
+        let dir = test_dir("token");
+        let presented = Arc::new(Mutex::new(None));
+        let upload_presented = presented.clone();
+        let app = axum::Router::new()
+            .route(
+                "/scans/publish/gradle/4.3.2/token",
+                axum::routing::post(|| async {
+                    r#"{"id":"s","scanUploadUrl":"/scans/publish/gradle/4.3.2/upload","scanUploadToken":"real"}"#
+                }),
+            )
+            .route(
+                "/scans/publish/gradle/4.3.2/upload",
+                axum::routing::post(move |headers: axum::http::HeaderMap| async move {
+                    *upload_presented.lock().unwrap() = headers
+                        .get(UPLOAD_TOKEN_HEADER)
+                        .map(|value| value.to_str().unwrap().to_string());
+                }),
+            );
+        let url = serve(app).await;
+
+        let queue = open(&dir, None);
+        let token = queue
+            .hold_token(TokenRequest {
+                url: format!("{url}/scans/publish/gradle/4.3.2/token"),
+                headers: Vec::new(),
+                body: "{}".into(),
+            })
+            .await
+            .unwrap();
+        drop(queue);
+
+        // The held request survives a restart, and the plugin presents the token encoded.
+        let queue = open(&dir, None);
+        let headers = vec![(
+            UPLOAD_TOKEN_HEADER.to_string(),
+            URL_SAFE_NO_PAD.encode(&token),
+        )];
+        let (held, request) = queue.held_token(&headers).unwrap();
+        assert_eq!(held, token);
+        let mut stand_in = upload("1", "http://upstream.invalid");
+        stand_in.headers = headers;
+        stand_in.token_request = Some(request);
+        assert!(
+            enqueue(&queue, stand_in).await.is_none(),
+            "left to delivery"
+        );
+        queue.release_token(&held).await;
+        assert_eq!(queue.state().held_tokens, 0);
+
+        // Left to the delivery task, which asks for a real token first.
+        let due = queue.take_due().unwrap();
+        queue.deliver(due).await;
+        assert_eq!(queue.len(), 0);
+        assert_eq!(
+            presented.lock().unwrap().as_deref(),
+            Some(URL_SAFE_NO_PAD.encode("real").as_str())
+        );
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_attempt_is_left_to_delivery() {
+        let dir = test_dir("abandoned");
+        let upstream = serve_statuses(vec![200]).await;
+        let queue = open(&dir, None);
+        let attempt = enqueue(&queue, upload("1", &upstream.url)).await.unwrap();
+        assert!(queue.take_due().is_none(), "in flight");
+        assert_eq!(queue.state().uploads[0].state, "delivering");
+
+        // The client went away while the upstream was being sent the upload.
+        drop(attempt);
+        assert_eq!(queue.state().uploads[0].state, "pending");
+        let due = queue.take_due().unwrap();
+        assert_eq!(due.attempts, 0);
+        queue.deliver(due).await;
+        assert_eq!(upstream.calls(), 1);
+        assert_eq!(queue.len(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unqueued_body_is_removed() {
+        let dir = test_dir("unqueued");
+        let queue = open(&dir, None);
+        let mut body = queue.spool("1").unwrap();
+        body.write(b"abc").unwrap();
+        assert!(dir.join("1.body").exists());
+        // Reading the rest of the body failed.
+        drop(body);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
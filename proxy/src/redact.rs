@@ -0,0 +1,98 @@
+//! Applies the configured `Redaction` to a capture before it is written.
+
+use config::{REDACTED, Redaction};
+use format::Payload;
+use serde_json::Value;
+
+/// Redacts the headers of both sides and the query string of the request URI.
+pub fn payload(redaction: &Redaction, payload: &mut Payload) {
+    payload.request.uri = redaction.uri(&payload.request.uri);
+    headers(redaction, &mut payload.request.headers);
+    if let Some(response_headers) = payload.response.headers.as_mut() {
+        headers(redaction, response_headers);
+    }
+}
+
+fn headers(redaction: &Redaction, headers: &mut [(String, String)]) {
+    for (name, value) in headers {
+        if redaction.header(name) {
+            *value = REDACTED.to_string();
+        }
+    }
+}
+
+/// Replaces the values of redacted fields anywhere in `value`; returns whether any were.
+pub fn json(redaction: &Redaction, value: &mut Value) -> bool {
+    match value {
+        Value::Object(fields) => {
+            let mut redacted = false;
+            for (name, field) in fields.iter_mut() {
+                if redaction.body_field(name) {
+                    *field = Value::String(REDACTED.to_string());
+                    redacted = true;
+                } else {
+                    redacted |= json(redaction, field);
+                }
+            }
+            redacted
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .fold(false, |redacted, item| json(redaction, item) | redacted),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::{RequestData, ResponseData};
+    use serde_json::json;
+
+    #[test]
+    fn test_redacts_headers_query_and_json_fields() {
+        let redaction = Redaction::default();
+        let mut captured = Payload {
+            request_id: "id".into(),
+            timestamp: "ts".into(),
+            request: RequestData {
+                method: "GET".into(),
+                uri: "/scans?token=secret&page=2".into(),
+                headers: vec![
+                    ("authorization".into(), "Bearer key".into()),
+                    ("accept".into(), "*/*".into()),
+                ],
+                body: json!(""),
+            },
+            response: ResponseData {
+                status: Some(200),
+                headers: Some(vec![("Set-Cookie".into(), "session=1".into())]),
+                body: None,
+                error: None,
+            },
+            timings: None,
+            mirrors: Vec::new(),
+        };
+        payload(&redaction, &mut captured);
+        assert_eq!(captured.request.uri, "/scans?token=[REDACTED]&page=2");
+        assert_eq!(captured.request.headers[0].1, REDACTED);
+        assert_eq!(captured.request.headers[1].1, "*/*");
+        assert_eq!(captured.response.headers.unwrap()[0].1, REDACTED);
+
+        let mut body = json!({
+            "id": "abc",
+            "credentials": [{"accessKey": "k", "host": "ge"}],
+            "Token": {"nested": true},
+        });
+        assert!(json(&redaction, &mut body));
+        assert_eq!(
+            body,
+            json!({
+                "id": "abc",
+                "credentials": [{"accessKey": REDACTED, "host": "ge"}],
+                "Token": REDACTED,
+            })
+        );
+        assert!(!json(&redaction, &mut json!([1, {"id": "x"}])));
+    }
+}
@@ -0,0 +1,36 @@
+//! When delivering a request again is worth it, and how long to wait first.
+
+use std::time::Duration;
+
+/// Whether a response with `status` may go differently if the request is sent again.
+pub fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Wait after the `attempt`th failed attempt: `first`, doubled for each attempt after
+/// that, up to `max`.
+pub fn backoff(first: Duration, max: Duration, attempt: u32) -> Duration {
+    first
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_and_retryable() {
+        let (first, max) = (Duration::from_secs(1), Duration::from_secs(60));
+        assert_eq!(backoff(first, max, 1), Duration::from_secs(1));
+        assert_eq!(backoff(first, max, 3), Duration::from_secs(4));
+        assert_eq!(backoff(first, max, 40), max);
+
+        assert!(is_retryable(reqwest::StatusCode::BAD_GATEWAY));
+        assert!(is_retryable(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable(reqwest::StatusCode::UNAUTHORIZED));
+        assert!(!is_retryable(reqwest::StatusCode::OK));
+    }
+}
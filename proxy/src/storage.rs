@@ -0,0 +1,549 @@
+//! Where captures live on disk: the directory layout, compression, the index of
+//! captures, and retention.
+//!
+//! A capture is a group of files sharing a stem (`<timestamp>-<request id>`): the
+//! exchange itself plus the parsed scan or parse report saved beside uploads. Retention
+//! deletes whole groups, oldest first.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use config::{CaptureFormat, Compression, Layout, Storage};
+use flate2::write::GzEncoder;
+use format::compression::{self, Codec};
+use format::{BUILD_SCAN_SUFFIX, CAPTURE_SUFFIXES, HAR_SUFFIX, INDEX_FILE, PAYLOAD_SUFFIX};
+use models::BuildScanPayload;
+use tracing::{error, info, warn};
+
+/// Lists every capture in `INDEX_FILE`, one tab-separated line each, under this header.
+const INDEX_HEADER: &str = "timestamp\trequest_id\tmethod\tstatus\tsize\tfile\turi\n";
+
+/// How often retention limits are enforced.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub struct Store {
+    root: PathBuf,
+    config: Storage,
+    /// Held while the index is appended to or rewritten.
+    index: Mutex<()>,
+}
+
+/// A line of the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry<'a> {
+    pub timestamp: &'a str,
+    pub request_id: &'a str,
+    pub method: &'a str,
+    pub status: Option<u16>,
+    /// Size of the capture file on disk.
+    pub size: u64,
+    pub file: &'a Path,
+    pub uri: &'a str,
+}
+
+/// What a retention pass deleted.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Pruned {
+    pub captures: usize,
+    pub bytes: u64,
+}
+
+impl Store {
+    pub fn new(root: PathBuf, config: Storage) -> Self {
+        Self {
+            root,
+            config,
+            index: Mutex::new(()),
+        }
+    }
+
+    /// The directory for a capture taken at `timestamp` (`%Y%m%d_%H%M%S%.3f`).
+    pub fn dir(&self, timestamp: &str) -> PathBuf {
+        match (self.config.layout, timestamp.get(..8)) {
+            (Layout::Daily, Some(day)) if day.bytes().all(|b| b.is_ascii_digit()) => self
+                .root
+                .join(format!("{}-{}-{}", &day[..4], &day[4..6], &day[6..])),
+            _ => self.root.clone(),
+        }
+    }
+
+    /// `<stem><suffix>`, plus `.gz` or `.zst` when compressing.
+    pub fn file_name(&self, stem: &str, suffix: &str) -> String {
+        let extension = self.codec().map_or("", Codec::extension);
+        format!("{stem}{suffix}{extension}")
+    }
+
+    fn codec(&self) -> Option<Codec> {
+        match self.config.compression {
+            Compression::None => None,
+            Compression::Gzip => Some(Codec::Gzip),
+            Compression::Zstd => Some(Codec::Zstd),
+        }
+    }
+
+    pub fn format(&self) -> CaptureFormat {
+        self.config.format
+    }
+
+    /// Suffix of the exchange's own file, by capture format.
+    pub fn capture_suffix(&self) -> &'static str {
+        match self.format() {
+            CaptureFormat::Payload => PAYLOAD_SUFFIX,
+            CaptureFormat::Har => HAR_SUFFIX,
+        }
+    }
+
+    /// Creates a capture file, compressed as configured.
+    pub fn create(&self, path: &Path) -> io::Result<Output> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(match self.config.compression {
+            Compression::None => Output::Plain(file),
+            Compression::Gzip => Output::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+            Compression::Zstd => Output::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    /// Adds a written capture to the index.
+    pub fn record(&self, entry: &IndexEntry) -> io::Result<()> {
+        if self.config.no_index {
+            return Ok(());
+        }
+        let file = entry.file.strip_prefix(&self.root).unwrap_or(entry.file);
+        let line = [
+            entry.timestamp.to_string(),
+            entry.request_id.to_string(),
+            entry.method.to_string(),
+            entry.status.map_or("-".to_string(), |s| s.to_string()),
+            entry.size.to_string(),
+            file.display().to_string(),
+            entry.uri.to_string(),
+        ]
+        .map(|field| field.replace(['\t', '\n', '\r'], " "))
+        .join("\t");
+
+        let _guard = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let path = self.root.join(INDEX_FILE);
+        let mut index = OpenOptions::new().create(true).append(true).open(&path)?;
+        if index.metadata()?.len() == 0 {
+            index.write_all(INDEX_HEADER.as_bytes())?;
+        }
+        index.write_all(format!("{line}\n").as_bytes())
+    }
+
+    /// Enforces retention every [`PRUNE_INTERVAL`], starting now, if any limit is set.
+    pub fn spawn_retention(self: &Arc<Self>) {
+        if !self.config.retains() {
+            return;
+        }
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let pass = store.clone();
+                match tokio::task::spawn_blocking(move || pass.prune(SystemTime::now())).await {
+                    Ok(Ok(pruned)) if pruned.captures > 0 => info!(
+                        "Deleted {} captures ({} bytes) past the retention limits",
+                        pruned.captures, pruned.bytes
+                    ),
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => error!("Failed to enforce capture retention: {}", e),
+                    Err(e) => error!("Capture retention task failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Deletes captures older than the age limit, then the oldest ones until the rest fit
+    /// the size limit, and drops them from the index.
+    pub fn prune(&self, now: SystemTime) -> io::Result<Pruned> {
+        let mut captures = self.captures()?;
+        captures.sort_by(|a, b| a.modified.cmp(&b.modified).then(a.files.cmp(&b.files)));
+
+        let expired = captures.iter().take_while(|capture| {
+            self.config.max_age.is_some_and(|max_age| {
+                now.duration_since(capture.modified)
+                    .is_ok_and(|age| age > max_age)
+            })
+        });
+        let mut remove = expired.count();
+        if let Some(max_total_size) = self.config.max_total_size {
+            let mut total: u64 = captures[remove..].iter().map(|c| c.size).sum();
+            while total > max_total_size && remove < captures.len() {
+                total -= captures[remove].size;
+                remove += 1;
+            }
+        }
+        if remove == 0 {
+            return Ok(Pruned::default());
+        }
+
+        let _guard = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let mut pruned = Pruned::default();
+        let mut removed = HashSet::new();
+        let mut dirs = HashSet::new();
+        for capture in &captures[..remove] {
+            for file in &capture.files {
+                match std::fs::remove_file(file) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+                removed.insert(file.strip_prefix(&self.root).unwrap_or(file).to_path_buf());
+                if let Some(dir) = file.parent().filter(|dir| *dir != self.root) {
+                    dirs.insert(dir.to_path_buf());
+                }
+            }
+            pruned.captures += 1;
+            pruned.bytes += capture.size;
+        }
+        // Day directories left empty go too, except today's, which captures are about to
+        // be written to; directories still in use refuse.
+        let today = self.dir(&chrono::Utc::now().format("%Y%m%d").to_string());
+        for dir in dirs.into_iter().filter(|dir| *dir != today) {
+            let _ = std::fs::remove_dir(dir);
+        }
+        self.drop_from_index(&removed)?;
+        Ok(pruned)
+    }
+
+    /// Reads the parsed scans saved beside uploads, with their capture stems. Ones that
+    /// can't be read are logged and left out.
+    pub fn build_scans(&self) -> io::Result<Vec<(String, BuildScanPayload)>> {
+        let mut scans = Vec::new();
+        for file in self
+            .captures()?
+            .into_iter()
+            .flat_map(|capture| capture.files)
+        {
+            let Some(name) = file.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(stem) = compression::split(name).0.strip_suffix(BUILD_SCAN_SUFFIX) else {
+                continue;
+            };
+            let read = compression::read(&file)
+                .and_then(|json| Ok(serde_json::from_slice::<BuildScanPayload>(&json)?));
+            match read {
+                Ok(scan) => scans.push((stem.to_string(), scan)),
+                Err(e) => warn!("Failed to read parsed scan {:?}: {}", file, e),
+            }
+        }
+        Ok(scans)
+    }
+
+    /// Groups the files under the payload directory into captures by stem.
+    fn captures(&self) -> io::Result<Vec<StoredCapture>> {
+        let mut captures: BTreeMap<PathBuf, StoredCapture> = BTreeMap::new();
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            for entry in entries {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                let path = entry.path();
+                if metadata.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let Some(stem) = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(capture_stem)
+                else {
+                    continue;
+                };
+                let capture = captures
+                    .entry(dir.join(stem))
+                    .or_insert_with(|| StoredCapture {
+                        files: Vec::new(),
+                        size: 0,
+                        modified: SystemTime::UNIX_EPOCH,
+                    });
+                capture.size += metadata.len();
+                capture.modified = capture.modified.max(metadata.modified()?);
+                capture.files.push(path);
+            }
+        }
+        Ok(captures.into_values().collect())
+    }
+
+    fn drop_from_index(&self, removed: &HashSet<PathBuf>) -> io::Result<()> {
+        let path = self.root.join(INDEX_FILE);
+        let index = match File::open(&path) {
+            Ok(index) => index,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let rewritten = path.with_extension("tsv.tmp");
+        let mut out = BufWriter::new(File::create(&rewritten)?);
+        for line in BufReader::new(index).lines() {
+            let line = line?;
+            let file = line.split('\t').nth(5).map(Path::new);
+            if !file.is_some_and(|file| removed.contains(file)) {
+                writeln!(out, "{line}")?;
+            }
+        }
+        out.flush()?;
+        std::fs::rename(rewritten, path)
+    }
+}
+
+#[derive(Debug)]
+struct StoredCapture {
+    files: Vec<PathBuf>,
+    size: u64,
+    /// Of the most recently written file.
+    modified: SystemTime,
+}
+
+/// The stem of a capture file name, or `None` for other files (spools, the index).
+fn capture_stem(name: &str) -> Option<&str> {
+    let name = compression::split(name).0;
+    CAPTURE_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .filter(|stem| !stem.is_empty())
+}
+
+/// A capture file being written.
+pub enum Output {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl Output {
+    /// Completes the file; dropping an `Output` instead may lose buffered data.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Plain(mut file) => file.flush(),
+            Output::Gzip(gzip) => gzip.finish()?.flush(),
+            Output::Zstd(zstd) => zstd.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(file) => file.write(buf),
+            Output::Gzip(gzip) => gzip.write(buf),
+            Output::Zstd(zstd) => zstd.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(file) => file.flush(),
+            Output::Gzip(gzip) => gzip.flush(),
+            Output::Zstd(zstd) => zstd.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+    use std::io::Read;
+
+    fn write(path: &Path, len: usize, age: Duration) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![b'x'; len]).unwrap();
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[test]
+    fn test_layout_and_names() {
+        let root = PathBuf::from("/captures");
+        let flat = Store::new(root.clone(), Storage::default());
+        assert_eq!(flat.dir("20261018_204514.959"), root);
+        assert_eq!(flat.file_name("stem", ".json"), "stem.json");
+
+        let daily = Store::new(
+            root.clone(),
+            Storage {
+                layout: Layout::Daily,
+                compression: Compression::Gzip,
+                ..Default::default()
+            },
+        );
+        assert_eq!(daily.dir("20261018_204514.959"), root.join("2026-10-18"));
+        assert_eq!(daily.dir("bogus"), root);
+        assert_eq!(
+            daily.file_name("stem", ".build-scan.json"),
+            "stem.build-scan.json.gz"
+        );
+
+        assert_eq!(capture_stem("a-1.json"), Some("a-1"));
+        assert_eq!(capture_stem("a-1.parse-report.json.gz"), Some("a-1"));
+        assert_eq!(capture_stem("a-1.har"), Some("a-1"));
+        assert_eq!(capture_stem("a-1.har.zst"), Some("a-1"));
+        assert_eq!(capture_stem("a-1.request.part"), None);
+        assert_eq!(capture_stem(INDEX_FILE), None);
+    }
+
+    #[test]
+    fn test_gzip_output() {
+        let dir = test_dir("gzip");
+        let store = Store::new(
+            dir.clone(),
+            Storage {
+                compression: Compression::Gzip,
+                ..Default::default()
+            },
+        );
+        let path = dir.join("capture.json.gz");
+        let mut out = store.create(&path).unwrap();
+        out.write_all(b"{\"request_id\": \"id\"}").unwrap();
+        out.finish().unwrap();
+
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(File::open(&path).unwrap())
+            .read_to_string(&mut json)
+            .unwrap();
+        assert_eq!(json, "{\"request_id\": \"id\"}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_zstd_output() {
+        let dir = test_dir("zstd");
+        let store = Store::new(
+            dir.clone(),
+            Storage {
+                compression: Compression::Zstd,
+                ..Default::default()
+            },
+        );
+        assert_eq!(store.file_name("capture", ".json"), "capture.json.zst");
+        let path = dir.join("capture.json.zst");
+        let mut out = store.create(&path).unwrap();
+        out.write_all(b"{\"request_id\": \"id\"}").unwrap();
+        out.finish().unwrap();
+
+        let json = compression::read(&path).unwrap();
+        assert_eq!(json, b"{\"request_id\": \"id\"}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_by_age_and_size() {
+        let dir = test_dir("prune");
+        let store = Store::new(
+            dir.clone(),
+            Storage {
+                layout: Layout::Daily,
+                max_total_size: Some(250),
+                max_age: Some(Duration::from_secs(24 * 60 * 60)),
+                ..Default::default()
+            },
+        );
+        let day = Duration::from_secs(24 * 60 * 60);
+        let minute = Duration::from_secs(60);
+        let captures = [
+            ("2026-10-15/old.json", 10, 3 * day),
+            ("2026-10-17/a.json", 100, 3 * minute),
+            ("2026-10-17/a.build-scan.json", 50, 3 * minute),
+            ("2026-10-18/b.json", 100, 2 * minute),
+            ("2026-10-18/c.json.gz", 100, minute),
+        ];
+        for (file, len, age) in captures {
+            write(&dir.join(file), len, age);
+            store
+                .record(&IndexEntry {
+                    timestamp: "ts",
+                    request_id: file,
+                    method: "POST",
+                    status: Some(200),
+                    size: len as u64,
+                    file: &dir.join(file),
+                    uri: "/scans\tx",
+                })
+                .unwrap();
+        }
+        write(&dir.join("2026-10-18/d.request.part"), 1000, 2 * day);
+
+        assert_eq!(
+            store.prune(SystemTime::now()).unwrap(),
+            Pruned {
+                captures: 2,
+                bytes: 160
+            }
+        );
+        assert!(!dir.join("2026-10-15").exists());
+        assert!(!dir.join("2026-10-17").exists());
+        assert!(dir.join("2026-10-18/b.json").exists());
+        assert!(dir.join("2026-10-18/d.request.part").exists());
+
+        let index = std::fs::read_to_string(dir.join(INDEX_FILE)).unwrap();
+        let lines: Vec<&str> = index.lines().collect();
+        assert_eq!(lines[0], INDEX_HEADER.trim_end());
+        assert_eq!(
+            &lines[1..],
+            [
+                "ts\t2026-10-18/b.json\tPOST\t200\t100\t2026-10-18/b.json\t/scans x",
+                "ts\t2026-10-18/c.json.gz\tPOST\t200\t100\t2026-10-18/c.json.gz\t/scans x",
+            ]
+        );
+        assert_eq!(store.prune(SystemTime::now()).unwrap(), Pruned::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_scans() {
+        let dir = test_dir("build-scans");
+        let store = Store::new(
+            dir.clone(),
+            Storage {
+                layout: Layout::Daily,
+                compression: Compression::Gzip,
+                ..Default::default()
+            },
+        );
+        let scan = BuildScanPayload {
+            tasks: vec![models::Task {
+                id: 1,
+                build_path: ":".into(),
+                task_path: ":app:test".into(),
+                class_name: None,
+                outcome: None,
+                cacheable: None,
+                caching_disabled_reason: None,
+                caching_disabled_explanation: None,
+                origin_build_cache_key: None,
+                actionable: None,
+                started_at: None,
+                finished_at: None,
+                duration_ms: Some(100),
+                inputs: None,
+            }],
+            ..Default::default()
+        };
+        std::fs::create_dir_all(dir.join("2026-10-18")).unwrap();
+        let mut out = store
+            .create(&dir.join("2026-10-18/a.build-scan.json.gz"))
+            .unwrap();
+        serde_json::to_writer(&mut out, &scan).unwrap();
+        out.finish().unwrap();
+        write(&dir.join("2026-10-18/a.json.gz"), 10, Duration::ZERO);
+        write(&dir.join("b.build-scan.json"), 10, Duration::ZERO);
+
+        let scans = store.build_scans().unwrap();
+        assert_eq!(scans.len(), 1, "unreadable scans are left out");
+        assert_eq!(scans[0].0, "a");
+        assert_eq!(&*scans[0].1.tasks[0].task_path, ":app:test");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+//! Fixtures shared by the proxy's tests: scratch directories and throwaway upstreams.
+
+use axum::http::{HeaderMap, StatusCode};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// An empty directory for the test `name`, left over from no earlier run.
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("proxy-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Serves `app` on a free local port, returning its base URL.
+pub async fn serve(app: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+/// A base URL nothing listens on: a port just given up.
+pub async fn unreachable_url() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    url
+}
+
+/// An upstream started by [`serve_statuses`].
+pub struct Upstream {
+    pub url: String,
+    pub calls: Arc<AtomicU32>,
+    /// The URI and headers of each request, in the order they came.
+    pub requests: Arc<Mutex<Vec<(String, HeaderMap)>>>,
+}
+
+impl Upstream {
+    pub fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+/// Serves `statuses` in turn, then the last one forever, recording each request.
+pub async fn serve_statuses(statuses: Vec<u16>) -> Upstream {
+    let calls = Arc::new(AtomicU32::new(0));
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let (handler_calls, handler_requests) = (calls.clone(), requests.clone());
+    let app = axum::Router::new().fallback(move |request: axum::extract::Request| {
+        let calls = handler_calls.clone();
+        let requests = handler_requests.clone();
+        let statuses = statuses.clone();
+        async move {
+            let call = calls.fetch_add(1, Ordering::SeqCst) as usize;
+            requests
+                .lock()
+                .unwrap()
+                .push((request.uri().to_string(), request.headers().clone()));
+            StatusCode::from_u16(statuses[call.min(statuses.len() - 1)]).unwrap()
+        }
+    });
+    Upstream {
+        url: serve(app).await,
+        calls,
+        requests,
+    }
+}